@@ -15,12 +15,8 @@ impl Allocate for ir::Type {
         match self.kind() {
             Int32 => 4,
             Unit => 0,
-            Array(_, _) => unimplemented!("Future"),
-            Pointer(t) => match t.kind() {
-                Int32 => 4,
-                Unit => unreachable!(),
-                _ => unimplemented!("Future"),
-            },
+            Array(base, len) => base.allocate() * *len as i32,
+            Pointer(_) => 4,
             Function(_, _) => unimplemented!("Function size unknown"),
         }
     }
@@ -32,7 +28,12 @@ impl Allocate for ir::entities::ValueData {
         use ir::ValueKind::*;
         let ty_size = self.ty().allocate();
         match self.kind() {
-            Alloc(_) | Binary(_) | Call(_) => ty_size,
+            // 局部变量的大小为所指向的类型的大小
+            Alloc(_) => match self.ty().kind() {
+                ir::TypeKind::Pointer(base) => base.allocate(),
+                _ => unreachable!(),
+            },
+            Binary(_) | Call(_) | BlockArgRef(_) | GetPtr(_) | GetElemPtr(_) => ty_size,
             _ => 0,
         }
     }
@@ -1,9 +1,13 @@
-use std::cell::{Ref, RefCell, RefMut};
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    collections::HashSet,
+};
 
-use crate::WrapProgram;
+use crate::{util::intrinsic::Intrinsic, WrapProgram};
 use koopa::ir;
 
 use super::{
+    TargetOptions,
     risc::{RiscInst, RiscReg as Reg, MAX_IMM, RiscLabel},
     memory::{stack::StackMap, regmap::RegMap},
 };
@@ -14,8 +18,11 @@ pub struct Context<'a> {
     stack: &'a mut RefCell<StackMap>,
     func: ir::Function,
     is_leaf: RefCell<Option<bool>>,
+    /// 返回 64 位值的调用，见 [`Context::is_wide_call`]
+    wide_calls: HashSet<ir::Value>,
     reg_map: RefCell<RegMap>,
     // stack_map: RefCell<FrameMap>,
+    pub opts: TargetOptions,
 }
 
 impl<'a> WrapProgram for Context<'a> {
@@ -42,16 +49,19 @@ macro_rules! frame {
 }
 
 impl<'a> Context<'a> {
-    pub fn new(program: &'a mut ir::Program, stack: &'a mut RefCell<StackMap>, func: ir::Function) -> Context<'a> {
+    pub fn new(program: &'a mut ir::Program, stack: &'a mut RefCell<StackMap>, func: ir::Function, opts: TargetOptions) -> Context<'a> {
         let name = unsafe { program.func(func).name().get_unchecked(1..) }.into();
+        let wide_calls = Self::wide_calls(program, func);
         Context {
             program,
             name,
             stack,
             func,
             is_leaf: RefCell::new(None),
+            wide_calls,
             reg_map: RefCell::new(RegMap::new()),
             // stack_map: RefCell::new(FrameMap::new()),
+            opts,
         }
     }
 
@@ -98,6 +108,40 @@ impl<'a> Context<'a> {
         &self.name
     }
 
+    /// 若为对内部函数的调用，返回该内部函数
+    pub fn intrinsic(&self, call: &ir::values::Call) -> Option<Intrinsic> {
+        Intrinsic::from_name(self.func(call.callee()).name())
+    }
+
+    /// 若为对需要内联展开的内部函数的调用，返回该内部函数
+    pub fn inline_intrinsic(&self, call: &ir::values::Call) -> Option<Intrinsic> {
+        self.intrinsic(call).filter(|i| i.is_inline(self.opts.rv32f))
+    }
+
+    /// 调用是否返回 64 位值，即其结果被 [`Intrinsic::Hi`] 取高位字
+    pub fn is_wide_call(&self, val: ir::Value) -> bool {
+        self.wide_calls.contains(&val)
+    }
+
+    /// 找出函数中被 [`Intrinsic::Hi`] 取高位字的调用
+    ///
+    /// `ValueData` 的 clone 不带 `used_by`，改写过的值也不一定带有正确的 `used_by`，须扫描布局中的指令；数据流图中还可能留有不在布局中的死值。
+    fn wide_calls(program: &ir::Program, func: ir::Function) -> HashSet<ir::Value> {
+        use ir::ValueKind;
+        let data = program.func(func);
+        let mut wide = HashSet::new();
+        for (_bb, node) in data.layout().bbs() {
+            for &inst in node.insts().keys() {
+                if let ValueKind::Call(c) = data.dfg().value(inst).kind() {
+                    if Intrinsic::from_name(program.func(c.callee()).name()) == Some(Intrinsic::Hi) {
+                        wide.extend(c.args().first());
+                    }
+                }
+            }
+        }
+        wide
+    }
+
     pub fn label<T>(&self, ir_name: T) -> RiscLabel
     where T: ToString
     {
@@ -116,9 +160,13 @@ impl<'a> Context<'a> {
         }
 
         // 统计所有函数调用的参数长度，每个参数
+        // 内联展开的内部函数不是真正的调用
         let calls: Vec<_> = ir_insts
             .iter()
-            .filter(|i| matches!(self.value(**i).kind(), ValueKind::Call(..)))
+            .filter(|i| match self.value(**i).kind() {
+                ValueKind::Call(c) => self.inline_intrinsic(c).is_none(),
+                _ => false,
+            })
             .map(|i| {
                 if let ValueKind::Call(call) = self.value(*i).kind() {
                     let args = call.args();
@@ -142,21 +190,39 @@ impl<'a> Context<'a> {
         // 此处在本栈帧中插入一个**不实际分配空间的**，指向前一栈帧的偏移量 Allocate::Prev
         //   键：参数
         //   值：偏移量 Allocate::Prev
+        // 前 8 个参数在 `a0`–`a7` 中，若在可能覆盖这些寄存器的调用之后仍被使用，则在序言中存入栈上
         let params = self.this_func().params();
+        let spilled = self.clobbered_params();
         params.iter().enumerate().for_each(|(i, h)| {
             if i >= 8 {
                 // ? 简单规定所有参数空间都是 4
                 frame!(self._mut).insert_prev(*h, &4);
+            } else if spilled.contains(h) {
+                frame!(self._mut).insert_high(*h, &4);
             } else {
                 self.reg_map_mut().appoint_reg(*h, Reg::A(i.try_into().unwrap()));
             }
         });
 
         // 分配局部变量
+        // 返回 64 位值的调用占两个字
         ir_insts.iter().for_each(|h| {
             let d = self.value(*h);
-            frame!(self._mut).insert_high(*h, &d);
+            if self.is_wide_call(*h) {
+                frame!(self._mut).insert_high(*h, &8);
+            } else {
+                frame!(self._mut).insert_high(*h, &d);
+            }
         });
+        for h in self.stale_loads() {
+            frame!(self._mut).insert_high(h, &4);
+        }
+        // 基本块的参数由跳转前的复制写入
+        for bb in self.this_func().layout().bbs().keys() {
+            for p in self.bb(*bb).params() {
+                frame!(self._mut).insert_high(*p, &self.value(*p));
+            }
+        }
 
         // 分配额外的用于本函数调用**其他函数**传参所需的空间
         // 寄存器上最多存储 8 个 i32，多余的参数要想传给下一个函数，需要存在本栈帧的底部
@@ -183,10 +249,122 @@ impl<'a> Context<'a> {
             v.push(Sw(Reg::Ra, frame!(self).get(Reg::Ra), Reg::Sp));
         }
 
+        // 保存之后仍要使用的参数
+        for (i, h) in params.iter().enumerate().take(8) {
+            if spilled.contains(h) {
+                v.push(Sw(Reg::A(i.try_into().unwrap()), frame!(self).get(*h), Reg::Sp));
+            }
+        }
+
         v
     }
 
-    pub fn epilogue(&self) -> Vec<RiscInst> {
+    /// 在调用之后或入口基本块之外使用的参数
+    ///
+    /// 调用（包括传参和内联展开的内部函数）会覆盖 `a0`–`a7`。前端生成的代码在入口处就将参数存入局部变量，
+    /// 经过优化后参数则可能在任何地方使用。
+    fn clobbered_params(&self) -> HashSet<ir::Value> {
+        use ir::ValueKind;
+        let func = self.this_func();
+        let params: HashSet<ir::Value> = func.params().iter().copied().collect();
+        let mut spilled = HashSet::new();
+        let mut clobbered = false;
+        for (bb, node) in func.layout().bbs() {
+            clobbered |= Some(*bb) != func.layout().entry_bb();
+            for inst in node.insts().keys() {
+                let kind = func.dfg().value(*inst).kind();
+                clobbered |= matches!(kind, ValueKind::Call(_));
+                if clobbered {
+                    spilled.extend(kind.value_uses().filter(|v| params.contains(v)));
+                }
+            }
+        }
+        spilled
+    }
+
+    /// 读取之后、某次使用之前内存可能已被改变的 `load`
+    ///
+    /// `load` 本身不生成代码，使用时才从内存读取。前端生成的代码读取后随即使用；经过优化后，
+    /// 读取的值可能在写入或调用之后、或在其他基本块中使用，须在读取处存入栈上。
+    fn stale_loads(&self) -> HashSet<ir::Value> {
+        use ir::ValueKind;
+        let func = self.this_func();
+        let is_load = |v: ir::Value| !v.is_global() && matches!(func.dfg().value(v).kind(), ValueKind::Load(_));
+        let mut stale = HashSet::new();
+        for node in func.layout().bbs().nodes() {
+            // 本基本块中读取后还没有经过写入或调用的 `load`
+            let mut fresh = HashSet::new();
+            for inst in node.insts().keys() {
+                let kind = func.dfg().value(*inst).kind();
+                stale.extend(kind.value_uses().filter(|v| is_load(*v) && !fresh.contains(v)));
+                match kind {
+                    ValueKind::Load(_) => {
+                        fresh.insert(*inst);
+                    }
+                    ValueKind::Store(_) | ValueKind::Call(_) => fresh.clear(),
+                    _ => {}
+                }
+            }
+        }
+        stale
+    }
+
+    /// 同一基本块中的下一条指令
+    fn next_inst(&self, val: ir::Value) -> Option<ir::Value> {
+        let layout = self.this_func().layout();
+        let bb = layout.parent_bb(val)?;
+        layout.bbs().node(&bb)?.insts().cursor(val).next_key().copied()
+    }
+
+    /// 同一基本块中的上一条指令
+    pub fn prev_inst(&self, val: ir::Value) -> Option<ir::Value> {
+        let layout = self.this_func().layout();
+        let bb = layout.parent_bb(val)?;
+        layout.bbs().node(&bb)?.insts().cursor(val).prev_key().copied()
+    }
+
+    /// 是否为可以生成为尾调用的调用
+    ///
+    /// 调用之后紧接着返回其结果（或不返回值）时，可以先拆除本函数的栈帧，再跳转到被调用者，由它直接返回到本函数的调用者。
+    /// 被调用者在栈上的参数写入本函数在栈上的参数的位置，须放得下；实参为本函数在栈上的另一个参数时，
+    /// 读取之前可能已被覆盖，也不作为尾调用。局部变量的地址传出过时，被调用者可能经由它访问已拆除的栈帧，
+    /// 本函数中的调用都不作为尾调用。
+    pub fn is_tail_call(&self, val: ir::Value) -> bool {
+        use ir::ValueKind;
+        let dfg = self.this_func().dfg();
+        let ValueKind::Call(call) = dfg.value(val).kind() else {
+            return false;
+        };
+        if self.inline_intrinsic(call).is_some() || self.is_wide_call(val) {
+            return false;
+        }
+        let returns = match self.next_inst(val).map(|ret| dfg.value(ret).kind()) {
+            Some(ValueKind::Return(r)) => r.value().is_none_or(|v| v == val),
+            _ => false,
+        };
+        let params = self.this_func().params();
+        let fits = call.args().iter().enumerate().skip(8).all(|(i, arg)| {
+            i < params.len() && params.iter().position(|p| p == arg).is_none_or(|j| j < 8 || j == i)
+        });
+        returns && fits && !self.frame_escapes()
+    }
+
+    /// 是否有局部变量的地址传出，即 `alloc` 在 `load` 与 `store` 的地址之外被使用
+    fn frame_escapes(&self) -> bool {
+        use ir::ValueKind;
+        let dfg = self.this_func().dfg();
+        let is_alloc = |v: ir::Value| !v.is_global() && matches!(dfg.value(v).kind(), ValueKind::Alloc(_));
+        self.this_func().layout().bbs().nodes().flat_map(|node| node.insts().keys()).any(|&inst| {
+            match dfg.value(inst).kind() {
+                ValueKind::Load(_) => false,
+                ValueKind::Store(s) => is_alloc(s.value()),
+                kind => kind.value_uses().any(is_alloc),
+            }
+        })
+    }
+
+    /// 恢复 `ra` 与栈指针，拆除本函数的栈帧
+    pub fn teardown(&self) -> Vec<RiscInst> {
         use RiscInst::*;
         let mut v = vec![];
 
@@ -204,8 +382,12 @@ impl<'a> Context<'a> {
             v.push(Addi(Reg::Sp, Reg::Sp, size))
         }
 
-        v.push(Ret);
+        v
+    }
 
+    pub fn epilogue(&self) -> Vec<RiscInst> {
+        let mut v = self.teardown();
+        v.push(RiscInst::Ret);
         v
     }
 }
@@ -0,0 +1,45 @@
+use koopa::ir::{builder_traits::*, FunctionData, ValueKind};
+
+use crate::opt::edit;
+
+/// 拆分带实参的 `br` 的边
+///
+/// 实参在跳转之前复制到目标基本块的参数中，而 `br` 的两个目标只有一个会执行，
+/// 复制不能放在 `br` 之前。对带实参的每个目标，在其间插入一个只含 `jump` 的基本块，
+/// 由它传递实参；此后只有 `jump` 带有实参。插入的基本块紧接在 `br` 所在的基本块之后，
+/// 名字为该基本块的名字加上 `_true` 或 `_false`，重名时再加编号。
+pub fn split_arg_edges(func: &mut FunctionData) {
+    let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+    let mut namer = edit::Namer::new(func);
+    for bb in bbs {
+        let Some(&last) = func.layout().bbs().node(&bb).unwrap().insts().back_key() else {
+            continue;
+        };
+        let ValueKind::Branch(br) = func.dfg().value(last).kind().clone() else {
+            continue;
+        };
+        let mut split = |target, args: &[_], suffix| {
+            if args.is_empty() {
+                return target;
+            }
+            let name = namer.derive(func, bb, suffix);
+            let edge = func.dfg_mut().new_bb().basic_block(name);
+            let jump = func.dfg_mut().new_value().jump_with_args(target, args.to_vec());
+            func.layout_mut().bbs_mut().cursor_mut(bb).insert_key_after(edge).unwrap();
+            func.layout_mut().bb_mut(edge).insts_mut().push_key_back(jump).unwrap();
+            edge
+        };
+        // 先插入 `_false`，`_true` 插在其前
+        let false_bb = split(br.false_bb(), br.false_args(), "false");
+        let true_bb = split(br.true_bb(), br.true_args(), "true");
+        if true_bb != br.true_bb() || false_bb != br.false_bb() {
+            edit::rewrite(func, last, |kind| {
+                let ValueKind::Branch(b) = kind else { unreachable!() };
+                *b.true_bb_mut() = true_bb;
+                *b.false_bb_mut() = false_bb;
+                b.true_args_mut().clear();
+                b.false_args_mut().clear();
+            });
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::{collections::{HashMap, HashSet}, hash::Hash};
+
+use koopa::ir::{self, BasicBlock, ValueKind};
+
+use crate::back::{
+    risc::{RiscInst as Inst, RiscReg as Reg},
+    Context,
+};
+use crate::{frame, WrapProgram};
+
+use super::ToReg;
+
+/// 将跳转的实参复制到目标基本块的参数在栈上的空间
+///
+/// 所有复制同时发生：实参可能正是目标的参数（如循环中交换两个变量），
+/// 须按 [`sequentialize`] 排序，环上借 `t0` 暂存。
+pub fn generate(ctx: &Context, target: BasicBlock, args: &[ir::Value]) -> Vec<Inst> {
+    let copies: Vec<_> = ctx
+        .bb(target)
+        .params()
+        .iter()
+        .zip(args)
+        .filter(|(_, arg)| !matches!(ctx.value(**arg).kind(), ValueKind::Undef(_)))
+        .map(|(&param, &arg)| (Some(param), Some(arg)))
+        .collect();
+    let mut v = vec![];
+    for (dst, src) in sequentialize(&copies, None) {
+        match (dst, src) {
+            (Some(dst), Some(src)) => {
+                let (reg, inst) = src.to_reg(ctx, None);
+                v.extend(inst);
+                v.push(Inst::Sw(reg, frame!(ctx).get(dst), Reg::Sp));
+            }
+            (None, Some(src)) => v.extend(src.to_reg(ctx, Some(Reg::T(0))).1),
+            (Some(dst), None) => v.push(Inst::Sw(Reg::T(0), frame!(ctx).get(dst), Reg::Sp)),
+            (None, None) => unreachable!(),
+        }
+    }
+    v
+}
+
+/// 将并行复制 `(dst, src)` 排成依次执行的复制，`tmp` 为打破环所用的暂存位置
+///
+/// 按 Boissinot 等的算法（“Revisiting Out-of-SSA Translation for Correctness, Code Quality, and Efficiency”）：
+/// 先进行目标不再被读取的复制；余下的复制成环，将环上的一个位置存入 `tmp` 即可继续。
+/// 各 `dst` 须互不相同。
+pub fn sequentialize<T: Copy + Eq + Hash>(copies: &[(T, T)], tmp: T) -> Vec<(T, T)> {
+    let mut out = vec![];
+    // 每个目标的来源，每个来源的值当前所在的位置
+    let mut pred: HashMap<T, T> = HashMap::new();
+    let mut loc: HashMap<T, T> = HashMap::new();
+    let mut todo = vec![];
+    for &(dst, src) in copies.iter().filter(|(dst, src)| dst != src) {
+        pred.insert(dst, src);
+        loc.insert(src, src);
+        todo.push(dst);
+    }
+    let mut ready: Vec<T> = todo.iter().copied().filter(|dst| !loc.contains_key(dst)).collect();
+    let mut done = HashSet::new();
+    todo.reverse();
+    loop {
+        while let Some(dst) = ready.pop() {
+            let src = pred[&dst];
+            let from = loc[&src];
+            out.push((dst, from));
+            done.insert(dst);
+            loc.insert(src, dst);
+            // 来源的值已有副本，来源本身可以被覆盖
+            if from == src && pred.contains_key(&src) && !done.contains(&src) {
+                ready.push(src);
+            }
+        }
+        let Some(dst) = todo.pop() else { break };
+        if !done.contains(&dst) {
+            out.push((tmp, dst));
+            loc.insert(dst, tmp);
+            ready.push(dst);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::sequentialize;
+
+    /// 依次执行复制，检查结果与同时执行相同
+    fn check(copies: &[(char, char)]) -> usize {
+        let seq = sequentialize(copies, '_');
+        let mut regs: HashMap<char, char> = ('a'..='z').map(|c| (c, c)).collect();
+        for &(dst, src) in seq.iter() {
+            let value = regs[&src];
+            regs.insert(dst, value);
+        }
+        for &(dst, src) in copies {
+            assert_eq!(regs[&dst], src, "{:?} -> {:?}", copies, seq);
+        }
+        seq.len()
+    }
+
+    #[test]
+    fn parallel_copies() {
+        assert_eq!(check(&[('a', 'b'), ('c', 'd')]), 2);
+        assert_eq!(check(&[('a', 'a')]), 0);
+        // 链：须先读后写
+        assert_eq!(check(&[('b', 'a'), ('c', 'b'), ('d', 'c')]), 3);
+        // 交换与三元环各需一次暂存
+        assert_eq!(check(&[('a', 'b'), ('b', 'a')]), 3);
+        assert_eq!(check(&[('a', 'b'), ('b', 'c'), ('c', 'a')]), 4);
+        // 一个值复制到多处，且带有环
+        assert_eq!(check(&[('a', 'b'), ('b', 'a'), ('c', 'a'), ('d', 'b')]), 4);
+    }
+}
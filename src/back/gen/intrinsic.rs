@@ -0,0 +1,258 @@
+use koopa::ir::{self, values::Call};
+
+use crate::back::{
+    risc::{RiscInst as Inst, RiscReg as Reg},
+    Context,
+};
+use crate::{frame, util::intrinsic::Intrinsic, WrapProgram};
+
+use super::ToReg;
+
+/// 将内联的内部函数调用展开为 RISC-V 指令，结果与 [`ir::BinaryOp`] 一样存入该值的栈上空间
+pub fn generate(intrinsic: Intrinsic, ctx: &Context, value: ir::Value, call: &Call) -> Vec<Inst> {
+    let mut v = vec![];
+    let args = call.args();
+    match intrinsic {
+        Intrinsic::Sltu | Intrinsic::Divu | Intrinsic::Remu | Intrinsic::Mulhu => {
+            let dreg = ctx.reg_map_mut().appoint_temp_reg(value);
+            let (lreg, linst) = args[0].to_reg(ctx, None);
+            let (rreg, rinst) = args[1].to_reg(ctx, None);
+            v.extend(linst);
+            v.extend(rinst);
+            v.push(match intrinsic {
+                Intrinsic::Sltu => Inst::Sltu(dreg, lreg, rreg),
+                Intrinsic::Divu => Inst::Divu(dreg, lreg, rreg),
+                Intrinsic::Remu => Inst::Remu(dreg, lreg, rreg),
+                Intrinsic::Mulhu => Inst::Mulhu(dreg, lreg, rreg),
+                _ => unreachable!(),
+            });
+            v.push(Inst::Sw(dreg, frame!(ctx).get(value), Reg::Sp));
+        }
+        Intrinsic::Hi => {
+            // 高位字在返回 64 位值的调用的槽中，紧邻低位字
+            let dreg = ctx.reg_map_mut().appoint_temp_reg(value);
+            v.push(Inst::Lw(dreg, frame!(ctx).get(args[0]) + 4, Reg::Sp));
+            v.push(Inst::Sw(dreg, frame!(ctx).get(value), Reg::Sp));
+        }
+        Intrinsic::SetHi => {
+            let (_, inst) = args[0].to_reg(ctx, Some(Reg::A(1)));
+            v.extend(inst);
+        }
+        _ if intrinsic.is_soft_float() => v.extend(soft_float(intrinsic, ctx, value, args)),
+        _ => unreachable!("not an inline intrinsic"),
+    }
+    v
+}
+
+/// 启用 F 扩展时，将软浮点函数展开为 RV32F 指令
+///
+/// 操作数的位模式经 `fmv.w.x` 移入 `ft0`、`ft1`，结果移回整数寄存器。
+/// 比较函数的结果须与 libgcc 的约定一致，即与 0 的大小关系表示比较的结果。
+fn soft_float(intrinsic: Intrinsic, ctx: &Context, value: ir::Value, args: &[ir::Value]) -> Vec<Inst> {
+    use Intrinsic::*;
+    let mut v = vec![];
+    let (f0, f1) = (Reg::Ft(0), Reg::Ft(1));
+    for (arg, freg) in args.iter().zip([f0, f1]) {
+        let (reg, inst) = arg.to_reg(ctx, None);
+        v.extend(inst);
+        v.push(match intrinsic {
+            Floatsisf => Inst::FcvtSW(freg, reg),
+            Floatunsisf => Inst::FcvtSWu(freg, reg),
+            _ => Inst::FmvWX(freg, reg),
+        });
+    }
+    let dreg = ctx.reg_map_mut().appoint_temp_reg(value);
+    match intrinsic {
+        Addsf3 | Subsf3 | Mulsf3 | Divsf3 | Floatsisf | Floatunsisf => {
+            v.extend(match intrinsic {
+                Addsf3 => Some(Inst::FaddS(f0, f0, f1)),
+                Subsf3 => Some(Inst::FsubS(f0, f0, f1)),
+                Mulsf3 => Some(Inst::FmulS(f0, f0, f1)),
+                Divsf3 => Some(Inst::FdivS(f0, f0, f1)),
+                _ => None,
+            });
+            v.push(Inst::FmvXW(dreg, f0));
+        }
+        Fixsfsi => v.push(Inst::FcvtWS(dreg, f0)),
+        Fixunssfsi => v.push(Inst::FcvtWuS(dreg, f0)),
+        // 相等时为 0，不等或无序时为 1
+        Eqsf2 | Nesf2 => v.extend([Inst::FeqS(dreg, f0, f1), Inst::Xori(dreg, dreg, 1)]),
+        // 小于时为 -1，否则为 0
+        Ltsf2 => v.extend([Inst::FltS(dreg, f0, f1), Inst::Sub(dreg, Reg::Zero, dreg)]),
+        // 小于等于时为 0，否则为 1
+        Lesf2 => v.extend([Inst::FleS(dreg, f0, f1), Inst::Xori(dreg, dreg, 1)]),
+        // 大于时为 1，否则为 0
+        Gtsf2 => v.push(Inst::FltS(dreg, f1, f0)),
+        // 大于等于时为 0，否则为 -1
+        Gesf2 => v.extend([Inst::FleS(dreg, f1, f0), Inst::Addi(dreg, dreg, -1)]),
+        _ => unreachable!(),
+    }
+    v.push(Inst::Sw(dreg, frame!(ctx).get(value), Reg::Sp));
+    v
+}
+
+#[cfg(test)]
+mod test {
+    use crate::back::{riscv_text, TargetOptions};
+
+    #[test]
+    fn wide_and_unsigned() {
+        // 无符号运算展开为对应的指令；`__divdi3` 的高位字在 `a1` 中返回，存入结果的槽之后，
+        // 由 `__sysy_hi` 读出；`__sysy_set_hi` 在 `ret` 之前放入 `a1`
+        let asm = riscv_text(
+            r#"
+decl @__sysy_sltu(i32, i32): i32
+decl @__sysy_divu(i32, i32): i32
+decl @__sysy_remu(i32, i32): i32
+decl @__sysy_mulhu(i32, i32): i32
+decl @__divdi3(i32, i32, i32, i32): i32
+decl @__sysy_hi(i32): i32
+decl @__sysy_set_hi(i32)
+
+fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = call @__sysy_sltu(@a, @b)
+  %1 = call @__sysy_divu(%0, @b)
+  %2 = call @__sysy_remu(%1, @b)
+  %3 = call @__sysy_mulhu(%2, @b)
+  %4 = call @__divdi3(@a, %3, %2, 0)
+  %5 = call @__sysy_hi(%4)
+  call @__sysy_set_hi(%5)
+  ret %4
+}
+"#,
+            TargetOptions::default(),
+        );
+        assert_eq!(
+            asm.trim(),
+            r#"
+  .text
+  .globl f
+f:
+  addi sp, sp, -48
+  sw ra, 44(sp)
+  sw a0, 40(sp)
+  sw a1, 36(sp)
+  lw t2, 40(sp)
+  lw t3, 36(sp)
+  sltu t1, t2, t3
+  sw t1, 32(sp)
+  lw t5, 32(sp)
+  lw t6, 36(sp)
+  divu t4, t5, t6
+  sw t4, 28(sp)
+  lw t2, 28(sp)
+  lw t3, 36(sp)
+  remu t1, t2, t3
+  sw t1, 24(sp)
+  lw t5, 24(sp)
+  lw t6, 36(sp)
+  mulhu t4, t5, t6
+  sw t4, 20(sp)
+  lw t1, 40(sp)
+  mv a0, t1
+  lw t2, 20(sp)
+  mv a1, t2
+  lw t3, 24(sp)
+  mv a2, t3
+  li t4, 0
+  mv a3, t4
+  call __divdi3
+  sw a0, 12(sp)
+  sw a1, 16(sp)
+  lw t5, 16(sp)
+  sw t5, 8(sp)
+  lw a1, 8(sp)
+  lw a0, 12(sp)
+  j f_end
+f_end:
+  lw ra, 44(sp)
+  addi sp, sp, 48
+  ret
+"#
+            .trim()
+        );
+    }
+
+    const FLOAT: &str = r#"
+decl @__addsf3(i32, i32): i32
+decl @__ltsf2(i32, i32): i32
+decl @__floatsisf(i32): i32
+decl @__fixsfsi(i32): i32
+
+fun @f(@x: i32, @n: i32): i32 {
+%entry:
+  %0 = call @__floatsisf(@n)
+  %1 = call @__addsf3(@x, %0)
+  %2 = call @__ltsf2(%1, @x)
+  %3 = lt %2, 0
+  %4 = call @__fixsfsi(%1)
+  %5 = add %3, %4
+  ret %5
+}
+"#;
+
+    #[test]
+    fn soft_float_calls() {
+        // 默认调用 libgcc 的软浮点函数
+        let asm = riscv_text(FLOAT, TargetOptions::from_march("rv32im"));
+        for func in ["__floatsisf", "__addsf3", "__ltsf2", "__fixsfsi"] {
+            assert!(asm.contains(&format!("call {func}\n")), "{func} is not called:\n{asm}");
+        }
+        assert!(!asm.contains("ft0"));
+    }
+
+    #[test]
+    fn rv32f() {
+        // 启用 F 扩展时展开为 RV32F 指令，不再调用函数，叶函数不必保存 `ra`；
+        // `__ltsf2` 的结果为 -1 或 0，转换为 `int` 向零舍入
+        let asm = riscv_text(FLOAT, TargetOptions::from_march("rv32imf"));
+        assert_eq!(
+            asm.trim(),
+            r#"
+  .text
+  .globl f
+f:
+  addi sp, sp, -32
+  sw a0, 28(sp)
+  sw a1, 24(sp)
+  lw t1, 24(sp)
+  fcvt.s.w ft0, t1
+  fmv.x.w t2, ft0
+  sw t2, 20(sp)
+  lw t3, 28(sp)
+  fmv.w.x ft0, t3
+  lw t4, 20(sp)
+  fmv.w.x ft1, t4
+  fadd.s ft0, ft0, ft1
+  fmv.x.w t5, ft0
+  sw t5, 16(sp)
+  lw t6, 16(sp)
+  fmv.w.x ft0, t6
+  lw t1, 28(sp)
+  fmv.w.x ft1, t1
+  flt.s t2, ft0, ft1
+  sub t2, zero, t2
+  sw t2, 12(sp)
+  lw t4, 12(sp)
+  li t5, 0
+  slt t3, t4, t5
+  sw t3, 8(sp)
+  lw t6, 16(sp)
+  fmv.w.x ft0, t6
+  fcvt.w.s t1, ft0, rtz
+  sw t1, 4(sp)
+  lw t3, 8(sp)
+  lw t4, 4(sp)
+  add t2, t3, t4
+  sw t2, 0(sp)
+  lw a0, 0(sp)
+  j f_end
+f_end:
+  addi sp, sp, 32
+  ret
+"#
+            .trim()
+        );
+    }
+}
@@ -3,9 +3,13 @@ use super::risc;
 use koopa::ir;
 
 use crate::WrapProgram;
-use crate::back::risc::RiscLabel;
+use crate::back::allocate::Allocate;
+use crate::back::risc::{RiscLabel, MAX_IMM};
 use crate::frame;
 
+mod copy;
+mod intrinsic;
+mod strength;
 mod to_reg;
 use to_reg::ToReg;
 
@@ -27,6 +31,10 @@ impl<'a> Generate<'a> for ir::entities::Value {
         let value_data = ctx.value(*self);
         match value_data.kind() {
             Return(r) => {
+                // 尾调用的被调用者已直接返回
+                if ctx.prev_inst(*self).is_some_and(|call| ctx.is_tail_call(call)) {
+                    return vec![];
+                }
                 let mut v = vec![];
                 if let Some(val) = r.value() {
                     let (_, inst) = val.to_reg(ctx, Some(Reg::A(0)));
@@ -43,6 +51,16 @@ impl<'a> Generate<'a> for ir::entities::Value {
                 if !ctx.on_reg(*self) {
                     let (l, r) = (bin.lhs(), bin.rhs());
                     let dreg = ctx.reg_map_mut().appoint_temp_reg(*self);
+                    // 乘、除、模以常量时改为移位、加减或乘法取高位
+                    if let Some((x, c)) = strength::constant_operand(ctx, bin.op(), l, r) {
+                        let (xreg, xinst) = x.to_reg(ctx, None);
+                        if let Some(insts) = strength::reduce(bin.op(), dreg, xreg, c) {
+                            v.extend(xinst);
+                            v.extend(insts);
+                            v.push(Inst::Sw(dreg, frame!(ctx).get(*self), Reg::Sp));
+                            return v;
+                        }
+                    }
                     let (lreg, linst) = l.to_reg(ctx, None);
                     let (rreg, rinst) = r.to_reg(ctx, None);
                     v.extend(linst);
@@ -73,7 +91,9 @@ impl<'a> Generate<'a> for ir::entities::Value {
                         Mul => v.push(Inst::Mul(dreg, lreg, rreg)),
                         Div => v.push(Inst::Div(dreg, lreg, rreg)),
                         Mod => v.push(Inst::Rem(dreg, lreg, rreg)),
-                        _ => todo!(),
+                        Shl => v.push(Inst::Sll(dreg, lreg, rreg)),
+                        Shr => v.push(Inst::Srl(dreg, lreg, rreg)),
+                        Sar => v.push(Inst::Sra(dreg, lreg, rreg)),
                     };
                     v.push(Inst::Sw(dreg, offset, Reg::Sp))
                 }
@@ -92,7 +112,14 @@ impl<'a> Generate<'a> for ir::entities::Value {
             }, */
             Alloc(_) => vec![],
             GlobalAlloc(_) => vec![],
-            Load(_) => vec![],
+            Load(l) => {
+                if !frame!(ctx).contains(*self) {
+                    return vec![];
+                }
+                let mut v = to_reg::read(ctx, l.src(), Reg::T(0));
+                v.push(Inst::Sw(Reg::T(0), frame!(ctx).get(*self), Reg::Sp));
+                v
+            }
             Store(s) => {
                 let mut v = vec![];
                 if let Undef(_) = ctx.value(s.value()).kind() {
@@ -100,17 +127,56 @@ impl<'a> Generate<'a> for ir::entities::Value {
                 }
                 let (reg, inst) = s.value().to_reg(ctx, None);
                 v.extend(inst);
-                if !s.dest().is_global() {
-                    let offset = frame!(ctx).get(s.dest());
+                let dest = s.dest();
+                if dest.is_global() {
+                    let label = RiscLabel::strip(ctx.value(dest).name().clone().unwrap());
+                    v.extend([Inst::La(Reg::T(0), label), Inst::Sw(reg, 0, Reg::T(0))]);
+                } else if let Alloc(_) = ctx.value(dest).kind() {
+                    let offset = frame!(ctx).get(dest);
                     v.push(Inst::Sw(reg, offset, Reg::Sp));
                 } else {
-                    let label = RiscLabel::strip(ctx.value(s.dest()).name().clone().unwrap());
-                    v.extend([Inst::La(Reg::T(0), label), Inst::Sw(reg, 0, Reg::T(0))]);
+                    // 计算得到的地址
+                    v.extend(dest.to_reg(ctx, Some(Reg::T(0))).1);
+                    v.push(Inst::Sw(reg, 0, Reg::T(0)));
+                }
+                v
+            }
+            GetPtr(_) | GetElemPtr(_) => {
+                let (src, index) = match value_data.kind() {
+                    GetPtr(g) => (g.src(), g.index()),
+                    GetElemPtr(g) => (g.src(), g.index()),
+                    _ => unreachable!(),
+                };
+                // 两者的结果都指向一个元素，下标以元素的大小为单位
+                let size = match value_data.ty().kind() {
+                    ir::TypeKind::Pointer(base) => base.allocate(),
+                    _ => unreachable!(),
+                };
+                let dreg = ctx.reg_map_mut().appoint_temp_reg(*self);
+                let (sreg, mut v) = src.to_reg(ctx, None);
+                if let Integer(i) = ctx.value(index).kind() {
+                    let offset = i.value().wrapping_mul(size);
+                    if (-MAX_IMM - 1..=MAX_IMM).contains(&offset) {
+                        v.push(Inst::Addi(dreg, sreg, offset));
+                    } else {
+                        v.extend([Inst::Li(dreg, offset), Inst::Add(dreg, sreg, dreg)]);
+                    }
+                } else {
+                    let (ireg, iinst) = index.to_reg(ctx, None);
+                    v.extend(iinst);
+                    match strength::reduce(ir::BinaryOp::Mul, dreg, ireg, size) {
+                        Some(insts) => v.extend(insts),
+                        None => v.extend([Inst::Li(dreg, size), Inst::Mul(dreg, ireg, dreg)]),
+                    }
+                    v.push(Inst::Add(dreg, sreg, dreg));
                 }
+                v.push(Inst::Sw(dreg, frame!(ctx).get(*self), Reg::Sp));
                 v
             }
             Undef(_) => vec![],
             Branch(b) => {
+                // 带实参的边已由 `edge::split_arg_edges` 拆分
+                debug_assert!(b.true_args().is_empty() && b.false_args().is_empty());
                 let mut v = vec![];
                 let cond = b.cond();
                 let (gate, gate_inst) = cond.to_reg(ctx, None);
@@ -128,24 +194,47 @@ impl<'a> Generate<'a> for ir::entities::Value {
             Jump(j) => {
                 let target = j.target();
                 let target_block_name = ctx.bb(target).name().clone().unwrap();
-                vec![Inst::J(ctx.label(&target_block_name))]
+                let mut v = copy::generate(ctx, target, j.args());
+                v.push(Inst::J(ctx.label(&target_block_name)));
+                v
             }
             Call(c) => {
                 use crate::back::memory::stack::FrameObj::Slot;
+                if let Some(intrinsic) = ctx.inline_intrinsic(c) {
+                    return intrinsic::generate(intrinsic, ctx, *self, c);
+                }
                 let mut v = vec![];
+                let tail = ctx.is_tail_call(*self);
+                let params = ctx.this_func().params();
                 c.args().iter().enumerate().for_each(|(i, val)| {
                     let (reg, insts) = val.to_reg(ctx, None);
                     v.extend(insts);
                     if i >= 8 {
-                        v.push(Inst::Sw(reg, frame!(ctx).get(Slot(i.try_into().unwrap())), Reg::Sp))
+                        // 尾调用的栈上参数放在本函数的栈上参数的位置
+                        let offset = if tail {
+                            frame!(ctx).get(params[i])
+                        } else {
+                            frame!(ctx).get(Slot(i.try_into().unwrap()))
+                        };
+                        v.push(Inst::Sw(reg, offset, Reg::Sp))
                     } else {
                         v.push(Inst::Mv(Reg::A(i.try_into().unwrap()), reg))
                     };
                 });
-                v.push(Inst::Call(RiscLabel::strip(ctx.func(c.callee()).name())));
+                let label = RiscLabel::strip(ctx.func(c.callee()).name());
+                if tail {
+                    v.extend(ctx.teardown());
+                    v.push(Inst::Tail(label));
+                    return v;
+                }
+                v.push(Inst::Call(label));
                 if !ctx.value(*self).ty().is_unit() {
                     v.push(Inst::Sw(Reg::A(0), frame!(ctx).get(*self), Reg::Sp))
                 }
+                // 64 位返回值的高位字存在紧邻的槽中
+                if ctx.is_wide_call(*self) {
+                    v.push(Inst::Sw(Reg::A(1), frame!(ctx).get(*self) + 4, Reg::Sp))
+                }
                 v
             }
             FuncArgRef(_) => vec![],
@@ -153,3 +242,172 @@ impl<'a> Generate<'a> for ir::entities::Value {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::back::{riscv_text, TargetOptions};
+
+    #[test]
+    fn addresses() {
+        // 超出 12 位立即数的常量偏移量与栈帧地址用 `li` 与 `add` 计算；变量下标乘以元素的大小，
+        // `[i32, 3]` 的 12 字节拆为两次移位；数组的 `alloc` 在栈帧中占据整个数组
+        let asm = riscv_text(
+            r#"
+fun @f(@p: *i32, @i: i32): i32 {
+%entry:
+  %b = alloc [[i32, 3], 2]
+  %a = alloc [i32, 600]
+  %0 = getelemptr %a, 550
+  store 1, %0
+  %1 = getptr @p, -600
+  %2 = load %1
+  %3 = getptr @p, 3
+  %4 = load %3
+  %5 = getelemptr %a, @i
+  %6 = load %5
+  %7 = getelemptr %b, @i
+  %8 = getelemptr %7, 2
+  %9 = load %8
+  %10 = add %2, %4
+  %11 = add %10, %6
+  %12 = add %11, %9
+  ret %12
+}
+"#,
+            TargetOptions::default(),
+        );
+        assert_eq!(
+            asm.trim(),
+            r#"
+  .text
+  .globl f
+f:
+  li t0, -2464
+  add sp, sp, t0
+  addi t2, sp, 40
+  li t1, 2200
+  add t1, t2, t1
+  sw t1, 36(sp)
+  li t3, 1
+  lw t0, 36(sp)
+  sw t3, 0(t0)
+  mv t5, a0
+  li t4, -2400
+  add t4, t5, t4
+  sw t4, 32(sp)
+  mv t1, a0
+  addi t6, t1, 12
+  sw t6, 28(sp)
+  addi t3, sp, 40
+  mv t4, a1
+  slli t2, t4, 2
+  add t2, t3, t2
+  sw t2, 24(sp)
+  li t6, 2440
+  add t6, sp, t6
+  mv t1, a1
+  slli t0, t1, 3
+  slli t5, t1, 2
+  add t5, t5, t0
+  add t5, t6, t5
+  sw t5, 20(sp)
+  lw t3, 20(sp)
+  addi t2, t3, 8
+  sw t2, 16(sp)
+  lw t5, 32(sp)
+  lw t5, 0(t5)
+  lw t6, 28(sp)
+  lw t6, 0(t6)
+  add t4, t5, t6
+  sw t4, 12(sp)
+  lw t2, 12(sp)
+  lw t3, 24(sp)
+  lw t3, 0(t3)
+  add t1, t2, t3
+  sw t1, 8(sp)
+  lw t5, 8(sp)
+  lw t6, 16(sp)
+  lw t6, 0(t6)
+  add t4, t5, t6
+  sw t4, 4(sp)
+  lw a0, 4(sp)
+  j f_end
+f_end:
+  li t0, 2464
+  add sp, sp, t0
+  ret
+"#
+            .trim()
+        );
+    }
+
+    /// 以 `call` 或 `tail` 调用 `callee` 的指令
+    fn calls(asm: &str, callee: &str) -> Vec<String> {
+        asm.lines()
+            .map(str::trim)
+            .filter(|line| *line == format!("call {callee}") || *line == format!("tail {callee}"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn tail_calls() {
+        // 栈上的实参原样放回同一位置时可以尾调用；交换两个栈上参数、本函数在栈上的参数不够、
+        // 局部变量的地址传出或返回 64 位值时都是普通的调用；不返回值的调用也可以尾调用
+        let asm = riscv_text(
+            r#"
+decl @g(i32, i32, i32, i32, i32, i32, i32, i32, i32, i32): i32
+decl @k(*i32): i32
+decl @w(): i32
+decl @v()
+decl @__sysy_hi(i32): i32
+decl @__sysy_set_hi(i32)
+
+fun @same(@a: i32, @b: i32, @c: i32, @d: i32, @e: i32, @f: i32, @g: i32, @h: i32, @j: i32, @k: i32): i32 {
+%entry:
+  %0 = call @g(@a, @b, @c, @d, @e, @f, @g, @h, @j, @k)
+  ret %0
+}
+
+fun @swapped(@a: i32, @b: i32, @c: i32, @d: i32, @e: i32, @f: i32, @g: i32, @h: i32, @j: i32, @k: i32): i32 {
+%entry:
+  %0 = call @g(@a, @b, @c, @d, @e, @f, @g, @h, @k, @j)
+  ret %0
+}
+
+fun @fewer(@x: i32): i32 {
+%entry:
+  %0 = call @g(@x, @x, @x, @x, @x, @x, @x, @x, @x, @x)
+  ret %0
+}
+
+fun @escapes(): i32 {
+%entry:
+  %a = alloc i32
+  store 1, %a
+  %0 = call @k(%a)
+  ret %0
+}
+
+fun @wide(): i32 {
+%entry:
+  %0 = call @w()
+  %1 = call @__sysy_hi(%0)
+  call @__sysy_set_hi(%1)
+  ret %0
+}
+
+fun @void() {
+%entry:
+  call @v()
+  ret
+}
+"#,
+            TargetOptions::default(),
+        );
+        assert_eq!(calls(&asm, "g"), ["tail g", "call g", "call g"]);
+        assert_eq!(calls(&asm, "k"), ["call k"]);
+        assert_eq!(calls(&asm, "w"), ["call w"]);
+        assert_eq!(calls(&asm, "v"), ["tail v"]);
+    }
+}
@@ -0,0 +1,222 @@
+//! 乘、除、模以常量的强度削减
+//!
+//! - 乘以常量：常量的绝对值至多有两个为 1 的位，或为 2 的幂减 1 时，改为移位与加减，负数再取反；
+//! - 除以 2 的幂：负的被除数先加上除数减 1 的偏置，使算术右移向零取整；
+//! - 模 2 的幂：以同样的偏置求出被除数中可整除的部分，再从被除数中减去；
+//! - 除以其他常量：乘以“魔数”取高位再移位，最后加上符号位，使结果向零取整
+//!   （Warren，《Hacker's Delight》第 10 章）；模这些常量则由商乘回除数再相减。
+//!
+//! 除数为 0 时保留 `div`、`rem`，与 RISC-V 的结果一致。`t0` 用作暂存。
+
+use koopa::ir::{BinaryOp, Value, ValueKind};
+
+use crate::back::{
+    risc::{RiscInst as Inst, RiscReg as Reg},
+    Context,
+};
+use crate::WrapProgram;
+
+const TMP: Reg = Reg::T(0);
+
+/// 若为乘、除、模以常量，返回另一个操作数与常量；乘法的常量可以在左边
+pub fn constant_operand(ctx: &Context, op: BinaryOp, lhs: Value, rhs: Value) -> Option<(Value, i32)> {
+    let constant = |v: Value| match ctx.value(v).kind() {
+        ValueKind::Integer(i) => Some(i.value()),
+        _ => None,
+    };
+    match (op, constant(lhs), constant(rhs)) {
+        (BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod, None, Some(c)) => Some((lhs, c)),
+        (BinaryOp::Mul, Some(c), None) => Some((rhs, c)),
+        _ => None,
+    }
+}
+
+/// 计算 `x op c` 存入 `dreg` 的指令序列；不能削减时为 `None`
+pub fn reduce(op: BinaryOp, dreg: Reg, x: Reg, c: i32) -> Option<Vec<Inst>> {
+    match op {
+        BinaryOp::Mul => mul(dreg, x, c),
+        BinaryOp::Div => div(dreg, x, c),
+        BinaryOp::Mod => rem(dreg, x, c),
+        _ => None,
+    }
+}
+
+fn mul(dreg: Reg, x: Reg, c: i32) -> Option<Vec<Inst>> {
+    let a = c.unsigned_abs();
+    let (low, high) = (a.trailing_zeros() as u8, a.checked_ilog2().unwrap_or(0) as u8);
+    let mut v = match a {
+        0 => return Some(vec![Inst::Li(dreg, 0)]),
+        1 => vec![Inst::Mv(dreg, x)],
+        _ if a.is_power_of_two() => vec![Inst::Slli(dreg, x, low)],
+        // 两个为 1 的位
+        _ if (a & (a - 1)).is_power_of_two() && low == 0 => vec![Inst::Slli(TMP, x, high), Inst::Add(dreg, TMP, x)],
+        _ if (a & (a - 1)).is_power_of_two() => {
+            vec![Inst::Slli(TMP, x, high), Inst::Slli(dreg, x, low), Inst::Add(dreg, dreg, TMP)]
+        }
+        _ if (a + 1).is_power_of_two() => vec![Inst::Slli(TMP, x, high + 1), Inst::Sub(dreg, TMP, x)],
+        _ => return None,
+    };
+    if c < 0 {
+        v.push(Inst::Sub(dreg, Reg::Zero, dreg));
+    }
+    Some(v)
+}
+
+/// 将 `x` 加上偏置存入 `t0`：`x` 为负时加 `2^k - 1`，使之后的算术右移 `k` 位向零取整
+fn bias(x: Reg, k: u8) -> Vec<Inst> {
+    if k == 1 {
+        vec![Inst::Srli(TMP, x, 31), Inst::Add(TMP, x, TMP)]
+    } else {
+        vec![Inst::Srai(TMP, x, 31), Inst::Srli(TMP, TMP, 32 - k), Inst::Add(TMP, x, TMP)]
+    }
+}
+
+fn div(dreg: Reg, x: Reg, c: i32) -> Option<Vec<Inst>> {
+    let a = c.unsigned_abs();
+    match a {
+        0 => None,
+        1 if c > 0 => Some(vec![Inst::Mv(dreg, x)]),
+        1 => Some(vec![Inst::Sub(dreg, Reg::Zero, x)]),
+        _ if a.is_power_of_two() => {
+            let k = a.trailing_zeros() as u8;
+            let mut v = bias(x, k);
+            v.push(Inst::Srai(dreg, TMP, k));
+            if c < 0 {
+                v.push(Inst::Sub(dreg, Reg::Zero, dreg));
+            }
+            Some(v)
+        }
+        _ => Some(div_magic(dreg, x, c)),
+    }
+}
+
+fn rem(dreg: Reg, x: Reg, c: i32) -> Option<Vec<Inst>> {
+    let a = c.unsigned_abs();
+    match a {
+        0 => None,
+        1 => Some(vec![Inst::Li(dreg, 0)]),
+        // 余数的符号与被除数相同，与除数的符号无关
+        _ if a.is_power_of_two() => {
+            let k = a.trailing_zeros() as u8;
+            let mut v = bias(x, k);
+            if k <= 11 {
+                v.push(Inst::Andi(TMP, TMP, -(1 << k)));
+            } else {
+                v.extend([Inst::Srai(TMP, TMP, k), Inst::Slli(TMP, TMP, k)]);
+            }
+            v.push(Inst::Sub(dreg, x, TMP));
+            Some(v)
+        }
+        _ => {
+            let mut v = div_magic(dreg, x, c);
+            v.extend([Inst::Li(TMP, c), Inst::Mul(TMP, dreg, TMP), Inst::Sub(dreg, x, TMP)]);
+            Some(v)
+        }
+    }
+}
+
+/// 除以绝对值不小于 2 且不是 2 的幂的 `c`
+fn div_magic(dreg: Reg, x: Reg, c: i32) -> Vec<Inst> {
+    let (m, s) = magic(c);
+    let mut v = vec![Inst::Li(TMP, m), Inst::Mulh(dreg, x, TMP)];
+    if c > 0 && m < 0 {
+        v.push(Inst::Add(dreg, dreg, x));
+    } else if c < 0 && m > 0 {
+        v.push(Inst::Sub(dreg, dreg, x));
+    }
+    if s > 0 {
+        v.push(Inst::Srai(dreg, dreg, s));
+    }
+    v.extend([Inst::Srli(TMP, dreg, 31), Inst::Add(dreg, dreg, TMP)]);
+    v
+}
+
+/// 有符号除以 `d` 的魔数与移位量
+fn magic(d: i32) -> (i32, u8) {
+    const TWO31: u32 = 1 << 31;
+    let ad = d.unsigned_abs();
+    let t = TWO31 + ((d as u32) >> 31);
+    // |nc| 的上界
+    let anc = t - 1 - t % ad;
+    let mut p = 31;
+    let (mut q1, mut r1) = (TWO31 / anc, TWO31 % anc);
+    let (mut q2, mut r2) = (TWO31 / ad, TWO31 % ad);
+    loop {
+        p += 1;
+        q1 = q1.wrapping_mul(2);
+        r1 = r1.wrapping_mul(2);
+        if r1 >= anc {
+            q1 = q1.wrapping_add(1);
+            r1 = r1.wrapping_sub(anc);
+        }
+        q2 = q2.wrapping_mul(2);
+        r2 = r2.wrapping_mul(2);
+        if r2 >= ad {
+            q2 = q2.wrapping_add(1);
+            r2 = r2.wrapping_sub(ad);
+        }
+        let delta = ad - r2;
+        if !(q1 < delta || q1 == delta && r1 == 0) {
+            break;
+        }
+    }
+    let m = q2.wrapping_add(1) as i32;
+    (if d < 0 { m.wrapping_neg() } else { m }, (p - 32) as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use koopa::ir::BinaryOp;
+
+    use super::reduce;
+    use crate::back::risc::{RiscInst as Inst, RiscReg as Reg};
+
+    /// 执行指令序列，返回 `t1` 的值；`t2` 为操作数
+    fn run(insts: &[Inst], x: i32) -> i32 {
+        let mut regs: HashMap<Reg, i32> = HashMap::from([(Reg::Zero, 0), (Reg::T(2), x)]);
+        for inst in insts {
+            let r = |reg: &Reg| regs[reg];
+            let (rd, value) = match inst {
+                Inst::Li(rd, i) => (rd, *i),
+                Inst::Mv(rd, rs) => (rd, r(rs)),
+                Inst::Add(rd, a, b) => (rd, r(a).wrapping_add(r(b))),
+                Inst::Sub(rd, a, b) => (rd, r(a).wrapping_sub(r(b))),
+                Inst::Mul(rd, a, b) => (rd, r(a).wrapping_mul(r(b))),
+                Inst::Mulh(rd, a, b) => (rd, ((r(a) as i64 * r(b) as i64) >> 32) as i32),
+                Inst::Andi(rd, a, i) => (rd, r(a) & i),
+                Inst::Slli(rd, a, k) => (rd, r(a).wrapping_shl(*k as u32)),
+                Inst::Srli(rd, a, k) => (rd, ((r(a) as u32) >> k) as i32),
+                Inst::Srai(rd, a, k) => (rd, r(a) >> k),
+                _ => unreachable!("{}", inst),
+            };
+            regs.insert(*rd, value);
+        }
+        regs[&Reg::T(1)]
+    }
+
+    #[test]
+    fn constants() {
+        let divisors = [1, -1, 2, -2, 3, -3, 5, 6, 7, -7, 10, 25, 125, 641, 1 << 12, -(1 << 20), 1 << 30];
+        let divisors = divisors.into_iter().chain([i32::MIN, i32::MAX]);
+        let factors = [0, 3, 6, 7, 10, 15, 255, -9, 96, -96, 11];
+        let xs: Vec<i32> = (-300..300).chain([12345, -12345, 1 << 30, i32::MAX, i32::MIN, i32::MIN + 1]).collect();
+        for c in divisors.chain(factors) {
+            for op in [BinaryOp::Mul, BinaryOp::Div, BinaryOp::Mod] {
+                let Some(insts) = reduce(op, Reg::T(1), Reg::T(2), c) else {
+                    assert!(op == BinaryOp::Mul || c == 0, "{op:?} {c} is not reduced");
+                    continue;
+                };
+                for &x in xs.iter() {
+                    let expected = match op {
+                        BinaryOp::Mul => x.wrapping_mul(c),
+                        BinaryOp::Div => x.wrapping_div(c),
+                        _ => x.wrapping_rem(c),
+                    };
+                    assert_eq!(run(&insts, x), expected, "{x} {op:?} {c}");
+                }
+            }
+        }
+    }
+}
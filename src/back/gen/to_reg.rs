@@ -3,7 +3,7 @@ use koopa::ir::Value;
 use crate::back::{
     risc::{
         RiscInst::{self as Inst, *},
-        RiscReg as Reg, RiscLabel,
+        RiscReg as Reg, RiscLabel, MAX_IMM,
     },
     Context,
 };
@@ -28,22 +28,22 @@ impl<'a> ToReg<'a> for Value {
         };
         match value_data.kind() {
             Integer(i) => (reg, vec![Li(reg, i.value())]),
-            Binary(_) | Call(_) => {
+            Binary(_) | Call(_) | BlockArgRef(_) | GetPtr(_) | GetElemPtr(_) => {
                 let offset = frame!(ctx).get(*self);
                 (reg, vec![Lw(reg, offset, Reg::Sp)])
             }
             Load(l) => {
-                if !l.src().is_global() {
-                    let offset = frame!(ctx).get(l.src());
-                    (reg, vec![Lw(reg, offset, Reg::Sp)])
+                // 读取后内存可能改变的 `load` 已在读取处存入栈上，其余的在使用时才读取
+                if frame!(ctx).contains(*self) {
+                    (reg, vec![Lw(reg, frame!(ctx).get(*self), Reg::Sp)])
                 } else {
-                    let label = RiscLabel::strip(ctx.value(l.src()).name().clone().unwrap());
-                    (reg, vec![La(reg, label), Lw(reg, 0, reg)])
+                    (reg, read(ctx, l.src(), reg))
                 }
             }
             FuncArgRef(a) => {
                 let i = a.index();
-                if i >= 8 {
+                // 超过 8 个的参数以及在调用后使用的参数在栈上
+                if i >= 8 || frame!(ctx).contains(*self) {
                     (
                         reg,
                         vec![
@@ -59,7 +59,36 @@ impl<'a> ToReg<'a> for Value {
                     )
                 }
             }
+            // 局部变量与全局变量作为值时为其地址
+            Alloc(_) => (reg, frame_address(reg, frame!(ctx).get(*self))),
+            GlobalAlloc(_) => (reg, vec![La(reg, RiscLabel::strip(value_data.name().clone().unwrap()))]),
+            // 未定义的值可以是任意值
+            Undef(_) => (reg, vec![]),
             _ => todo!(),
         }
     }
 }
+
+/// 从 `src` 指向的位置读取到 `reg`
+pub fn read(ctx: &Context, src: Value, reg: Reg) -> Vec<Inst> {
+    if src.is_global() {
+        let label = RiscLabel::strip(ctx.value(src).name().clone().unwrap());
+        vec![La(reg, label), Lw(reg, 0, reg)]
+    } else if let koopa::ir::ValueKind::Alloc(_) = ctx.value(src).kind() {
+        vec![Lw(reg, frame!(ctx).get(src), Reg::Sp)]
+    } else {
+        // 计算得到的地址
+        let (_, mut v) = src.to_reg(ctx, Some(reg));
+        v.push(Lw(reg, 0, reg));
+        v
+    }
+}
+
+/// 将栈帧中偏移量为 `offset` 的地址存入 `reg`
+pub fn frame_address(reg: Reg, offset: i32) -> Vec<Inst> {
+    if offset > MAX_IMM {
+        vec![Li(reg, offset), Add(reg, Reg::Sp, reg)]
+    } else {
+        vec![Addi(reg, Reg::Sp, offset)]
+    }
+}
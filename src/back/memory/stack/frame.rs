@@ -115,9 +115,11 @@ impl FrameMap {
         }
     }
 
-    // pub fn contains_key(&self, val: ir::Value) -> bool {
-    //     self.stack_allo.contains_key(&val)
-    // }
+    pub fn contains<K>(&self, k: K) -> bool
+    where K: Into<FrameObj>
+    {
+        self.map.contains_key(&k.into())
+    }
 }
 
 impl Default for FrameMap {
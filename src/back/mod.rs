@@ -1,27 +1,56 @@
 use std::{error::Error, cell::RefCell};
 
+use koopa::ir;
+
 use crate::{front::Ir, WrapProgram};
 
 mod allocate;
 mod context;
+mod edge;
 mod gen;
 mod memory;
 mod risc;
 
 use context::Context;
-use self::{gen::Generate, memory::stack::StackMap, risc::{RiscItem as Item, RiscLabel, RiscDirc as Dirc}};
+use self::{allocate::Allocate, gen::Generate, memory::stack::StackMap, risc::{RiscItem as Item, RiscLabel, RiscDirc as Dirc}};
 
 pub struct Target(pub String);
 
+/// 目标平台的可选扩展
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TargetOptions {
+    /// 启用 F 扩展，`float` 运算直接生成 RV32F 指令；调用约定仍为 `ilp32`，浮点值在整数寄存器中传递
+    pub rv32f: bool,
+}
+
+impl TargetOptions {
+    /// 由 `-march` 的 ISA 字符串确定，如 `rv32im`、`rv32imf`、`rv32gc`
+    pub fn from_march(isa: &str) -> TargetOptions {
+        let exts = isa.strip_prefix("rv32").unwrap_or_else(|| panic!("Unsupported target '{isa}'!"));
+        // 单字母扩展在 `_` 分隔的多字母扩展之前
+        let exts = exts.split('_').next().unwrap();
+        TargetOptions { rv32f: exts.contains(['f', 'd', 'g']) }
+    }
+}
+
 pub fn into_riscv(ir: Ir) -> Result<String, Box<dyn Error>> {
-    let target: Target = ir.try_into()?;
-    Ok(target.0)
+    into_riscv_with(ir, TargetOptions::default())
+}
+
+pub fn into_riscv_with(ir: Ir, opts: TargetOptions) -> Result<String, Box<dyn Error>> {
+    Ok(Target::generate(ir, opts)?.0)
 }
 
 impl TryFrom<Ir> for Target {
     type Error = Box<dyn Error>;
     fn try_from(ir: Ir) -> Result<Self, Self::Error> {
-        let mut program = ir.0;
+        Target::generate(ir, TargetOptions::default())
+    }
+}
+
+impl Target {
+    pub fn generate(ir: Ir, opts: TargetOptions) -> Result<Target, Box<dyn Error>> {
+        let mut program = ir.program;
         let mut stack = RefCell::new(StackMap::new());
         let mut code = vec![];
         
@@ -35,12 +64,7 @@ impl TryFrom<Ir> for Target {
                     Item::Dirc(Dirc::Global(label.clone())),
                     Item::Label(label),
                 ];
-                v.push(
-                match program.borrow_value(a.init()).kind() {
-                    Integer(i) => Item::Dirc(Dirc::Word(i.value())),
-                    Undef(_) | ZeroInit(_) => Item::Dirc(Dirc::Zero(4)),
-                    _ => unreachable!()
-                });
+                v.extend(initializer(&program, a.init()).into_iter().map(Item::Dirc));
                 v.push(Item::Blank);
                 v
             } else {
@@ -50,12 +74,17 @@ impl TryFrom<Ir> for Target {
         drop(data);
 
         let funcs = program.func_layout().to_vec();
+        for &func in funcs.iter() {
+            if program.func(func).layout().entry_bb().is_some() {
+                edge::split_arg_edges(program.func_mut(func));
+            }
+        }
         code.extend(funcs.into_iter().flat_map(|func| {
             if program.func(func).layout().entry_bb().is_none() {
                 return vec![]
             }
 
-            let ctx = Context::new(&mut program, &mut stack, func);
+            let ctx = Context::new(&mut program, &mut stack, func, opts);
             let mut insts = vec![
                 Item::Dirc(Dirc::Text),
                 Item::Dirc(Dirc::Global(RiscLabel::new(ctx.name()))),
@@ -89,6 +118,25 @@ impl TryFrom<Ir> for Target {
     }
 }
 
+/// 全局变量的初值，数组按元素依次展开
+fn initializer(program: &ir::Program, init: ir::Value) -> Vec<Dirc> {
+    use koopa::ir::ValueKind::*;
+    let data = program.borrow_value(init);
+    match data.kind() {
+        Integer(i) => vec![Dirc::Word(i.value())],
+        Undef(_) | ZeroInit(_) => vec![Dirc::Zero(data.ty().allocate())],
+        Aggregate(a) => a.elems().iter().flat_map(|&e| initializer(program, e)).collect(),
+        _ => unreachable!(),
+    }
+}
+
+/// 解析 Koopa 文本并生成汇编；供后端的单元测试使用
+#[cfg(test)]
+pub(crate) fn riscv_text(source: &str, opts: TargetOptions) -> String {
+    let program = koopa::front::Driver::from(source).generate_program().expect("invalid Koopa text");
+    into_riscv_with(program.into(), opts).unwrap()
+}
+
 // /// [`Declare`] 处理 Koopa AST 中的条目：全局常量、变量声明和函数，并为每一个函数生成上下文（[`Context`]）
 // trait Declare<'a> {
 //     fn declare(&self, program: &'a mut ir::Program);
@@ -104,3 +152,37 @@ impl TryFrom<Ir> for Target {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use super::{riscv_text, TargetOptions};
+
+    /// 只含一个全局变量的程序生成的数据段
+    fn data(global: &str) -> String {
+        riscv_text(global, TargetOptions::default()).trim().to_string()
+    }
+
+    #[test]
+    fn global_initializers() {
+        // 嵌套的初值按元素依次展开，`zeroinit` 与 `undef` 按所占的字节数填零
+        assert_eq!(
+            data("global @a = alloc [[i32, 2], 3], {{1, 2}, zeroinit, {3, undef}}"),
+            r#"
+  .data
+  .globl a
+a:
+  .word 1
+  .word 2
+  .zero 8
+  .word 3
+  .zero 4
+"#
+            .trim()
+        );
+        assert_eq!(
+            data("global @b = alloc [[i32, 2], 2], zeroinit"),
+            ".data\n  .globl b\nb:\n  .zero 16"
+        );
+        assert_eq!(data("global @c = alloc i32, 5"), ".data\n  .globl c\nc:\n  .word 5");
+    }
+}
@@ -23,6 +23,8 @@ pub enum RiscInst {
     Slt(Reg, Reg, Reg),
     /// 大于 `sgt rd, rs1, rs2`
     Sgt(Reg, Reg, Reg),
+    /// 无符号小于 `sltu rd, rs1, rs2`
+    Sltu(Reg, Reg, Reg),
     /// 加 `add rd, rs1, rs2`
     Add(Reg, Reg, Reg),
     /// 加立即数 `addi rd, rs, imm12`
@@ -35,6 +37,26 @@ pub enum RiscInst {
     Div(Reg, Reg, Reg),
     /// 模 `rem rd, rs1, rs2`
     Rem(Reg, Reg, Reg),
+    /// 有符号乘法高位 `mulh rd, rs1, rs2`
+    Mulh(Reg, Reg, Reg),
+    /// 无符号乘法高位 `mulhu rd, rs1, rs2`
+    Mulhu(Reg, Reg, Reg),
+    /// 无符号除 `divu rd, rs1, rs2`
+    Divu(Reg, Reg, Reg),
+    /// 无符号模 `remu rd, rs1, rs2`
+    Remu(Reg, Reg, Reg),
+    /// 逻辑左移 `sll rd, rs1, rs2`
+    Sll(Reg, Reg, Reg),
+    /// 逻辑右移 `srl rd, rs1, rs2`
+    Srl(Reg, Reg, Reg),
+    /// 算术右移 `sra rd, rs1, rs2`
+    Sra(Reg, Reg, Reg),
+    /// 逻辑左移立即数 `slli rd, rs, shamt`
+    Slli(Reg, Reg, u8),
+    /// 逻辑右移立即数 `srli rd, rs, shamt`
+    Srli(Reg, Reg, u8),
+    /// 算术右移立即数 `srai rd, rs, shamt`
+    Srai(Reg, Reg, u8),
     /// 判零 `seqz rd, rs`
     Seqz(Reg, Reg),
     /// 非零 `snez rd, rs`
@@ -49,6 +71,8 @@ pub enum RiscInst {
     Ret,
     /// 调用函数 `call label`
     Call(RiscLabel),
+    /// 尾调用 `tail label`，跳转到函数而不改变 `ra`
+    Tail(RiscLabel),
     /// 加载立即数 `li rd, imm`
     Li(Reg, i32),
     /// 寄存器复制 `mv rd, rs`
@@ -60,6 +84,33 @@ pub enum RiscInst {
     /// 存 `sw rs2, imm12(rs1)`（存 `rs2` 入 `rs1+imm2`）
     Sw(Reg, i32, Reg),
 
+    /// 浮点加 `fadd.s rd, rs1, rs2`
+    FaddS(Reg, Reg, Reg),
+    /// 浮点减 `fsub.s rd, rs1, rs2`
+    FsubS(Reg, Reg, Reg),
+    /// 浮点乘 `fmul.s rd, rs1, rs2`
+    FmulS(Reg, Reg, Reg),
+    /// 浮点除 `fdiv.s rd, rs1, rs2`
+    FdivS(Reg, Reg, Reg),
+    /// 浮点相等 `feq.s rd, rs1, rs2`，结果在整数寄存器中
+    FeqS(Reg, Reg, Reg),
+    /// 浮点小于 `flt.s rd, rs1, rs2`，结果在整数寄存器中
+    FltS(Reg, Reg, Reg),
+    /// 浮点小于等于 `fle.s rd, rs1, rs2`，结果在整数寄存器中
+    FleS(Reg, Reg, Reg),
+    /// 有符号整数转浮点 `fcvt.s.w rd, rs`
+    FcvtSW(Reg, Reg),
+    /// 无符号整数转浮点 `fcvt.s.wu rd, rs`
+    FcvtSWu(Reg, Reg),
+    /// 浮点向零取整为有符号整数 `fcvt.w.s rd, rs, rtz`
+    FcvtWS(Reg, Reg),
+    /// 浮点向零取整为无符号整数 `fcvt.wu.s rd, rs, rtz`
+    FcvtWuS(Reg, Reg),
+    /// 整数寄存器的位模式移入浮点寄存器 `fmv.w.x rd, rs`
+    FmvWX(Reg, Reg),
+    /// 浮点寄存器的位模式移入整数寄存器 `fmv.x.w rd, rs`
+    FmvXW(Reg, Reg),
+
     /// 注释
     Com(String),
 }
@@ -76,18 +127,30 @@ impl Display for RiscInst {
             Xori(rd, rs, i) => write!(f, "xori {rd}, {rs}, {i}"),
             Slt(rd, rs1, rs2) => write!(f, "slt {rd}, {rs1}, {rs2}"),
             Sgt(rd, rs1, rs2) => write!(f, "sgt {rd}, {rs1}, {rs2}"),
+            Sltu(rd, rs1, rs2) => write!(f, "sltu {rd}, {rs1}, {rs2}"),
             Add(rd, rs1, rs2) => write!(f, "add {rd}, {rs1}, {rs2}"),
             Addi(rd, rs, i) => write!(f, "addi {rd}, {rs}, {i}"),
             Sub(rd, rs1, rs2) => write!(f, "sub {rd}, {rs1}, {rs2}"),
             Mul(rd, rs1, rs2) => write!(f, "mul {rd}, {rs1}, {rs2}"),
             Div(rd, rs1, rs2) => write!(f, "div {rd}, {rs1}, {rs2}"),
             Rem(rd, rs1, rs2) => write!(f, "rem {rd}, {rs1}, {rs2}"),
+            Mulh(rd, rs1, rs2) => write!(f, "mulh {rd}, {rs1}, {rs2}"),
+            Mulhu(rd, rs1, rs2) => write!(f, "mulhu {rd}, {rs1}, {rs2}"),
+            Divu(rd, rs1, rs2) => write!(f, "divu {rd}, {rs1}, {rs2}"),
+            Remu(rd, rs1, rs2) => write!(f, "remu {rd}, {rs1}, {rs2}"),
+            Sll(rd, rs1, rs2) => write!(f, "sll {rd}, {rs1}, {rs2}"),
+            Srl(rd, rs1, rs2) => write!(f, "srl {rd}, {rs1}, {rs2}"),
+            Sra(rd, rs1, rs2) => write!(f, "sra {rd}, {rs1}, {rs2}"),
+            Slli(rd, rs, i) => write!(f, "slli {rd}, {rs}, {i}"),
+            Srli(rd, rs, i) => write!(f, "srli {rd}, {rs}, {i}"),
+            Srai(rd, rs, i) => write!(f, "srai {rd}, {rs}, {i}"),
             Seqz(rd, rs) => write!(f, "seqz {rd}, {rs}"),
             Snez(rd, rs) => write!(f, "snez {rd}, {rs}"),
             Beqz(rs, label) => write!(f, "beqz {rs}, {label}"),
             Bnez(rs, label) => write!(f, "bnez {rs}, {label}"),
             Ret => write!(f, "ret"),
             Call(label) => write!(f, "call {label}"),
+            Tail(label) => write!(f, "tail {label}"),
             J(label) => write!(f, "j {label}"),
             Li(r, i) => write!(f, "li {r}, {i}"),
             Mv(rd, rs) => write!(f, "mv {rd}, {rs}"),
@@ -95,6 +158,20 @@ impl Display for RiscInst {
             Lw(rs, of, rd) => write!(f, "lw {rs}, {of}({rd})"),
             Sw(rs2, of, rs1) => write!(f, "sw {rs2}, {of}({rs1})"),
 
+            FaddS(rd, rs1, rs2) => write!(f, "fadd.s {rd}, {rs1}, {rs2}"),
+            FsubS(rd, rs1, rs2) => write!(f, "fsub.s {rd}, {rs1}, {rs2}"),
+            FmulS(rd, rs1, rs2) => write!(f, "fmul.s {rd}, {rs1}, {rs2}"),
+            FdivS(rd, rs1, rs2) => write!(f, "fdiv.s {rd}, {rs1}, {rs2}"),
+            FeqS(rd, rs1, rs2) => write!(f, "feq.s {rd}, {rs1}, {rs2}"),
+            FltS(rd, rs1, rs2) => write!(f, "flt.s {rd}, {rs1}, {rs2}"),
+            FleS(rd, rs1, rs2) => write!(f, "fle.s {rd}, {rs1}, {rs2}"),
+            FcvtSW(rd, rs) => write!(f, "fcvt.s.w {rd}, {rs}"),
+            FcvtSWu(rd, rs) => write!(f, "fcvt.s.wu {rd}, {rs}"),
+            FcvtWS(rd, rs) => write!(f, "fcvt.w.s {rd}, {rs}, rtz"),
+            FcvtWuS(rd, rs) => write!(f, "fcvt.wu.s {rd}, {rs}, rtz"),
+            FmvWX(rd, rs) => write!(f, "fmv.w.x {rd}, {rs}"),
+            FmvXW(rd, rs) => write!(f, "fmv.x.w {rd}, {rs}"),
+
             Com(c) => write!(f, "# {c}"),
         }
     }
@@ -19,6 +19,11 @@ pub enum RiscReg {
     Ra,
     /// `x2`，栈指针，调用者保存
     Sp,
+    /// 浮点临时寄存器，调用者保存，仅在启用 F 扩展时使用
+    ///
+    /// - `f0-7` `ft0-7`
+    /// - `f28-31` `ft8-11`
+    Ft(u8),
 }
 
 impl Display for RiscReg {
@@ -30,6 +35,7 @@ impl Display for RiscReg {
             Zero => write!(f, "zero"),
             Ra => write!(f, "ra"),
             Sp => write!(f, "sp"),
+            Ft(i) => write!(f, "ft{i}"),
         }
     }
 }
@@ -0,0 +1,55 @@
+//! `sysy-fmt [--check] [FILE]...`
+//!
+//! 原地格式化给出的文件；没有给出文件时从标准输入读入，结果写到标准输出。
+//! `--check` 只检查而不写回，存在未格式化的文件时以状态 1 退出。
+
+use std::{
+    env::args,
+    error::Error,
+    fs,
+    io::{self, Read},
+    process::exit,
+};
+
+use compiler::front::fmt;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut check = false;
+    let mut files = vec![];
+    for arg in args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ => files.push(arg),
+        }
+    }
+
+    if files.is_empty() {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        let formatted = fmt::format(&source)?;
+        if check {
+            exit(i32::from(formatted != source));
+        }
+        print!("{}", formatted);
+        return Ok(());
+    }
+
+    let mut unformatted = false;
+    for file in files {
+        let source = fs::read_to_string(&file)?;
+        let formatted = fmt::format(&source).map_err(|e| format!("{}: {}", file, e))?;
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file);
+            unformatted = true;
+        } else {
+            fs::write(&file, formatted)?;
+        }
+    }
+    if unformatted {
+        exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,14 @@
+//! `sysy-lsp`
+//!
+//! SysY 的语言服务器，经由标准输入输出与编辑器通信，参见 [`compiler::lsp`]。
+
+use std::{io, panic, process::exit};
+
+use compiler::lsp;
+
+fn main() -> io::Result<()> {
+    // 语义错误以 panic 报告，由服务器捕获后转为诊断，不再打印到标准错误
+    panic::set_hook(Box::new(|_| {}));
+    let code = lsp::run(&mut io::stdin().lock(), &mut io::stdout().lock())?;
+    exit(code);
+}
@@ -1,12 +1,23 @@
 use std::env::args;
 
+use crate::{back::TargetOptions, front::{ast::dump, lint::LintConfig}, opt::OptLevel};
+
 pub struct Config {
     pub mode: CompilerMode,
     pub input: String,
     pub output: String,
+    pub target: TargetOptions,
+    pub lints: LintConfig,
+    /// `--verify`：检查生成的 Koopa IR 并报告警告；调试构建总是检查，但只报告错误
+    pub verify: bool,
+    pub opt_level: OptLevel,
+    /// `--passes=a,b,c`：代替 `-O` 级别预设的变换序列
+    pub passes: Option<Vec<String>>,
 }
 
 pub enum CompilerMode {
+    /// `--emit=ast`，格式由 `--ast-format=sexp|json|sysy` 指定，默认为 `sexp`
+    Ast(dump::Format),
     Koopa,
     Riscv,
 }
@@ -17,6 +28,13 @@ impl Config {
         let mut mode = CompilerMode::Koopa;
         let mut input = String::new();
         let mut output = String::new();
+        let mut target = TargetOptions::default();
+        let mut lints = LintConfig::default();
+        let mut verify = false;
+        let mut opt_level = OptLevel::default();
+        let mut passes = None;
+        let mut emit_ast = false;
+        let mut ast_format = dump::Format::Sexp;
         for (idx, arg) in args.iter().enumerate() {
             if idx == 0 {
                 continue;
@@ -31,15 +49,52 @@ impl Config {
                         mode = CompilerMode::Riscv;
                         input.push_str(args.get(idx + 1).expect("Missing input path!"))
                     }
+                    // 课程评测的性能测试，即开启优化的 `-riscv`
+                    "-perf" => {
+                        mode = CompilerMode::Riscv;
+                        opt_level = OptLevel::O2;
+                        input.push_str(args.get(idx + 1).expect("Missing input path!"))
+                    }
                     "-o" => output.push_str(args.get(idx + 1).expect("Missing output path!")),
+                    march if march.starts_with("-march=") => {
+                        target = TargetOptions::from_march(&march["-march=".len()..])
+                    }
+                    warning if warning.starts_with("-W") => lints.apply(warning),
+                    "--verify" => verify = true,
+                    level if level.starts_with("-O") => opt_level = OptLevel::new(&level["-O".len()..]),
+                    list if list.starts_with("--passes=") => {
+                        passes = Some(
+                            list["--passes=".len()..]
+                                .split(',')
+                                .filter(|p| !p.is_empty())
+                                .map(str::to_string)
+                                .collect(),
+                        )
+                    }
+                    "--emit=ast" => emit_ast = true,
+                    "--emit=koopa" => mode = CompilerMode::Koopa,
+                    "--emit=riscv" => mode = CompilerMode::Riscv,
+                    format if format.starts_with("--ast-format=") => {
+                        ast_format = dump::Format::new(&format["--ast-format=".len()..])
+                    }
                     _ => unimplemented!(),
                 }
+            } else if input.is_empty() && !matches!(args[idx - 1].as_str(), "-o" | "-koopa" | "-riscv" | "-perf") {
+                input.push_str(arg);
             }
         }
+        if emit_ast {
+            mode = CompilerMode::Ast(ast_format);
+        }
         Config {
             mode,
             input,
             output,
+            target,
+            lints,
+            verify,
+            opt_level,
+            passes,
         }
     }
 }
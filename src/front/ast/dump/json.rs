@@ -0,0 +1,142 @@
+use super::*;
+
+/// 顶层为条目的数组，每个结点都是带 `"kind"` 字段的对象
+///
+/// 表达式忽略优先级层次，括号保留为 `"paren"` 结点；浮点数字面量的值为 `f32` 转换得到的 JSON 数，无穷大为 `null`。
+pub fn dump(items: &[Item]) -> String {
+    let items: Vec<_> = items.iter().map(item).collect();
+    format!("[{}]\n", items.join(","))
+}
+
+fn string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<_> = fields.iter().map(|(k, v)| format!("{}:{}", string(k), v)).collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+fn array(elems: impl Iterator<Item = String>) -> String {
+    format!("[{}]", elems.collect::<Vec<_>>().join(","))
+}
+
+fn item(i: &Item) -> String {
+    match &i.kind {
+        ItemKind::Global(decls) => object(&[
+            ("kind", string("global")),
+            ("decls", array(decls.iter().map(decl))),
+        ]),
+        ItemKind::Func(f) => object(&[
+            ("kind", string("func")),
+            ("ident", string(&f.ident)),
+            ("output", string(f.output.name())),
+            ("params", array(f.params.iter().map(|p| object(&[
+                ("ident", string(&p.ident)),
+                ("ty", string(p.ty.name())),
+            ])))),
+            ("block", block(&f.block)),
+        ]),
+    }
+}
+
+fn decl(d: &Decl) -> String {
+    let kind = match d.kind {
+        SymKind::Const => "const",
+        SymKind::Var => "var",
+    };
+    object(&[
+        ("kind", string(kind)),
+        ("ident", string(&d.ident)),
+        ("ty", string(d.ty.name())),
+        ("init", d.exp.as_ref().map_or("null".to_string(), |e| exp(e))),
+    ])
+}
+
+fn block(b: &Block) -> String {
+    let items = b.0.iter().map(|i| match i {
+        BlockItem::Stmt(s) => stmt(s),
+        BlockItem::Decl(decls) => object(&[
+            ("kind", string("decl")),
+            ("decls", array(decls.iter().map(decl))),
+        ]),
+    });
+    object(&[("kind", string("block")), ("items", array(items))])
+}
+
+fn stmt(s: &Stmt) -> String {
+    match &s.kind {
+        StmtKind::Unit => object(&[("kind", string("nop"))]),
+        StmtKind::Exp(e) => object(&[("kind", string("exp")), ("exp", exp(e))]),
+        StmtKind::Block(b) => block(b),
+        StmtKind::Assign(l, e) => object(&[
+            ("kind", string("assign")),
+            ("lval", string(&l.0)),
+            ("exp", exp(e)),
+        ]),
+        StmtKind::If(e, then, alt) => object(&[
+            ("kind", string("if")),
+            ("cond", exp(e)),
+            ("then", stmt(then)),
+            ("else", alt.as_ref().map_or("null".to_string(), |s| stmt(s))),
+        ]),
+        StmtKind::While(e, body) => object(&[
+            ("kind", string("while")),
+            ("cond", exp(e)),
+            ("body", stmt(body)),
+        ]),
+        StmtKind::Break => object(&[("kind", string("break"))]),
+        StmtKind::Continue => object(&[("kind", string("continue"))]),
+        StmtKind::Return(e) => object(&[
+            ("kind", string("return")),
+            ("exp", e.as_ref().map_or("null".to_string(), |e| exp(e))),
+        ]),
+    }
+}
+
+fn exp(e: &dyn Node) -> String {
+    match e.view() {
+        View::Binary(op, l, r) => object(&[
+            ("kind", string("binary")),
+            ("op", string(op)),
+            ("lhs", exp(l)),
+            ("rhs", exp(r)),
+        ]),
+        View::Unary(op, e) => object(&[
+            ("kind", string("unary")),
+            ("op", string(op)),
+            ("exp", exp(e)),
+        ]),
+        View::Call(ident, args) => object(&[
+            ("kind", string("call")),
+            ("func", string(ident)),
+            ("args", array(args.into_iter().map(exp))),
+        ]),
+        View::Paren(e) => object(&[("kind", string("paren")), ("exp", exp(e))]),
+        View::Literal(l) => {
+            let value = match l {
+                Literal::Int(i) => i.to_string(),
+                Literal::UInt(u) => u.to_string(),
+                Literal::Long(l) => l.to_string(),
+                Literal::Float(f) if f.is_finite() => format!("{:?}", f),
+                Literal::Float(_) => "null".to_string(),
+            };
+            object(&[
+                ("kind", string("literal")),
+                ("ty", string(l.ty().name())),
+                ("value", value),
+            ])
+        }
+        View::LVal(l) => object(&[("kind", string("lval")), ("ident", string(&l.0))]),
+    }
+}
@@ -0,0 +1,176 @@
+//! AST 的文本形式，供 `--emit=ast` 使用
+//!
+//! - [`Format::Sexp`]：紧凑的 S 表达式，忽略表达式的优先级层次和括号；
+//! - [`Format::Json`]：供外部工具使用的 JSON；
+//! - [`Format::Sysy`]：SysY 源代码，重新解析后得到与原先相同的 AST。
+
+use super::*;
+
+mod json;
+mod sexp;
+mod sysy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Sexp,
+    Json,
+    Sysy,
+}
+
+impl Format {
+    pub fn new(format: &str) -> Format {
+        match format {
+            "sexp" => Format::Sexp,
+            "json" => Format::Json,
+            "sysy" => Format::Sysy,
+            _ => panic!("Unknown AST format '{}', expected one of sexp, json, sysy.", format),
+        }
+    }
+}
+
+pub fn dump(items: &[Item], format: Format) -> String {
+    match format {
+        Format::Sexp => sexp::dump(items),
+        Format::Json => json::dump(items),
+        Format::Sysy => sysy::dump(items),
+    }
+}
+
+impl Ty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ty::Int => "int",
+            Ty::UInt => "unsigned int",
+            Ty::Long => "long",
+            Ty::Float => "float",
+            Ty::Void => "void",
+        }
+    }
+}
+
+/// 忽略优先级层次后的表达式结点，各层的 `Unary` 变体直接展开为其内层
+enum View<'a> {
+    Binary(&'static str, &'a dyn Node, &'a dyn Node),
+    Unary(&'static str, &'a dyn Node),
+    Call(&'a str, Vec<&'a dyn Node>),
+    Paren(&'a dyn Node),
+    Literal(&'a Literal),
+    LVal(&'a LVal),
+}
+
+trait Node {
+    fn view(&self) -> View<'_>;
+}
+
+impl Node for Exp {
+    fn view(&self) -> View<'_> {
+        self.0.view()
+    }
+}
+
+impl Node for LOrExp {
+    fn view(&self) -> View<'_> {
+        match self {
+            LOrExp::Unary(e) => e.view(),
+            LOrExp::Binary(l, r) => View::Binary("||", l.as_ref(), r),
+        }
+    }
+}
+
+impl Node for LAndExp {
+    fn view(&self) -> View<'_> {
+        match self {
+            LAndExp::Unary(e) => e.view(),
+            LAndExp::Binary(l, r) => View::Binary("&&", l.as_ref(), r),
+        }
+    }
+}
+
+impl Node for EqExp {
+    fn view(&self) -> View<'_> {
+        match self {
+            EqExp::Unary(e) => e.view(),
+            EqExp::Binary(l, op, r) => {
+                let op = match op {
+                    EqOp::Eq => "==",
+                    EqOp::Ne => "!=",
+                };
+                View::Binary(op, l.as_ref(), r)
+            }
+        }
+    }
+}
+
+impl Node for RelExp {
+    fn view(&self) -> View<'_> {
+        match self {
+            RelExp::Unary(e) => e.view(),
+            RelExp::Binary(l, op, r) => {
+                let op = match op {
+                    RelOp::Lt => "<",
+                    RelOp::Gt => ">",
+                    RelOp::Le => "<=",
+                    RelOp::Ge => ">=",
+                };
+                View::Binary(op, l.as_ref(), r)
+            }
+        }
+    }
+}
+
+impl Node for AddExp {
+    fn view(&self) -> View<'_> {
+        match self {
+            AddExp::Unary(e) => e.view(),
+            AddExp::Binary(l, op, r) => {
+                let op = match op {
+                    AddOp::Add => "+",
+                    AddOp::Sub => "-",
+                };
+                View::Binary(op, l.as_ref(), r)
+            }
+        }
+    }
+}
+
+impl Node for MulExp {
+    fn view(&self) -> View<'_> {
+        match self {
+            MulExp::Unary(e) => e.view(),
+            MulExp::Binary(l, op, r) => {
+                let op = match op {
+                    MulOp::Mul => "*",
+                    MulOp::Div => "/",
+                    MulOp::Mod => "%",
+                };
+                View::Binary(op, l.as_ref(), r)
+            }
+        }
+    }
+}
+
+impl Node for UnaryExp {
+    fn view(&self) -> View<'_> {
+        match self {
+            UnaryExp::Primary(e) => e.view(),
+            UnaryExp::Unary(op, e) => {
+                let op = match op {
+                    UnaryOp::Minus => "-",
+                    UnaryOp::LNot => "!",
+                };
+                View::Unary(op, e.as_ref())
+            }
+            UnaryExp::Call(ident, args) => View::Call(ident, args.iter().map(|a| a.as_ref() as &dyn Node).collect()),
+        }
+    }
+}
+
+impl Node for PrimaryExp {
+    fn view(&self) -> View<'_> {
+        match self {
+            PrimaryExp::Exp(e) => View::Paren(e.as_ref()),
+            PrimaryExp::Literal(l) => View::Literal(l),
+            PrimaryExp::LVal(l) => View::LVal(l),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use super::*;
+
+/// 每个全局条目一行，如 `(func int main () (block (return (+ 1 2))))`
+pub fn dump(items: &[Item]) -> String {
+    let mut out = String::new();
+    for item in items {
+        match &item.kind {
+            ItemKind::Global(decls) => {
+                for d in decls {
+                    decl(&mut out, d);
+                    out.push('\n');
+                }
+            }
+            ItemKind::Func(f) => {
+                out.push_str(&format!("(func {} {} (", f.output.name(), f.ident));
+                for (idx, p) in f.params.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(' ');
+                    }
+                    out.push_str(&format!("({} {})", p.ty.name(), p.ident));
+                }
+                out.push_str(") ");
+                block(&mut out, &f.block);
+                out.push_str(")\n");
+            }
+        }
+    }
+    out
+}
+
+fn decl(out: &mut String, d: &Decl) {
+    let kind = match d.kind {
+        SymKind::Const => "const",
+        SymKind::Var => "var",
+    };
+    out.push_str(&format!("({} {} {}", kind, d.ty.name(), d.ident));
+    if let Some(e) = &d.exp {
+        out.push(' ');
+        exp(out, e);
+    }
+    out.push(')');
+}
+
+fn block(out: &mut String, b: &Block) {
+    out.push_str("(block");
+    for item in b.0.iter() {
+        match item {
+            BlockItem::Stmt(s) => {
+                out.push(' ');
+                stmt(out, s);
+            }
+            BlockItem::Decl(decls) => {
+                for d in decls {
+                    out.push(' ');
+                    decl(out, d);
+                }
+            }
+        }
+    }
+    out.push(')');
+}
+
+fn stmt(out: &mut String, s: &Stmt) {
+    match &s.kind {
+        StmtKind::Unit => out.push_str("(nop)"),
+        StmtKind::Exp(e) => {
+            out.push_str("(exp ");
+            exp(out, e);
+            out.push(')');
+        }
+        StmtKind::Block(b) => block(out, b),
+        StmtKind::Assign(l, e) => {
+            out.push_str(&format!("(assign {} ", l.0));
+            exp(out, e);
+            out.push(')');
+        }
+        StmtKind::If(e, then, alt) => {
+            out.push_str("(if ");
+            exp(out, e);
+            out.push(' ');
+            stmt(out, then);
+            if let Some(alt) = alt {
+                out.push(' ');
+                stmt(out, alt);
+            }
+            out.push(')');
+        }
+        StmtKind::While(e, body) => {
+            out.push_str("(while ");
+            exp(out, e);
+            out.push(' ');
+            stmt(out, body);
+            out.push(')');
+        }
+        StmtKind::Break => out.push_str("(break)"),
+        StmtKind::Continue => out.push_str("(continue)"),
+        StmtKind::Return(e) => {
+            out.push_str("(return");
+            if let Some(e) = e {
+                out.push(' ');
+                exp(out, e);
+            }
+            out.push(')');
+        }
+    }
+}
+
+fn exp(out: &mut String, e: &dyn Node) {
+    match e.view() {
+        View::Binary(op, l, r) => {
+            out.push_str(&format!("({} ", op));
+            exp(out, l);
+            out.push(' ');
+            exp(out, r);
+            out.push(')');
+        }
+        View::Unary(op, e) => {
+            out.push_str(&format!("({} ", op));
+            exp(out, e);
+            out.push(')');
+        }
+        View::Call(ident, args) => {
+            out.push_str(&format!("(call {}", ident));
+            for a in args {
+                out.push(' ');
+                exp(out, a);
+            }
+            out.push(')');
+        }
+        View::Paren(e) => exp(out, e),
+        View::Literal(l) => match l {
+            Literal::Int(i) => out.push_str(&i.to_string()),
+            Literal::UInt(u) => out.push_str(&format!("{}u", u)),
+            Literal::Long(l) => out.push_str(&format!("{}L", l)),
+            Literal::Float(f) => out.push_str(&format!("{:?}f", f)),
+        },
+        View::LVal(l) => out.push_str(&l.0),
+    }
+}
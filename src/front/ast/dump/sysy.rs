@@ -0,0 +1,469 @@
+use super::*;
+
+/// 以四个空格缩进输出 SysY 源代码
+///
+/// 对解析器得到的 AST，输出重新解析后得到相同的 AST：表达式的层次与文法一一对应，括号原样保留；
+/// 字面量带上决定其类型的后缀，浮点数在十进制表示无法精确还原时改用十六进制表示。
+pub fn dump(items: &[Item]) -> String {
+    let mut p = Printer { out: String::new(), indent: 0 };
+    for (idx, item) in items.iter().enumerate() {
+        let is_func = matches!(item.kind, ItemKind::Func(_));
+        if idx > 0 && (is_func || matches!(items[idx - 1].kind, ItemKind::Func(_))) {
+            p.out.push('\n');
+        }
+        match &item.kind {
+            ItemKind::Global(decls) => p.decls(decls),
+            ItemKind::Func(f) => p.func(f),
+        }
+    }
+    p.out
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+}
+
+impl Printer {
+    fn push(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    fn push_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn func(&mut self, f: &Func) {
+        self.push(&format!("{} {}(", f.output.name(), f.ident));
+        for (idx, p) in f.params.iter().enumerate() {
+            if idx > 0 {
+                self.push(", ");
+            }
+            self.push(&format!("{} {}", p.ty.name(), p.ident));
+        }
+        self.push(") ");
+        self.block(&f.block);
+        self.push("\n");
+    }
+
+    /// 同一条声明语句中的定义共享类型和种类
+    fn decls(&mut self, decls: &[Decl]) {
+        let first = &decls[0];
+        if first.kind == SymKind::Const {
+            self.push("const ");
+        }
+        self.push(first.ty.name());
+        for (idx, d) in decls.iter().enumerate() {
+            self.push(if idx > 0 { ", " } else { " " });
+            self.push(&d.ident);
+            if let Some(e) = &d.exp {
+                self.push(" = ");
+                self.exp(e);
+            }
+        }
+        self.push(";\n");
+    }
+
+    /// 输出 `{ ... }`，不含结尾的换行
+    fn block(&mut self, b: &Block) {
+        self.push("{\n");
+        self.indent += 1;
+        for item in b.0.iter() {
+            self.push_indent();
+            match item {
+                BlockItem::Stmt(s) => self.stmt(s),
+                BlockItem::Decl(decls) => self.decls(decls),
+            }
+        }
+        self.indent -= 1;
+        self.push_indent();
+        self.push("}");
+    }
+
+    /// `if`、`while` 的分支：块与关键字同行，其余语句另起一行并缩进。返回分支是否为块
+    fn branch(&mut self, s: &Stmt) -> bool {
+        if let StmtKind::Block(b) = &s.kind {
+            self.push(" ");
+            self.block(b);
+            true
+        } else {
+            self.push("\n");
+            self.indent += 1;
+            self.push_indent();
+            self.stmt(s);
+            self.indent -= 1;
+            false
+        }
+    }
+
+    /// 调用时已经输出缩进，结束时输出换行
+    fn stmt(&mut self, s: &Stmt) {
+        match &s.kind {
+            StmtKind::Unit => self.push(";\n"),
+            StmtKind::Exp(e) => {
+                self.exp(e);
+                self.push(";\n");
+            }
+            StmtKind::Block(b) => {
+                self.block(b);
+                self.push("\n");
+            }
+            StmtKind::Assign(l, e) => {
+                self.push(&format!("{} = ", l.0));
+                self.exp(e);
+                self.push(";\n");
+            }
+            StmtKind::If(e, then, alt) => {
+                self.push("if (");
+                self.exp(e);
+                self.push(")");
+                let is_block = self.branch(then);
+                match alt {
+                    Some(alt) => {
+                        if is_block {
+                            self.push(" else");
+                        } else {
+                            self.push_indent();
+                            self.push("else");
+                        }
+                        if matches!(alt.kind, StmtKind::If(..)) {
+                            self.push(" ");
+                            self.stmt(alt);
+                        } else if self.branch(alt) {
+                            self.push("\n");
+                        }
+                    }
+                    None if is_block => self.push("\n"),
+                    None => {}
+                }
+            }
+            StmtKind::While(e, body) => {
+                self.push("while (");
+                self.exp(e);
+                self.push(")");
+                if self.branch(body) {
+                    self.push("\n");
+                }
+            }
+            StmtKind::Break => self.push("break;\n"),
+            StmtKind::Continue => self.push("continue;\n"),
+            StmtKind::Return(e) => {
+                self.push("return");
+                if let Some(e) = e {
+                    self.push(" ");
+                    self.exp(e);
+                }
+                self.push(";\n");
+            }
+        }
+    }
+
+    fn exp(&mut self, e: &dyn Node) {
+        match e.view() {
+            View::Binary(op, l, r) => {
+                self.exp(l);
+                self.push(&format!(" {} ", op));
+                self.exp(r);
+            }
+            View::Unary(op, e) => {
+                self.push(op);
+                self.exp(e);
+            }
+            View::Call(ident, args) => {
+                self.push(&format!("{}(", ident));
+                for (idx, a) in args.into_iter().enumerate() {
+                    if idx > 0 {
+                        self.push(", ");
+                    }
+                    self.exp(a);
+                }
+                self.push(")");
+            }
+            View::Paren(e) => {
+                self.push("(");
+                self.exp(e);
+                self.push(")");
+            }
+            View::Literal(l) => {
+                let l = literal(l);
+                self.push(&l);
+            }
+            View::LVal(l) => self.push(&l.0),
+        }
+    }
+}
+
+fn literal(l: &Literal) -> String {
+    match l {
+        Literal::Int(i) => i.to_string(),
+        Literal::UInt(u) => format!("{}u", u),
+        Literal::Long(l) => format!("{}L", l),
+        Literal::Float(f) => {
+            // 解析时先得到 `f64` 再舍入为 `f32`，最短的十进制表示经过两次舍入后未必还原
+            let repr = format!("{:?}", f);
+            if f.is_finite() && (repr.parse::<f64>().unwrap() as f32).to_bits() == f.to_bits() {
+                repr
+            } else {
+                hex_float(*f)
+            }
+        }
+    }
+}
+
+/// 精确的十六进制表示 `0x<尾数>p<指数>`，无穷大表示为 `0x1p128`
+fn hex_float(f: f32) -> String {
+    if f.is_infinite() {
+        return "0x1p128".to_string();
+    }
+    let bits = f.to_bits();
+    let (exp, mantissa) = ((bits >> 23) & 0xff, bits & 0x7f_ffff);
+    let (mantissa, exp) = if exp == 0 {
+        (mantissa, -149)
+    } else {
+        (mantissa | 0x80_0000, exp as i32 - 150)
+    };
+    format!("0x{:x}p{}", mantissa, exp)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::front::parser::CompUnitParser;
+
+    /// xorshift64，保证每次运行生成相同的用例
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+
+        fn chance(&mut self, percent: u64) -> bool {
+            self.below(100) < percent
+        }
+
+        /// 生成的 AST 不对应源代码，位置一律为空
+        fn ident(&mut self) -> Ident {
+            const IDENTS: [&str; 8] = ["a", "b", "x1", "_t", "n", "f", "getint", "main"];
+            Ident::new(IDENTS[self.below(IDENTS.len() as u64) as usize], Span::default())
+        }
+
+        fn ty(&mut self) -> Ty {
+            [Ty::Int, Ty::UInt, Ty::Long, Ty::Float][self.below(4) as usize]
+        }
+
+        fn literal(&mut self) -> Literal {
+            match self.below(4) {
+                0 => Literal::Int((self.next() >> 33) as i32),
+                1 => Literal::UInt(self.next() as u32),
+                2 => Literal::Long((self.next() >> 1) as i64),
+                _ => loop {
+                    let f = f32::from_bits(self.next() as u32 & 0x7fff_ffff);
+                    if f.is_finite() {
+                        break Literal::Float(f);
+                    }
+                },
+            }
+        }
+
+        fn exp(&mut self, depth: u32) -> Exp {
+            Exp(self.lor(depth))
+        }
+
+        fn lor(&mut self, depth: u32) -> LOrExp {
+            if depth > 0 && self.chance(20) {
+                LOrExp::Binary(Box::new(self.lor(depth - 1)), self.land(depth - 1))
+            } else {
+                LOrExp::Unary(self.land(depth))
+            }
+        }
+
+        fn land(&mut self, depth: u32) -> LAndExp {
+            if depth > 0 && self.chance(20) {
+                LAndExp::Binary(Box::new(self.land(depth - 1)), self.eq(depth - 1))
+            } else {
+                LAndExp::Unary(self.eq(depth))
+            }
+        }
+
+        fn eq(&mut self, depth: u32) -> EqExp {
+            if depth > 0 && self.chance(20) {
+                let op = if self.chance(50) { EqOp::Eq } else { EqOp::Ne };
+                EqExp::Binary(Box::new(self.eq(depth - 1)), op, self.rel(depth - 1))
+            } else {
+                EqExp::Unary(self.rel(depth))
+            }
+        }
+
+        fn rel(&mut self, depth: u32) -> RelExp {
+            if depth > 0 && self.chance(20) {
+                let op = [RelOp::Lt, RelOp::Gt, RelOp::Le, RelOp::Ge][self.below(4) as usize];
+                RelExp::Binary(Box::new(self.rel(depth - 1)), op, self.add(depth - 1))
+            } else {
+                RelExp::Unary(self.add(depth))
+            }
+        }
+
+        fn add(&mut self, depth: u32) -> AddExp {
+            if depth > 0 && self.chance(30) {
+                let op = if self.chance(50) { AddOp::Add } else { AddOp::Sub };
+                AddExp::Binary(Box::new(self.add(depth - 1)), op, self.mul(depth - 1))
+            } else {
+                AddExp::Unary(self.mul(depth))
+            }
+        }
+
+        fn mul(&mut self, depth: u32) -> MulExp {
+            if depth > 0 && self.chance(30) {
+                let op = [MulOp::Mul, MulOp::Div, MulOp::Mod][self.below(3) as usize];
+                MulExp::Binary(Box::new(self.mul(depth - 1)), op, self.unary(depth - 1))
+            } else {
+                MulExp::Unary(self.unary(depth))
+            }
+        }
+
+        fn unary(&mut self, depth: u32) -> UnaryExp {
+            match if depth > 0 { self.below(6) } else { 0 } {
+                0..=3 => UnaryExp::Primary(self.primary(depth)),
+                4 => {
+                    let op = if self.chance(50) { UnaryOp::Minus } else { UnaryOp::LNot };
+                    UnaryExp::Unary(op, Box::new(self.unary(depth - 1)))
+                }
+                _ => {
+                    let args = (0..self.below(3)).map(|_| Box::new(self.exp(depth - 1))).collect();
+                    UnaryExp::Call(self.ident(), args)
+                }
+            }
+        }
+
+        fn primary(&mut self, depth: u32) -> PrimaryExp {
+            match if depth > 0 { self.below(3) } else { 1 + self.below(2) } {
+                0 => PrimaryExp::Exp(Box::new(self.exp(depth - 1))),
+                1 => PrimaryExp::Literal(self.literal()),
+                _ => PrimaryExp::LVal(LVal(self.ident())),
+            }
+        }
+
+        fn decls(&mut self, depth: u32) -> Vec<Decl> {
+            let ty = self.ty();
+            let kind = if self.chance(30) { SymKind::Const } else { SymKind::Var };
+            (0..1 + self.below(3))
+                .map(|_| Decl {
+                    ident: self.ident(),
+                    ty,
+                    kind,
+                    exp: (kind == SymKind::Const || self.chance(50)).then(|| self.exp(depth)),
+                })
+                .collect()
+        }
+
+        fn block(&mut self, depth: u32) -> Block {
+            let len = if depth > 0 { self.below(4) } else { 0 };
+            Block(
+                (0..len)
+                    .map(|_| {
+                        if self.chance(25) {
+                            BlockItem::Decl(self.decls(2))
+                        } else {
+                            let closed = self.chance(50);
+                            BlockItem::Stmt(self.stmt(depth - 1, closed))
+                        }
+                    })
+                    .collect(),
+            )
+        }
+
+        /// `closed` 为真时生成的语句可以出现在 `else` 之前，即其中的每个 `if` 都带有 `else`
+        fn stmt(&mut self, depth: u32, closed: bool) -> Stmt {
+            let kind = match if depth > 0 { self.below(10) } else { self.below(6) } {
+                0 => StmtKind::Unit,
+                1 => StmtKind::Exp(self.exp(3)),
+                2 => StmtKind::Assign(LVal(self.ident()), self.exp(3)),
+                3 => StmtKind::Break,
+                4 => StmtKind::Continue,
+                5 => StmtKind::Return(self.chance(50).then(|| self.exp(3))),
+                6 | 7 => StmtKind::Block(self.block(depth)),
+                8 => {
+                    let e = self.exp(3);
+                    if !closed && self.chance(50) {
+                        StmtKind::If(e, Box::new(self.stmt(depth - 1, false)), None)
+                    } else {
+                        let then = Box::new(self.stmt(depth - 1, true));
+                        StmtKind::If(e, then, Some(Box::new(self.stmt(depth - 1, closed))))
+                    }
+                }
+                _ => StmtKind::While(self.exp(3), Box::new(self.stmt(depth - 1, closed))),
+            };
+            Stmt { kind }
+        }
+
+        fn items(&mut self) -> Vec<Item> {
+            (0..1 + self.below(4))
+                .map(|_| {
+                    let kind = if self.chance(30) {
+                        ItemKind::Global(self.decls(3))
+                    } else {
+                        let output = if self.chance(20) { Ty::Void } else { self.ty() };
+                        let params = (0..self.below(3)).map(|_| Param { ident: self.ident(), ty: self.ty() }).collect();
+                        ItemKind::Func(Func::new(self.ident(), output, params, self.block(4), Span::default()))
+                    };
+                    Item { kind }
+                })
+                .collect()
+        }
+    }
+
+    fn round_trip(items: &[Item]) {
+        let source = dump(items);
+        let parsed = CompUnitParser::new()
+            .parse(&source)
+            .unwrap_or_else(|e| panic!("{}\n{}", e, source));
+        assert_eq!(parsed, items, "{}", source);
+    }
+
+    #[test]
+    fn round_trip_sample() {
+        let source = r#"
+            const int N = 0x10, M = 010u;
+            long big = 12345678901;
+            float pi = 3.14159f, tiny = 1e-40, h = 0x1.8p3;
+            int fib(int n) {
+                if (n <= 1) return n; else if (n == 2) { return 1; }
+                else return fib(n - 1) + fib((n - 2));
+            }
+            void f() {
+                int i = 0, s;
+                while (i < N && !(i % 3 == 0) || -i > -M) { i = i + 1; if (i) if (s) ; else break; }
+                { ; }
+                return;
+            }
+        "#;
+        let items = CompUnitParser::new().parse(source).unwrap();
+        round_trip(&items);
+    }
+
+    #[test]
+    fn round_trip_random() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..300 {
+            round_trip(&rng.items());
+        }
+    }
+
+    #[test]
+    fn float_literal() {
+        assert_eq!(literal(&Literal::Float(0.1)), "0.1");
+        assert_eq!(literal(&Literal::Float(12.0)), "12.0");
+        assert_eq!(literal(&Literal::Float(f32::INFINITY)), "0x1p128");
+        assert_eq!(hex_float(f32::from_bits(1)), "0x1p-149");
+    }
+}
@@ -1,94 +1,237 @@
 use super::*;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Exp(pub LOrExp);
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum PrimaryExp {
     Exp(Box<Exp>),
-    Literal(i32),
+    Literal(Literal),
     LVal(LVal),
 }
 
 impl PrimaryExp {
     pub fn literal(src: &str, radix: u32, prefix_len: usize) -> PrimaryExp {
-        PrimaryExp::Literal(
-            i32::from_str_radix(unsafe { src.get_unchecked(prefix_len..) }, radix).unwrap(),
-        )
+        PrimaryExp::Literal(Literal::new(src, radix, prefix_len))
+    }
+
+    pub fn float(src: &str) -> PrimaryExp {
+        PrimaryExp::Literal(Literal::float(src))
+    }
+}
+
+/// 整数或浮点数字面量
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Literal {
+    Int(i32),
+    UInt(u32),
+    Long(i64),
+    Float(f32),
+}
+
+impl Literal {
+    /// 按 C 的规则确定字面量的类型：无后缀的十进制数依次尝试 `int`、`long`，
+    /// 八进制和十六进制数依次尝试 `int`、`unsigned int`、`long`；
+    /// 后缀 `u`/`U` 跳过有符号的 `int`，后缀 `l`/`L` 直接取 `long`。
+    ///
+    /// 没有 `unsigned long`，`ul` 后缀按 `long` 处理。
+    pub fn new(src: &str, radix: u32, prefix_len: usize) -> Literal {
+        let digits = src[prefix_len..].trim_end_matches(['u', 'U', 'l', 'L']);
+        let suffix = &src[prefix_len + digits.len()..];
+        let unsigned = suffix.contains(['u', 'U']);
+        let long = suffix.contains(['l', 'L']);
+        let value = u64::from_str_radix(digits, radix).unwrap_or_else(|_| {
+            panic!("SyntaxError[LiteralOverflow]: '{}' is too large for any integer type.", src)
+        });
+        let value: i64 = value.try_into().unwrap_or_else(|_| {
+            panic!("SyntaxError[LiteralOverflow]: '{}' is too large for any integer type.", src)
+        });
+        if !long {
+            if !unsigned {
+                if let Ok(i) = i32::try_from(value) {
+                    return Literal::Int(i);
+                }
+            }
+            if unsigned || radix != 10 {
+                if let Ok(u) = u32::try_from(value) {
+                    return Literal::UInt(u);
+                }
+            }
+        }
+        Literal::Long(value)
+    }
+
+    /// 十进制或十六进制（`0x1.8p3`）浮点数字面量，后缀 `f`/`F` 可省略
+    ///
+    /// 直接舍入为单精度：先得到 `f64` 再转换会舍入两次，恰好落在两个单精度数中点的结果按偶数舍入，可能与一次舍入不同。
+    pub fn float(src: &str) -> Literal {
+        let src = src.trim_end_matches(['f', 'F']);
+        let value = match src.strip_prefix("0x").or_else(|| src.strip_prefix("0X")) {
+            Some(hex) => parse_hex_float(hex),
+            None => src.parse::<f32>().unwrap(),
+        };
+        Literal::Float(value)
+    }
+
+    pub fn ty(&self) -> Ty {
+        match self {
+            Literal::Int(_) => Ty::Int,
+            Literal::UInt(_) => Ty::UInt,
+            Literal::Long(_) => Ty::Long,
+            Literal::Float(_) => Ty::Float,
+        }
     }
 }
 
-#[derive(Debug)]
+/// 解析去掉 `0x` 前缀的十六进制浮点数 `h.hhhp[+-]d`，值为尾数乘以 2 的指数次幂，按就近偶数舍入为单精度
+fn parse_hex_float(src: &str) -> f32 {
+    let (mantissa, exp) = src.split_once(['p', 'P']).unwrap();
+    let mut exp: i32 = exp.parse().unwrap();
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mut value: u64 = 0;
+    // 超出 `u64` 精度的低位数字是否非零：恰好在中点时决定是否进位
+    let mut sticky = false;
+    for c in int.chars().chain(frac.chars()) {
+        let digit = c.to_digit(16).unwrap() as u64;
+        if value >> 60 == 0 {
+            value = value * 16 + digit;
+        } else {
+            sticky |= digit != 0;
+            exp += 4;
+        }
+    }
+    exp -= 4 * frac.len() as i32;
+    if value == 0 {
+        return 0.0;
+    }
+    // 保留的最低位：规格化数保留 24 位有效数字，非规格化数的最低位为 2^-149
+    let top = 63 - value.leading_zeros() as i32 + exp;
+    let lsb = (top - 23).max(-149);
+    let shift = lsb - exp;
+    let mant = if shift <= 0 {
+        value << -shift
+    } else if shift > 64 {
+        // 小于最小的非规格化数的一半
+        0
+    } else {
+        let (mant, rem) = if shift == 64 { (0, value) } else { (value >> shift, value & ((1 << shift) - 1)) };
+        let half = 1u64 << (shift - 1);
+        let up = rem > half || (rem == half && (sticky || mant & 1 == 1));
+        mant + up as u64
+    };
+    // 舍入后的尾数不超过 2^24，乘以 2 的幂在 `f64` 中是精确的；转换为 `f32` 时超出范围的为无穷大
+    (mant as f64 * 2f64.powi(lsb)) as f32
+}
+
+#[derive(Debug, PartialEq)]
 pub enum UnaryExp {
     Primary(PrimaryExp),
     Unary(UnaryOp, Box<UnaryExp>),
-    Call(String, Vec<Box<Exp>>),
+    Call(Ident, Vec<Box<Exp>>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOp {
     Minus,
     LNot,
 }
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum MulExp {
     Unary(UnaryExp),
     Binary(Box<MulExp>, MulOp, UnaryExp),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MulOp {
     Mul,
     Div,
     Mod,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum AddExp {
     Unary(MulExp),
     Binary(Box<AddExp>, AddOp, MulExp),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddOp {
     Add,
     Sub,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum LOrExp {
     Unary(LAndExp),
     Binary(Box<LOrExp>, LAndExp),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum LAndExp {
     Unary(EqExp),
     Binary(Box<LAndExp>, EqExp),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum EqExp {
     Unary(RelExp),
     Binary(Box<EqExp>, EqOp, RelExp),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EqOp {
     Eq,
     Ne,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum RelExp {
     Unary(AddExp),
     Binary(Box<RelExp>, RelOp, AddExp),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RelOp {
     Lt,
     Gt,
     Le,
     Ge,
 }
+
+#[cfg(test)]
+mod test {
+    use super::Literal;
+
+    fn float(src: &str) -> f32 {
+        match Literal::float(src) {
+            Literal::Float(f) => f,
+            l => panic!("{:?}", l),
+        }
+    }
+
+    #[test]
+    fn float_literals() {
+        let (one_ulp, two_ulp) = (1.0 + f32::EPSILON, 1.0 + 2.0 * f32::EPSILON);
+        assert_eq!(float("1.5f"), 1.5);
+        assert_eq!(float(".5"), 0.5);
+        assert_eq!(float("3e9"), 3e9);
+        assert_eq!(float("0x1.8p3"), 12.0);
+        assert_eq!(float("0X.8P+1F"), 1.0);
+        // 略大于 1 与 `1 + 2^-23` 的中点：先舍入为 `f64` 会恰好落在中点上，再按偶数舍入为 1
+        assert_eq!(float("1.00000005960464477550"), one_ulp);
+        assert_eq!(float("1.000000059604644775390625"), 1.0);
+        // 恰好在中点时按偶数舍入，超出 `u64` 精度的非零低位使之进位
+        assert_eq!(float("0x1.000001p0"), 1.0);
+        assert_eq!(float("0x1.000003p0"), two_ulp);
+        assert_eq!(float("0x1.0000010000000000001p0"), one_ulp);
+        assert_eq!(float("0x1.0000010000000000000p0"), 1.0);
+        // 非规格化数与溢出
+        assert_eq!(float("0x1p-149"), f32::from_bits(1));
+        assert_eq!(float("0x1p-150"), 0.0);
+        assert_eq!(float("0x1.0000000000000000001p-150"), f32::from_bits(1));
+        assert_eq!(float("0x1.8p-149"), f32::from_bits(2));
+        assert_eq!(float("0x1.fffffep127"), f32::MAX);
+        assert_eq!(float("0x1.ffffffp127"), f32::INFINITY);
+        assert_eq!(float("0x0p0"), 0.0);
+    }
+}
@@ -0,0 +1,203 @@
+//! 以取得所有权的方式重建 AST，用于改写结点的种类
+//!
+//! [`Fold`] 的每个方法默认调用同名的自由函数，后者折叠所有子结点后原样重建该结点。
+
+use super::*;
+
+pub trait Fold {
+    fn fold_item(&mut self, i: Item) -> Item {
+        fold_item(self, i)
+    }
+    fn fold_func(&mut self, f: Func) -> Func {
+        fold_func(self, f)
+    }
+    fn fold_param(&mut self, p: Param) -> Param {
+        p
+    }
+    fn fold_block(&mut self, b: Block) -> Block {
+        fold_block(self, b)
+    }
+    fn fold_block_item(&mut self, i: BlockItem) -> BlockItem {
+        fold_block_item(self, i)
+    }
+    fn fold_decl(&mut self, d: Decl) -> Decl {
+        fold_decl(self, d)
+    }
+    fn fold_stmt(&mut self, s: Stmt) -> Stmt {
+        fold_stmt(self, s)
+    }
+    fn fold_lval(&mut self, l: LVal) -> LVal {
+        l
+    }
+    fn fold_exp(&mut self, e: Exp) -> Exp {
+        fold_exp(self, e)
+    }
+    fn fold_lor_exp(&mut self, e: LOrExp) -> LOrExp {
+        fold_lor_exp(self, e)
+    }
+    fn fold_land_exp(&mut self, e: LAndExp) -> LAndExp {
+        fold_land_exp(self, e)
+    }
+    fn fold_eq_exp(&mut self, e: EqExp) -> EqExp {
+        fold_eq_exp(self, e)
+    }
+    fn fold_rel_exp(&mut self, e: RelExp) -> RelExp {
+        fold_rel_exp(self, e)
+    }
+    fn fold_add_exp(&mut self, e: AddExp) -> AddExp {
+        fold_add_exp(self, e)
+    }
+    fn fold_mul_exp(&mut self, e: MulExp) -> MulExp {
+        fold_mul_exp(self, e)
+    }
+    fn fold_unary_exp(&mut self, e: UnaryExp) -> UnaryExp {
+        fold_unary_exp(self, e)
+    }
+    fn fold_primary_exp(&mut self, e: PrimaryExp) -> PrimaryExp {
+        fold_primary_exp(self, e)
+    }
+    fn fold_literal(&mut self, l: Literal) -> Literal {
+        l
+    }
+}
+
+pub fn fold_item<F: Fold + ?Sized>(f: &mut F, i: Item) -> Item {
+    let kind = match i.kind {
+        ItemKind::Global(decls) => ItemKind::Global(decls.into_iter().map(|d| f.fold_decl(d)).collect()),
+        ItemKind::Func(func) => ItemKind::Func(f.fold_func(func)),
+    };
+    Item { kind }
+}
+
+pub fn fold_func<F: Fold + ?Sized>(f: &mut F, func: Func) -> Func {
+    Func {
+        ident: func.ident,
+        output: func.output,
+        params: func.params.into_iter().map(|p| f.fold_param(p)).collect(),
+        block: f.fold_block(func.block),
+        span: func.span,
+    }
+}
+
+pub fn fold_block<F: Fold + ?Sized>(f: &mut F, b: Block) -> Block {
+    Block(b.0.into_iter().map(|i| f.fold_block_item(i)).collect())
+}
+
+pub fn fold_block_item<F: Fold + ?Sized>(f: &mut F, i: BlockItem) -> BlockItem {
+    match i {
+        BlockItem::Stmt(s) => BlockItem::Stmt(f.fold_stmt(s)),
+        BlockItem::Decl(decls) => BlockItem::Decl(decls.into_iter().map(|d| f.fold_decl(d)).collect()),
+    }
+}
+
+pub fn fold_decl<F: Fold + ?Sized>(f: &mut F, d: Decl) -> Decl {
+    Decl {
+        exp: d.exp.map(|e| f.fold_exp(e)),
+        ..d
+    }
+}
+
+pub fn fold_stmt<F: Fold + ?Sized>(f: &mut F, s: Stmt) -> Stmt {
+    let kind = match s.kind {
+        k @ (StmtKind::Unit | StmtKind::Break | StmtKind::Continue) => k,
+        StmtKind::Exp(e) => StmtKind::Exp(f.fold_exp(e)),
+        StmtKind::Block(b) => StmtKind::Block(f.fold_block(b)),
+        StmtKind::Assign(l, e) => {
+            let l = f.fold_lval(l);
+            StmtKind::Assign(l, f.fold_exp(e))
+        }
+        StmtKind::If(e, then, alt) => {
+            let e = f.fold_exp(e);
+            let then = Box::new(f.fold_stmt(*then));
+            StmtKind::If(e, then, alt.map(|alt| Box::new(f.fold_stmt(*alt))))
+        }
+        StmtKind::While(e, body) => {
+            let e = f.fold_exp(e);
+            StmtKind::While(e, Box::new(f.fold_stmt(*body)))
+        }
+        StmtKind::Return(e) => StmtKind::Return(e.map(|e| f.fold_exp(e))),
+    };
+    Stmt { kind }
+}
+
+pub fn fold_exp<F: Fold + ?Sized>(f: &mut F, e: Exp) -> Exp {
+    Exp(f.fold_lor_exp(e.0))
+}
+
+pub fn fold_lor_exp<F: Fold + ?Sized>(f: &mut F, e: LOrExp) -> LOrExp {
+    match e {
+        LOrExp::Unary(r) => LOrExp::Unary(f.fold_land_exp(r)),
+        LOrExp::Binary(l, r) => {
+            let l = Box::new(f.fold_lor_exp(*l));
+            LOrExp::Binary(l, f.fold_land_exp(r))
+        }
+    }
+}
+
+pub fn fold_land_exp<F: Fold + ?Sized>(f: &mut F, e: LAndExp) -> LAndExp {
+    match e {
+        LAndExp::Unary(r) => LAndExp::Unary(f.fold_eq_exp(r)),
+        LAndExp::Binary(l, r) => {
+            let l = Box::new(f.fold_land_exp(*l));
+            LAndExp::Binary(l, f.fold_eq_exp(r))
+        }
+    }
+}
+
+pub fn fold_eq_exp<F: Fold + ?Sized>(f: &mut F, e: EqExp) -> EqExp {
+    match e {
+        EqExp::Unary(r) => EqExp::Unary(f.fold_rel_exp(r)),
+        EqExp::Binary(l, op, r) => {
+            let l = Box::new(f.fold_eq_exp(*l));
+            EqExp::Binary(l, op, f.fold_rel_exp(r))
+        }
+    }
+}
+
+pub fn fold_rel_exp<F: Fold + ?Sized>(f: &mut F, e: RelExp) -> RelExp {
+    match e {
+        RelExp::Unary(r) => RelExp::Unary(f.fold_add_exp(r)),
+        RelExp::Binary(l, op, r) => {
+            let l = Box::new(f.fold_rel_exp(*l));
+            RelExp::Binary(l, op, f.fold_add_exp(r))
+        }
+    }
+}
+
+pub fn fold_add_exp<F: Fold + ?Sized>(f: &mut F, e: AddExp) -> AddExp {
+    match e {
+        AddExp::Unary(r) => AddExp::Unary(f.fold_mul_exp(r)),
+        AddExp::Binary(l, op, r) => {
+            let l = Box::new(f.fold_add_exp(*l));
+            AddExp::Binary(l, op, f.fold_mul_exp(r))
+        }
+    }
+}
+
+pub fn fold_mul_exp<F: Fold + ?Sized>(f: &mut F, e: MulExp) -> MulExp {
+    match e {
+        MulExp::Unary(r) => MulExp::Unary(f.fold_unary_exp(r)),
+        MulExp::Binary(l, op, r) => {
+            let l = Box::new(f.fold_mul_exp(*l));
+            MulExp::Binary(l, op, f.fold_unary_exp(r))
+        }
+    }
+}
+
+pub fn fold_unary_exp<F: Fold + ?Sized>(f: &mut F, e: UnaryExp) -> UnaryExp {
+    match e {
+        UnaryExp::Primary(p) => UnaryExp::Primary(f.fold_primary_exp(p)),
+        UnaryExp::Unary(op, e) => UnaryExp::Unary(op, Box::new(f.fold_unary_exp(*e))),
+        UnaryExp::Call(ident, args) => {
+            UnaryExp::Call(ident, args.into_iter().map(|a| Box::new(f.fold_exp(*a))).collect())
+        }
+    }
+}
+
+pub fn fold_primary_exp<F: Fold + ?Sized>(f: &mut F, e: PrimaryExp) -> PrimaryExp {
+    match e {
+        PrimaryExp::Exp(e) => PrimaryExp::Exp(Box::new(f.fold_exp(*e))),
+        PrimaryExp::Literal(l) => PrimaryExp::Literal(f.fold_literal(l)),
+        PrimaryExp::LVal(l) => PrimaryExp::LVal(f.fold_lval(l)),
+    }
+}
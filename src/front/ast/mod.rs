@@ -1,12 +1,15 @@
 use koopa::ir;
-use std::ops::{Deref, DerefMut};
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Item {
     pub kind: ItemKind,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum ItemKind {
     /// Global const/variable declaration
     Global(Vec<Decl>),
@@ -15,28 +18,95 @@ pub enum ItemKind {
     Func(Func),
 }
 
+/// 比较时不看位置，见 [`Ident`]
 #[derive(Debug)]
 pub struct Func {
-    pub ident: String,
+    pub ident: Ident,
     pub output: Ty,
     pub params: Vec<Param>,
     pub block: Block,
+    /// 从返回类型到右花括号的整个定义
+    pub span: Span,
 }
 
 impl Func {
-    pub fn new(ident: String, output: Ty, params: Vec<Param>, block: Block) -> Func {
+    pub fn new(ident: Ident, output: Ty, params: Vec<Param>, block: Block, span: Span) -> Func {
         Func {
             ident,
             output,
             params,
             block,
+            span,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl PartialEq for Func {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident == other.ident
+            && self.output == other.output
+            && self.params == other.params
+            && self.block == other.block
+    }
+}
+
+/// 源代码中的字节区间 `[lo, hi)`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub lo: usize,
+    pub hi: usize,
+}
+
+impl Span {
+    /// `offset` 落在区间内，紧跟在区间之后也算
+    pub fn contains(&self, offset: usize) -> bool {
+        (self.lo..=self.hi).contains(&offset)
+    }
+}
+
+/// 标识符及其位置
+///
+/// 比较时只看名字，以便比较由不同源代码得到的 AST。
+#[derive(Debug, Clone)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+impl Ident {
+    pub fn new(name: impl Into<String>, span: Span) -> Ident {
+        Ident { name: name.into(), span }
+    }
+}
+
+impl PartialEq for Ident {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Deref for Ident {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Ty {
     Int,
+    /// `unsigned int`
+    UInt,
+    /// 64 位 `long`，在 Koopa 中拆为低、高两个 `i32` 字
+    Long,
+    /// 单精度浮点数，在 Koopa 中以 `i32` 保存其位模式
+    Float,
     Void,
 }
 
@@ -44,28 +114,61 @@ impl Ty {
     pub fn new(ty: &str) -> Ty {
         match ty {
             "int" => Ty::Int,
+            "unsigned" | "unsigned int" => Ty::UInt,
+            "long" | "long int" => Ty::Long,
+            "float" => Ty::Float,
             "void" => Ty::Void,
             _ => unreachable!(),
         }
     }
+
+    /// 该类型在 Koopa 中占据的 `i32` 字数
+    pub fn words(&self) -> usize {
+        match self {
+            Ty::Int | Ty::UInt | Ty::Float => 1,
+            Ty::Long => 2,
+            Ty::Void => 0,
+        }
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(self, Ty::UInt)
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Ty::Float)
+    }
+
+    /// C 的寻常算术转换（usual arithmetic conversions）：两个操作数转换到的公共类型
+    pub fn common(lhs: Ty, rhs: Ty) -> Ty {
+        use Ty::*;
+        match (lhs, rhs) {
+            (Void, _) | (_, Void) => panic!("SemanticsError[VoidOperand]: void value used in an expression."),
+            (Float, _) | (_, Float) => Float,
+            (Long, _) | (_, Long) => Long,
+            (UInt, _) | (_, UInt) => UInt,
+            _ => Int,
+        }
+    }
 }
 
+/// 单个 Koopa 值的类型，`long` 为其低位字的类型，`float` 为其位模式的类型
 impl From<&Ty> for ir::Type {
     fn from(t: &Ty) -> Self {
         match t {
-            Ty::Int => ty!(i32),
+            Ty::Int | Ty::UInt | Ty::Long | Ty::Float => ty!(i32),
             Ty::Void => ty!(()),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum BlockItem {
     Stmt(Stmt),
     Decl(Vec<Decl>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Stmt {
     pub kind: StmtKind,
 }
@@ -83,7 +186,7 @@ impl DerefMut for Stmt {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum StmtKind {
     Unit,
     Exp(Exp),
@@ -100,29 +203,29 @@ pub enum StmtKind {
 //     unimplemented!()
 // }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SymKind {
     Var,
     Const,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Block(pub Vec<BlockItem>);
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Decl {
-    pub ident: String,
+    pub ident: Ident,
     pub ty: Ty,
     pub kind: SymKind,
     pub exp: Option<Exp>,
 }
 
-#[derive(Debug)]
-pub struct LVal(pub String);
+#[derive(Debug, PartialEq)]
+pub struct LVal(pub Ident);
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Param {
-    pub ident: String,
+    pub ident: Ident,
     pub ty: Ty,
 }
 
@@ -130,3 +233,135 @@ pub use exp::*;
 
 use crate::ty;
 mod exp;
+
+pub mod dump;
+pub mod fold;
+pub mod visit;
+pub mod visit_mut;
+
+#[cfg(test)]
+mod test {
+    use super::{fold::Fold, visit::*, visit_mut::*, *};
+    use crate::front::parser::CompUnitParser;
+
+    /// 每种语句与表达式都至少出现一次，字面量按遍历顺序编号
+    const SAMPLE: &str = r#"
+const int N = 1, M = 2;
+int x = 3;
+int x(int x, float b) {
+    int y = 4, z;
+    x = x + 5 * (b - 6) / 7 % 8;
+    if (x < 9 || y > 10 && N <= 11) y = 12; else { z = 13; }
+    while (x >= 14 == (y != 15)) { x = -x; if (!M) break; continue; }
+    x(16, x);
+    ;
+    return 17;
+}
+"#;
+
+    fn parse(source: &str) -> Vec<Item> {
+        CompUnitParser::new().parse(source).unwrap()
+    }
+
+    #[derive(Default)]
+    struct Collect {
+        params: Vec<String>,
+        decls: Vec<String>,
+        stmts: usize,
+        lvals: Vec<String>,
+        calls: Vec<String>,
+        literals: Vec<i32>,
+    }
+
+    impl<'ast> Visit<'ast> for Collect {
+        fn visit_param(&mut self, p: &'ast Param) {
+            self.params.push(p.ident.name.clone());
+        }
+        fn visit_decl(&mut self, d: &'ast Decl) {
+            self.decls.push(d.ident.name.clone());
+            walk_decl(self, d)
+        }
+        fn visit_stmt(&mut self, s: &'ast Stmt) {
+            self.stmts += 1;
+            walk_stmt(self, s)
+        }
+        fn visit_lval(&mut self, l: &'ast LVal) {
+            // 标识符的位置即其在源代码中的字节区间
+            assert_eq!(&SAMPLE[l.0.span.lo..l.0.span.hi], l.0.name);
+            self.lvals.push(l.0.name.clone());
+        }
+        fn visit_unary_exp(&mut self, e: &'ast UnaryExp) {
+            if let UnaryExp::Call(ident, _) = e {
+                self.calls.push(ident.name.clone());
+            }
+            walk_unary_exp(self, e)
+        }
+        fn visit_literal(&mut self, l: &'ast Literal) {
+            let Literal::Int(i) = l else { unreachable!() };
+            self.literals.push(*i);
+        }
+    }
+
+    #[test]
+    fn visit() {
+        let items = parse(SAMPLE);
+        let mut c = Collect::default();
+        items.iter().for_each(|i| c.visit_item(i));
+        assert_eq!(c.params, ["x", "b"]);
+        assert_eq!(c.decls, ["N", "M", "x", "y", "z"]);
+        assert_eq!(c.stmts, 14);
+        assert_eq!(c.lvals, ["x", "x", "b", "x", "y", "N", "y", "z", "x", "y", "x", "x", "M", "x"]);
+        assert_eq!(c.calls, ["x"]);
+        assert_eq!(c.literals, (1..=17).collect::<Vec<_>>());
+    }
+
+    /// 将所有的 `x` 改名为 `w`
+    struct Rename;
+
+    impl VisitMut for Rename {
+        fn visit_func_mut(&mut self, f: &mut Func) {
+            rename(&mut f.ident);
+            walk_func_mut(self, f)
+        }
+        fn visit_param_mut(&mut self, p: &mut Param) {
+            rename(&mut p.ident);
+        }
+        fn visit_decl_mut(&mut self, d: &mut Decl) {
+            rename(&mut d.ident);
+            walk_decl_mut(self, d)
+        }
+        fn visit_lval_mut(&mut self, l: &mut LVal) {
+            rename(&mut l.0);
+        }
+        fn visit_unary_exp_mut(&mut self, e: &mut UnaryExp) {
+            if let UnaryExp::Call(ident, _) = e {
+                rename(ident);
+            }
+            walk_unary_exp_mut(self, e)
+        }
+    }
+
+    fn rename(ident: &mut Ident) {
+        if ident.name == "x" {
+            ident.name = "w".to_string();
+        }
+    }
+
+    #[test]
+    fn visit_mut() {
+        let mut items = parse(SAMPLE);
+        items.iter_mut().for_each(|i| Rename.visit_item_mut(i));
+        assert_eq!(items, parse(&SAMPLE.replace('x', "w")));
+    }
+
+    struct Identity;
+
+    impl Fold for Identity {}
+
+    #[test]
+    fn fold() {
+        let items: Vec<Item> = parse(SAMPLE).into_iter().map(|i| Identity.fold_item(i)).collect();
+        assert_eq!(items, parse(SAMPLE));
+        assert_eq!(parse(&dump::dump(&items, dump::Format::Sysy)), items);
+    }
+}
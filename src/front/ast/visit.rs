@@ -0,0 +1,189 @@
+//! 以只读方式遍历 AST
+//!
+//! [`Visit`] 的每个方法默认调用同名的 `walk_*` 函数，后者依次访问该结点的所有子结点。
+//! 只需覆盖关心的方法；若仍需深入子结点，在覆盖的方法中调用对应的 `walk_*` 即可。
+
+use super::*;
+
+pub trait Visit<'ast> {
+    fn visit_item(&mut self, i: &'ast Item) {
+        walk_item(self, i)
+    }
+    fn visit_func(&mut self, f: &'ast Func) {
+        walk_func(self, f)
+    }
+    fn visit_param(&mut self, _p: &'ast Param) {}
+    fn visit_block(&mut self, b: &'ast Block) {
+        walk_block(self, b)
+    }
+    fn visit_block_item(&mut self, i: &'ast BlockItem) {
+        walk_block_item(self, i)
+    }
+    fn visit_decl(&mut self, d: &'ast Decl) {
+        walk_decl(self, d)
+    }
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        walk_stmt(self, s)
+    }
+    fn visit_lval(&mut self, _l: &'ast LVal) {}
+    fn visit_exp(&mut self, e: &'ast Exp) {
+        walk_exp(self, e)
+    }
+    fn visit_lor_exp(&mut self, e: &'ast LOrExp) {
+        walk_lor_exp(self, e)
+    }
+    fn visit_land_exp(&mut self, e: &'ast LAndExp) {
+        walk_land_exp(self, e)
+    }
+    fn visit_eq_exp(&mut self, e: &'ast EqExp) {
+        walk_eq_exp(self, e)
+    }
+    fn visit_rel_exp(&mut self, e: &'ast RelExp) {
+        walk_rel_exp(self, e)
+    }
+    fn visit_add_exp(&mut self, e: &'ast AddExp) {
+        walk_add_exp(self, e)
+    }
+    fn visit_mul_exp(&mut self, e: &'ast MulExp) {
+        walk_mul_exp(self, e)
+    }
+    fn visit_unary_exp(&mut self, e: &'ast UnaryExp) {
+        walk_unary_exp(self, e)
+    }
+    fn visit_primary_exp(&mut self, e: &'ast PrimaryExp) {
+        walk_primary_exp(self, e)
+    }
+    fn visit_literal(&mut self, _l: &'ast Literal) {}
+}
+
+pub fn walk_item<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, i: &'ast Item) {
+    match &i.kind {
+        ItemKind::Global(decls) => decls.iter().for_each(|d| v.visit_decl(d)),
+        ItemKind::Func(f) => v.visit_func(f),
+    }
+}
+
+pub fn walk_func<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, f: &'ast Func) {
+    f.params.iter().for_each(|p| v.visit_param(p));
+    v.visit_block(&f.block);
+}
+
+pub fn walk_block<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, b: &'ast Block) {
+    b.0.iter().for_each(|i| v.visit_block_item(i));
+}
+
+pub fn walk_block_item<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, i: &'ast BlockItem) {
+    match i {
+        BlockItem::Stmt(s) => v.visit_stmt(s),
+        BlockItem::Decl(decls) => decls.iter().for_each(|d| v.visit_decl(d)),
+    }
+}
+
+pub fn walk_decl<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, d: &'ast Decl) {
+    if let Some(e) = &d.exp {
+        v.visit_exp(e);
+    }
+}
+
+pub fn walk_stmt<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, s: &'ast Stmt) {
+    match &s.kind {
+        StmtKind::Unit | StmtKind::Break | StmtKind::Continue | StmtKind::Return(None) => {}
+        StmtKind::Exp(e) | StmtKind::Return(Some(e)) => v.visit_exp(e),
+        StmtKind::Block(b) => v.visit_block(b),
+        StmtKind::Assign(l, e) => {
+            v.visit_lval(l);
+            v.visit_exp(e);
+        }
+        StmtKind::If(e, then, alt) => {
+            v.visit_exp(e);
+            v.visit_stmt(then);
+            if let Some(alt) = alt {
+                v.visit_stmt(alt);
+            }
+        }
+        StmtKind::While(e, body) => {
+            v.visit_exp(e);
+            v.visit_stmt(body);
+        }
+    }
+}
+
+pub fn walk_exp<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast Exp) {
+    v.visit_lor_exp(&e.0);
+}
+
+pub fn walk_lor_exp<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast LOrExp) {
+    match e {
+        LOrExp::Unary(r) => v.visit_land_exp(r),
+        LOrExp::Binary(l, r) => {
+            v.visit_lor_exp(l);
+            v.visit_land_exp(r);
+        }
+    }
+}
+
+pub fn walk_land_exp<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast LAndExp) {
+    match e {
+        LAndExp::Unary(r) => v.visit_eq_exp(r),
+        LAndExp::Binary(l, r) => {
+            v.visit_land_exp(l);
+            v.visit_eq_exp(r);
+        }
+    }
+}
+
+pub fn walk_eq_exp<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast EqExp) {
+    match e {
+        EqExp::Unary(r) => v.visit_rel_exp(r),
+        EqExp::Binary(l, _, r) => {
+            v.visit_eq_exp(l);
+            v.visit_rel_exp(r);
+        }
+    }
+}
+
+pub fn walk_rel_exp<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast RelExp) {
+    match e {
+        RelExp::Unary(r) => v.visit_add_exp(r),
+        RelExp::Binary(l, _, r) => {
+            v.visit_rel_exp(l);
+            v.visit_add_exp(r);
+        }
+    }
+}
+
+pub fn walk_add_exp<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast AddExp) {
+    match e {
+        AddExp::Unary(r) => v.visit_mul_exp(r),
+        AddExp::Binary(l, _, r) => {
+            v.visit_add_exp(l);
+            v.visit_mul_exp(r);
+        }
+    }
+}
+
+pub fn walk_mul_exp<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast MulExp) {
+    match e {
+        MulExp::Unary(r) => v.visit_unary_exp(r),
+        MulExp::Binary(l, _, r) => {
+            v.visit_mul_exp(l);
+            v.visit_unary_exp(r);
+        }
+    }
+}
+
+pub fn walk_unary_exp<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast UnaryExp) {
+    match e {
+        UnaryExp::Primary(p) => v.visit_primary_exp(p),
+        UnaryExp::Unary(_, e) => v.visit_unary_exp(e),
+        UnaryExp::Call(_, args) => args.iter().for_each(|a| v.visit_exp(a)),
+    }
+}
+
+pub fn walk_primary_exp<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast PrimaryExp) {
+    match e {
+        PrimaryExp::Exp(e) => v.visit_exp(e),
+        PrimaryExp::Literal(l) => v.visit_literal(l),
+        PrimaryExp::LVal(l) => v.visit_lval(l),
+    }
+}
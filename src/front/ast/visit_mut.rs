@@ -0,0 +1,188 @@
+//! 以可变方式遍历 AST，用于原地改写
+//!
+//! 与 [`super::visit::Visit`] 一一对应，方法名带有 `_mut` 后缀。
+
+use super::*;
+
+pub trait VisitMut {
+    fn visit_item_mut(&mut self, i: &mut Item) {
+        walk_item_mut(self, i)
+    }
+    fn visit_func_mut(&mut self, f: &mut Func) {
+        walk_func_mut(self, f)
+    }
+    fn visit_param_mut(&mut self, _p: &mut Param) {}
+    fn visit_block_mut(&mut self, b: &mut Block) {
+        walk_block_mut(self, b)
+    }
+    fn visit_block_item_mut(&mut self, i: &mut BlockItem) {
+        walk_block_item_mut(self, i)
+    }
+    fn visit_decl_mut(&mut self, d: &mut Decl) {
+        walk_decl_mut(self, d)
+    }
+    fn visit_stmt_mut(&mut self, s: &mut Stmt) {
+        walk_stmt_mut(self, s)
+    }
+    fn visit_lval_mut(&mut self, _l: &mut LVal) {}
+    fn visit_exp_mut(&mut self, e: &mut Exp) {
+        walk_exp_mut(self, e)
+    }
+    fn visit_lor_exp_mut(&mut self, e: &mut LOrExp) {
+        walk_lor_exp_mut(self, e)
+    }
+    fn visit_land_exp_mut(&mut self, e: &mut LAndExp) {
+        walk_land_exp_mut(self, e)
+    }
+    fn visit_eq_exp_mut(&mut self, e: &mut EqExp) {
+        walk_eq_exp_mut(self, e)
+    }
+    fn visit_rel_exp_mut(&mut self, e: &mut RelExp) {
+        walk_rel_exp_mut(self, e)
+    }
+    fn visit_add_exp_mut(&mut self, e: &mut AddExp) {
+        walk_add_exp_mut(self, e)
+    }
+    fn visit_mul_exp_mut(&mut self, e: &mut MulExp) {
+        walk_mul_exp_mut(self, e)
+    }
+    fn visit_unary_exp_mut(&mut self, e: &mut UnaryExp) {
+        walk_unary_exp_mut(self, e)
+    }
+    fn visit_primary_exp_mut(&mut self, e: &mut PrimaryExp) {
+        walk_primary_exp_mut(self, e)
+    }
+    fn visit_literal_mut(&mut self, _l: &mut Literal) {}
+}
+
+pub fn walk_item_mut<V: VisitMut + ?Sized>(v: &mut V, i: &mut Item) {
+    match &mut i.kind {
+        ItemKind::Global(decls) => decls.iter_mut().for_each(|d| v.visit_decl_mut(d)),
+        ItemKind::Func(f) => v.visit_func_mut(f),
+    }
+}
+
+pub fn walk_func_mut<V: VisitMut + ?Sized>(v: &mut V, f: &mut Func) {
+    f.params.iter_mut().for_each(|p| v.visit_param_mut(p));
+    v.visit_block_mut(&mut f.block);
+}
+
+pub fn walk_block_mut<V: VisitMut + ?Sized>(v: &mut V, b: &mut Block) {
+    b.0.iter_mut().for_each(|i| v.visit_block_item_mut(i));
+}
+
+pub fn walk_block_item_mut<V: VisitMut + ?Sized>(v: &mut V, i: &mut BlockItem) {
+    match i {
+        BlockItem::Stmt(s) => v.visit_stmt_mut(s),
+        BlockItem::Decl(decls) => decls.iter_mut().for_each(|d| v.visit_decl_mut(d)),
+    }
+}
+
+pub fn walk_decl_mut<V: VisitMut + ?Sized>(v: &mut V, d: &mut Decl) {
+    if let Some(e) = &mut d.exp {
+        v.visit_exp_mut(e);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitMut + ?Sized>(v: &mut V, s: &mut Stmt) {
+    match &mut s.kind {
+        StmtKind::Unit | StmtKind::Break | StmtKind::Continue | StmtKind::Return(None) => {}
+        StmtKind::Exp(e) | StmtKind::Return(Some(e)) => v.visit_exp_mut(e),
+        StmtKind::Block(b) => v.visit_block_mut(b),
+        StmtKind::Assign(l, e) => {
+            v.visit_lval_mut(l);
+            v.visit_exp_mut(e);
+        }
+        StmtKind::If(e, then, alt) => {
+            v.visit_exp_mut(e);
+            v.visit_stmt_mut(then);
+            if let Some(alt) = alt {
+                v.visit_stmt_mut(alt);
+            }
+        }
+        StmtKind::While(e, body) => {
+            v.visit_exp_mut(e);
+            v.visit_stmt_mut(body);
+        }
+    }
+}
+
+pub fn walk_exp_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut Exp) {
+    v.visit_lor_exp_mut(&mut e.0);
+}
+
+pub fn walk_lor_exp_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut LOrExp) {
+    match e {
+        LOrExp::Unary(r) => v.visit_land_exp_mut(r),
+        LOrExp::Binary(l, r) => {
+            v.visit_lor_exp_mut(l);
+            v.visit_land_exp_mut(r);
+        }
+    }
+}
+
+pub fn walk_land_exp_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut LAndExp) {
+    match e {
+        LAndExp::Unary(r) => v.visit_eq_exp_mut(r),
+        LAndExp::Binary(l, r) => {
+            v.visit_land_exp_mut(l);
+            v.visit_eq_exp_mut(r);
+        }
+    }
+}
+
+pub fn walk_eq_exp_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut EqExp) {
+    match e {
+        EqExp::Unary(r) => v.visit_rel_exp_mut(r),
+        EqExp::Binary(l, _, r) => {
+            v.visit_eq_exp_mut(l);
+            v.visit_rel_exp_mut(r);
+        }
+    }
+}
+
+pub fn walk_rel_exp_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut RelExp) {
+    match e {
+        RelExp::Unary(r) => v.visit_add_exp_mut(r),
+        RelExp::Binary(l, _, r) => {
+            v.visit_rel_exp_mut(l);
+            v.visit_add_exp_mut(r);
+        }
+    }
+}
+
+pub fn walk_add_exp_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut AddExp) {
+    match e {
+        AddExp::Unary(r) => v.visit_mul_exp_mut(r),
+        AddExp::Binary(l, _, r) => {
+            v.visit_add_exp_mut(l);
+            v.visit_mul_exp_mut(r);
+        }
+    }
+}
+
+pub fn walk_mul_exp_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut MulExp) {
+    match e {
+        MulExp::Unary(r) => v.visit_unary_exp_mut(r),
+        MulExp::Binary(l, _, r) => {
+            v.visit_mul_exp_mut(l);
+            v.visit_unary_exp_mut(r);
+        }
+    }
+}
+
+pub fn walk_unary_exp_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut UnaryExp) {
+    match e {
+        UnaryExp::Primary(p) => v.visit_primary_exp_mut(p),
+        UnaryExp::Unary(_, e) => v.visit_unary_exp_mut(e),
+        UnaryExp::Call(_, args) => args.iter_mut().for_each(|a| v.visit_exp_mut(a)),
+    }
+}
+
+pub fn walk_primary_exp_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut PrimaryExp) {
+    match e {
+        PrimaryExp::Exp(e) => v.visit_exp_mut(e),
+        PrimaryExp::Literal(l) => v.visit_literal_mut(l),
+        PrimaryExp::LVal(l) => v.visit_lval_mut(l),
+    }
+}
@@ -4,12 +4,14 @@ use koopa::ir::{self, builder_traits::*};
 
 use crate::{util::autonum::Autonum, WrapProgram};
 
-use super::symtab::{Symtab, FuncTab, ValTab, FetchVal};
+use super::{hir::{self, SymId}, symtab::{Symtab, FuncTab, ValTab, Sym}};
 
 /// Context is a high-level [`koopa::ir::Program`] wrapper around a [`koopa::ir::Function`]
-/// with its symbol table [`Table`].
+/// with its symbol table [`Symtab`].
 pub struct Context<'a> {
     pub program: &'a mut ir::Program,
+    /// The HIR program being lowered, for symbol and function information
+    pub hir: &'a hir::Program,
     // pub globals: &'a mut Symtab,
     pub func: ir::Function,
     table: Symtab<'a>,
@@ -43,30 +45,22 @@ impl<'a> WrapProgram for Context<'a> {
     }
 }
 
-impl<'a> FetchVal<'a> for Context<'a> {
-    fn fetch_val(&self, name: &str) -> Option<ir::Value> {
-        self.table().get_val(name)
-    }
-
-    fn fetch_val_kind(&self, val: ir::Value) -> ir::entities::ValueKind {
-        self.value(val).kind().clone()
-    }
-}
-
 impl<'a: 'f, 'f> Context<'a> {
     pub fn new(
         program: &'a mut ir::Program,
+        hir: &'a hir::Program,
         func_tab: &'a mut FuncTab,
         global_val_tab: &'a mut ValTab,
         func: ir::Function,
     ) -> Context<'a> {
-        let mut this = Context::from(program, func_tab, global_val_tab, func).unwrap();
+        let mut this = Context::from(program, hir, func_tab, global_val_tab, func).unwrap();
         this.init();
         this
     }
 
     fn from(
         program: &'a mut ir::Program,
+        hir: &'a hir::Program,
         func_tab: &'a mut FuncTab,
         global_val_tab: &'a mut ValTab,
         func: ir::Function,
@@ -81,6 +75,7 @@ impl<'a: 'f, 'f> Context<'a> {
 
         Ok(Context {
             program,
+            hir,
             // globals,
             func,
             entry: None,
@@ -278,17 +273,6 @@ impl<'a> WrapProgram for GlobalContext<'a> {
     }
 }
 
-impl<'a> FetchVal<'a> for GlobalContext<'a> {
-    fn fetch_val(&self, name: &str) -> Option<ir::Value> {
-        dbg!(&self.global);
-        self.global.get(name).cloned()
-    }
-
-    fn fetch_val_kind(&self, val: ir::Value) -> ir::entities::ValueKind {
-        self.program.borrow_value(val).kind().clone()
-    }
-}
-
 impl<'a> GlobalContext<'a> {
     pub fn new(
         program: &'a mut ir::Program,
@@ -311,8 +295,7 @@ impl<'a> GlobalContext<'a> {
         val
     }
 
-    pub fn register_global_value(&mut self, name: &str, value: ir::Value) {
-        self.global
-            .insert(name.to_string(), value);
+    pub fn register_global_value(&mut self, id: SymId, sym: Sym) {
+        self.global.insert(id, sym);
     }
 }
@@ -1,77 +1,87 @@
-use koopa::ir::{self, builder_traits::*, Program};
+use std::collections::HashMap;
 
-use std::iter::zip;
+use koopa::ir::{self, builder_traits::*, Program};
 
-use crate::{WrapProgram, front::context::GlobalContext, ty};
+use crate::{WrapProgram, front::context::GlobalContext, ty, util::intrinsic::Intrinsic};
 
-use super::{ast, context::Context, gen::Generate, symtab::{FuncTab, ValTab}};
+use super::{ast, context::Context, gen::{self, Generate, TyVal}, hir, symtab::{FuncSym, FuncTab, Sym, ValTab}};
 
 
-/// [`Declare`] 处理 AST 中的条目（[`ast::Item`]）：全局常量、变量声明和函数，并为每一个函数生成上下文（[`Context`]）
+/// [`Declare`] 处理 HIR 中的条目（[`hir::Item`]）：全局常量、变量声明和函数，并为每一个函数生成上下文（[`Context`]）
 pub trait Declare<'a> {
-    fn declare(&self, program: &'a mut Program, func_tab: &'a mut FuncTab, global_val_tab: &'a mut ValTab);
+    /// `long_halves` 记录 `long` 局部变量高位字的 `alloc` 对应的低位字的 `alloc`
+    fn declare(
+        &self,
+        program: &'a mut Program,
+        hir: &'a hir::Program,
+        func_tab: &'a mut FuncTab,
+        global_val_tab: &'a mut ValTab,
+        long_halves: &mut HashMap<ir::Value, ir::Value>,
+    );
 }
 
-impl<'a> Declare<'a> for ast::Item {
-    fn declare(&self, program: &'a mut Program, func_tab: &'a mut FuncTab, global_val_tab: &'a mut ValTab) {
-        use ast::ItemKind::*;
-        use koopa::ir::{ValueKind, TypeKind};
-        match &self.kind {
-            Global(decls) => {
-                use crate::front::{
-                    gen::eval::Eval,
-                    ast::SymKind
-                };
-                for d in decls {
-                    let mut ctx = GlobalContext::new(program, global_val_tab);
-                    match d.kind {
-                        SymKind::Const => {
-                            dbg!(d.exp.as_ref());
-                            let val = d.exp.as_ref().unwrap().eval(&ctx).unwrap();
-                            let const_val = ctx.add_global_value(val!(integer(val)), None);
-                            let alloc = ctx.add_global_value(
-                                val!(global_alloc(const_val)),
-                                Some(format!("@{}", d.ident)),
-                            );
-                            ctx.register_global_value(&d.ident, alloc);
-                        }
-                        SymKind::Var => {
-                            let v = match &d.exp {
-                                Some(e) => match e.eval(&ctx) {
-                                    Some(v) => ctx.add_global_value(val!(integer(v)), None),
-                                    None => todo!(),
-                                },
-                                None => ctx.add_global_value(val!(zero_init(ty!(i32))), None),
-                            };
-                            let alloc = ctx.add_global_value(
-                                val!(global_alloc(v)),
-                                Some(format!("@{}", d.ident)),
-                            );
-                            ctx.register_global_value(&d.ident, alloc);
-                        }
+impl<'a> Declare<'a> for hir::Item {
+    fn declare(
+        &self,
+        program: &'a mut Program,
+        hir: &'a hir::Program,
+        func_tab: &'a mut FuncTab,
+        global_val_tab: &'a mut ValTab,
+        long_halves: &mut HashMap<ir::Value, ir::Value>,
+    ) {
+        use koopa::ir::ValueKind;
+        match self {
+            hir::Item::Global(g) => {
+                let symbol = hir.symbol(g.sym);
+                let mut ctx = GlobalContext::new(program, global_val_tab);
+                // 初始值已在编译期求得，`long` 的高位字另占一个 `global_alloc`
+                let mut word = |w: Option<i32>, name: String| {
+                    let v = match w {
+                        Some(w) => ctx.add_global_value(val!(integer(w)), None),
+                        None => ctx.add_global_value(val!(zero_init(ty!(i32))), None),
                     };
-                }
+                    ctx.add_global_value(val!(global_alloc(v)), Some(name))
+                };
+                let (lo, hi) = match g.init {
+                    Some(v) => {
+                        let (lo, hi) = v.words();
+                        (Some(lo), hi.map(Some))
+                    }
+                    None => (None, (symbol.ty == ast::Ty::Long).then_some(None)),
+                };
+                let lo = word(lo, format!("@{}", symbol.ident));
+                let hi = hi.map(|hi| word(hi, format!("@{}_hi", symbol.ident)));
+                ctx.register_global_value(g.sym, Sym { ty: symbol.ty, lo, hi });
             },
-            Func(f) => {
+            hir::Item::Func(f) => {
+                let sig = hir.func(f.id);
                 let func_data =
                     ir::FunctionData::with_param_names(
-                        format!("@{}", f.ident),
-                        f.params.iter().map(|p| (
-                            Some(format!("@_{}", p.ident)),
-                            (&p.ty).into()
-                        )).collect(),
-                        (&f.output).into());
+                        format!("@{}", sig.ident),
+                        f.params.iter().flat_map(|p| {
+                            let p = hir.symbol(*p);
+                            let lo = (Some(format!("@_{}", p.ident)), (&p.ty).into());
+                            let hi = (p.ty == ast::Ty::Long).then(|| (Some(format!("@_{}_hi", p.ident)), ty!(i32)));
+                            std::iter::once(lo).chain(hi)
+                        }).collect(),
+                        (&sig.output).into());
                 let func = program.new_func(func_data);
-                func_tab.insert(f.ident.clone(), func);
-                
-                let mut ctx = Context::new(program, func_tab, global_val_tab, func);
+                func_tab.insert(sig.ident.clone(), FuncSym { func });
 
-                let param_values = ctx.this_func().params().to_owned();
-                zip(f.params.iter(), param_values).for_each(|pair| pair.generate(&mut ctx));
-                ctx.table_mut().push_scope();
-                f.block.generate(&mut ctx);
+                let mut ctx = Context::new(program, hir, func_tab, global_val_tab, func);
 
-                // 保证最后一个基本块有 return
+                let mut param_values = ctx.this_func().params().to_owned().into_iter();
+                for p in f.params.iter() {
+                    let ty = hir.symbol(*p).ty;
+                    let lo = param_values.next().unwrap();
+                    let hi = (ty == ast::Ty::Long).then(|| param_values.next().unwrap());
+                    let sym = gen::alloc_sym(&mut ctx, *p);
+                    gen::store_sym(&mut ctx, TyVal { ty, lo, hi }, sym);
+                }
+                f.body.generate(&mut ctx);
+                long_halves.extend(ctx.table().local.values().filter_map(|sym| Some((sym.hi?, sym.lo))));
+
+                // 保证最后一个基本块有 return；非 void 函数落到末尾时由 `-Wreturn-type` 警告
                 let insts = ctx.bb_node(ctx.curr()).insts();
                 if (insts.back_key().is_some()
                     && !matches!(
@@ -80,22 +90,21 @@ impl<'a> Declare<'a> for ast::Item {
                     ))
                     || insts.back_key().is_none()
                 {
-                    let implicit_val = match ctx.this_func().ty().kind() {
-                        TypeKind::Function(_, ret_ty) => {
-                            match ret_ty.kind() {
-                                TypeKind::Int32 => Some(ctx.zero),
-                                TypeKind::Unit => None,
-                                TypeKind::Function(..) => unreachable!(),
-                                _ => unimplemented!()
-                            }
-                        },
-                        _ => unreachable!(),
+                    let mut implicit = vec![];
+                    let implicit_val = match sig.output {
+                        ast::Ty::Int | ast::Ty::UInt | ast::Ty::Float => Some(ctx.zero),
+                        ast::Ty::Long => {
+                            let set_hi = ctx.table().get_func(Intrinsic::SetHi.ident()).unwrap().func;
+                            let zero = ctx.zero;
+                            implicit.push(ctx.add_value(val!(call(set_hi, vec![zero])), None));
+                            Some(zero)
+                        }
+                        ast::Ty::Void => None,
                     };
-                    let implicit_ret = ctx.add_value(val!(ret(implicit_val)), None);
+                    implicit.push(ctx.add_value(val!(ret(implicit_val)), None));
                     ctx.bb_node_mut(ctx.curr())
                         .insts_mut()
-                        .push_key_back(implicit_ret)
-                        .unwrap();
+                        .extend(implicit);
                 }
             }
         };
@@ -0,0 +1,358 @@
+//! SysY 源代码格式化，保留行注释和块注释
+//!
+//! 在 [`token`] 得到的带琐碎内容的记号流上按语句结构重新排版：
+//!
+//! - 缩进为四个空格，左花括号与所在语句同行，`else` 紧跟在右花括号之后；
+//! - `if`、`while` 中不是块的分支另起一行并缩进；
+//! - 二元运算符两侧、逗号之后各有一个空格，一元运算符、括号内侧、调用的括号之前没有空格；
+//! - 每条语句、声明独占一行，函数之间空一行，其余连续的空行合并为一行；
+//! - 注释保留原先的位置：跟在记号之后的注释留在该行末尾，独占一行的注释仍独占一行。
+//!
+//! 数字字面量保持原样。格式化的结果与原先解析出相同的 AST，且再次格式化不再变化。
+
+use std::error::Error;
+
+use self::token::{Token, TokenKind, Trivia};
+
+use super::parser::CompUnitParser;
+
+pub mod token;
+
+pub fn format(source: &str) -> Result<String, Box<dyn Error>> {
+    let parser = CompUnitParser::new();
+    let before = parser.parse(source).map_err(|e| e.to_string())?;
+    let mut f = Formatter {
+        tokens: token::tokenize(source),
+        pos: 0,
+        w: Writer::new(),
+        prev: None,
+        prev_unary: false,
+    };
+    f.file();
+    let after = parser.parse(&f.w.out).map_err(|e| e.to_string())?;
+    if before != after {
+        return Err("formatting changed the meaning of the program".into());
+    }
+    Ok(f.w.out)
+}
+
+struct Writer {
+    out: String,
+    indent: usize,
+    /// 下一段文字之前应输出的换行数，2 表示空一行
+    pending: usize,
+    /// 当前行尚无文字
+    line_empty: bool,
+    /// 块的开头和结尾不留空行
+    suppress_blank: bool,
+}
+
+impl Writer {
+    fn new() -> Writer {
+        Writer {
+            out: String::new(),
+            indent: 0,
+            pending: 0,
+            line_empty: true,
+            suppress_blank: true,
+        }
+    }
+
+    fn line(&mut self) {
+        self.pending = self.pending.max(1);
+    }
+
+    fn blank(&mut self) {
+        if !self.suppress_blank {
+            self.pending = 2;
+        }
+    }
+
+    fn text(&mut self, text: &str, space: bool) {
+        if self.pending > 0 && !self.out.is_empty() {
+            for _ in 0..self.pending {
+                self.out.push('\n');
+            }
+            self.line_empty = true;
+        }
+        self.pending = 0;
+        if self.line_empty {
+            for _ in 0..self.indent {
+                self.out.push_str("    ");
+            }
+        } else if space {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+        self.line_empty = false;
+        self.suppress_blank = false;
+    }
+
+    /// 跟在上一个记号之后的注释，即使该行已经结束
+    fn trailing(&mut self, comment: &str) {
+        self.out.push(' ');
+        self.out.push_str(comment);
+    }
+}
+
+struct Formatter {
+    tokens: Vec<Token>,
+    pos: usize,
+    w: Writer,
+    prev: Option<Token>,
+    prev_unary: bool,
+}
+
+impl Formatter {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn is_eof(&self) -> bool {
+        self.peek().kind == TokenKind::Eof
+    }
+
+    /// 输出当前记号之前的注释
+    fn trivia(&mut self) {
+        let leading = std::mem::take(&mut self.tokens[self.pos].leading);
+        let mut newlines = 0;
+        let mut comment = false;
+        for piece in leading {
+            let (text, is_line) = match piece {
+                Trivia::Newlines(n) => {
+                    newlines += n;
+                    continue;
+                }
+                Trivia::LineComment(text) => (text, true),
+                Trivia::BlockComment(text) => (text, false),
+            };
+            if newlines == 0 && !self.w.line_empty {
+                self.w.trailing(&text);
+            } else {
+                self.w.line();
+                if newlines >= 2 {
+                    self.w.blank();
+                }
+                self.w.text(&text, false);
+            }
+            if is_line {
+                self.w.line();
+            }
+            newlines = 0;
+            comment = true;
+        }
+        if newlines > 0 && comment {
+            self.w.line();
+        }
+        if newlines >= 2 && self.w.pending > 0 {
+            self.w.blank();
+        }
+    }
+
+    fn is_unary_position(&self) -> bool {
+        match &self.prev {
+            None => true,
+            Some(prev) => (prev.kind == TokenKind::Punct && prev.text != ")") || prev.text == "return",
+        }
+    }
+
+    fn space_before(&self, tok: &Token) -> bool {
+        let Some(prev) = &self.prev else {
+            return false;
+        };
+        if matches!(tok.text.as_str(), ")" | "," | ";") || prev.is("(") || self.prev_unary {
+            return false;
+        }
+        !(tok.is("(") && prev.kind == TokenKind::Ident && !matches!(prev.text.as_str(), "if" | "while" | "return"))
+    }
+
+    fn emit(&mut self) {
+        self.trivia();
+        let tok = self.tokens[self.pos].clone();
+        self.pos += 1;
+        let space = self.space_before(&tok);
+        self.w.text(&tok.text, space);
+        self.prev_unary = matches!(tok.text.as_str(), "-" | "+" | "!") && self.is_unary_position();
+        self.prev = Some(tok);
+    }
+
+    /// 输出记号直到括号外的 `stop`，不含 `stop`
+    fn run(&mut self, stop: &str) {
+        let mut depth = 0;
+        while !(self.is_eof() || depth == 0 && self.peek().is(stop)) {
+            if self.peek().is("(") {
+                depth += 1;
+            } else if self.peek().is(")") {
+                depth -= 1;
+            }
+            self.emit();
+        }
+    }
+
+    fn file(&mut self) {
+        let mut prev_func = false;
+        while !self.is_eof() {
+            let is_func = self.is_func();
+            self.w.line();
+            if is_func || prev_func {
+                self.w.blank();
+            }
+            if is_func {
+                self.run("{");
+                self.block();
+            } else {
+                self.decl();
+            }
+            prev_func = is_func;
+        }
+        self.trivia();
+        let len = self.w.out.trim_end().len();
+        self.w.out.truncate(len);
+        self.w.out.push('\n');
+    }
+
+    /// 全局的条目是否为函数：类型和名字之后紧跟 `(`
+    fn is_func(&self) -> bool {
+        let mut pos = self.pos;
+        while self.tokens[pos].kind == TokenKind::Ident {
+            pos += 1;
+        }
+        self.tokens[pos].is("(")
+    }
+
+    fn is_decl(&self) -> bool {
+        matches!(self.peek().text.as_str(), "const" | "int" | "long" | "unsigned" | "float")
+    }
+
+    fn decl(&mut self) {
+        self.run(";");
+        self.emit();
+        self.w.line();
+    }
+
+    /// 输出 `{ ... }`，右花括号之前的注释仍在块内缩进
+    fn block(&mut self) {
+        self.emit();
+        self.w.indent += 1;
+        self.w.line();
+        self.w.suppress_blank = true;
+        while !self.is_eof() && !self.peek().is("}") {
+            self.w.line();
+            if self.is_decl() {
+                self.decl();
+            } else {
+                self.stmt();
+            }
+        }
+        self.trivia();
+        self.w.indent -= 1;
+        self.w.pending = 1;
+        self.w.suppress_blank = true;
+        self.emit();
+    }
+
+    /// `if`、`while` 的分支，返回分支是否为块
+    fn branch(&mut self) -> bool {
+        if self.peek().is("{") {
+            self.block();
+            true
+        } else {
+            self.w.indent += 1;
+            self.w.line();
+            self.stmt();
+            self.w.indent -= 1;
+            false
+        }
+    }
+
+    fn cond(&mut self) {
+        self.emit();
+        self.emit();
+        self.run(")");
+        self.emit();
+    }
+
+    fn stmt(&mut self) {
+        if self.peek().is("{") {
+            self.block();
+            self.w.line();
+        } else if self.peek().is("if") {
+            self.cond();
+            let is_block = self.branch();
+            if self.peek().is("else") {
+                // 块之后 `} else` 同行
+                if !is_block {
+                    self.w.line();
+                }
+                self.emit();
+                if self.peek().is("if") {
+                    self.stmt();
+                } else if self.branch() {
+                    self.w.line();
+                }
+            } else if is_block {
+                self.w.line();
+            }
+        } else if self.peek().is("while") {
+            self.cond();
+            if self.branch() {
+                self.w.line();
+            }
+        } else {
+            self.run(";");
+            self.emit();
+            self.w.line();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::format;
+
+    #[test]
+    fn comments() {
+        let source = r#"// header
+
+const int N=10;   // size
+int g ;
+int main( ){int a=-1,b = 0x1F;/* inline */ a=a+ - b*(g-1) ;
+
+    // own line
+if(a>0)return a;else if (!a) { return 0; }
+    else return -a ; // trailing
+  while(a<N)a=a+1;
+  putint( f(a,b) );
+
+
+  return 0; /* end */
+}
+"#;
+        let expected = r#"// header
+
+const int N = 10; // size
+int g;
+
+int main() {
+    int a = -1, b = 0x1F; /* inline */
+    a = a + -b * (g - 1);
+
+    // own line
+    if (a > 0)
+        return a;
+    else if (!a) {
+        return 0;
+    } else
+        return -a; // trailing
+    while (a < N)
+        a = a + 1;
+    putint(f(a, b));
+
+    return 0; /* end */
+}
+"#;
+        let formatted = format(source).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+}
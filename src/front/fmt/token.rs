@@ -0,0 +1,131 @@
+//! 保留注释和换行的词法分析
+//!
+//! [`crate::front::parser`] 的词法规则直接丢弃空白和注释；这里的每个 [`Token`] 则带着它之前的琐碎内容（[`Trivia`]），
+//! 文件末尾的琐碎内容挂在 [`TokenKind::Eof`] 上。切分规则与 `parser.lalrpop` 一致。
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trivia {
+    /// 连续的空白中换行的个数，不含换行的空白不记录
+    Newlines(usize),
+    /// `// ...`，不含结尾的换行
+    LineComment(String),
+    /// `/* ... */`
+    BlockComment(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// 标识符和关键字
+    Ident,
+    Number,
+    Punct,
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub leading: Vec<Trivia>,
+    /// 起始位置所在的行，从 0 开始
+    pub line: usize,
+    /// 起始位置在行内的字符偏移，从 0 开始
+    pub col: usize,
+}
+
+impl Token {
+    pub fn is(&self, text: &str) -> bool {
+        self.kind != TokenKind::Eof && self.text == text
+    }
+}
+
+const PUNCTS: [&str; 6] = ["<=", ">=", "==", "!=", "&&", "||"];
+
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut leading = vec![];
+    let mut i = 0;
+    // 已计算行列号的位置
+    let (mut at, mut line, mut col) = (0, 0, 0);
+    let mut locate = |to: usize| {
+        for &c in &chars[at..to] {
+            if c == '\n' {
+                line += 1;
+                col = 0;
+            } else {
+                col += 1;
+            }
+        }
+        at = to;
+        (line, col)
+    };
+    let take_while = |mut i: usize, f: &dyn Fn(char) -> bool| {
+        while i < chars.len() && f(chars[i]) {
+            i += 1;
+        }
+        i
+    };
+    loop {
+        let start = i;
+        let Some(&c) = chars.get(i) else {
+            let (line, col) = locate(i);
+            tokens.push(Token { kind: TokenKind::Eof, text: String::new(), leading, line, col });
+            return tokens;
+        };
+        let next = chars.get(i + 1).copied();
+        let kind = if c.is_whitespace() {
+            i = take_while(i, &|c| c.is_whitespace());
+            let newlines = chars[start..i]
+                .iter()
+                .enumerate()
+                .filter(|(j, &c)| c == '\n' || (c == '\r' && chars.get(start + j + 1) != Some(&'\n')))
+                .count();
+            if newlines > 0 {
+                leading.push(Trivia::Newlines(newlines));
+            }
+            continue;
+        } else if c == '/' && next == Some('/') {
+            i = take_while(i, &|c| c != '\n' && c != '\r');
+            leading.push(Trivia::LineComment(chars[start..i].iter().collect::<String>().trim_end().to_string()));
+            continue;
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i = (i + 2).min(chars.len());
+            leading.push(Trivia::BlockComment(chars[start..i].iter().collect()));
+            continue;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            i = take_while(i, &|c| c.is_ascii_alphanumeric() || c == '_');
+            TokenKind::Ident
+        } else if c.is_ascii_digit() || (c == '.' && next.is_some_and(|c| c.is_ascii_digit())) {
+            // 指数的符号只能跟在十进制数的 `e` 或十六进制数的 `p` 之后
+            let hex = c == '0' && matches!(next, Some('x' | 'X'));
+            i += 1;
+            while let Some(&c) = chars.get(i) {
+                let exp_sign = matches!(c, '+' | '-')
+                    && matches!((hex, chars[i - 1]), (false, 'e' | 'E') | (true, 'p' | 'P'));
+                if c.is_ascii_alphanumeric() || c == '.' || c == '_' || exp_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            TokenKind::Number
+        } else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            i += if PUNCTS.contains(&two.as_str()) { 2 } else { 1 };
+            TokenKind::Punct
+        };
+        let (line, col) = locate(start);
+        tokens.push(Token {
+            kind,
+            text: chars[start..i].iter().collect(),
+            leading: std::mem::take(&mut leading),
+            line,
+            col,
+        });
+    }
+}
@@ -0,0 +1,449 @@
+use crate::{front::{ast::Ty, hir::ConstVal}, util::intrinsic::Intrinsic};
+
+use super::*;
+
+/*
+
+long 在 RV32 上拆为低、高两个 i32 字 (lo, hi)：
+
+  a + b:  lo = al + bl;  hi = ah + bh + sltu(lo, al)
+  a - b:  lo = al - bl;  hi = ah - bh - sltu(al, bl)
+  a * b:  lo = al * bl;  hi = mulhu(al, bl) + al * bh + ah * bl
+  a / b:  __divdi3,  a % b:  __moddi3
+  a < b:  ah < bh || (ah == bh && sltu(al, bl))
+
+float 以 i32 位模式保存，运算调用 libgcc 的软浮点函数：
+
+  a + b:  __addsf3(a, b)         -a:  a ^ 0x80000000
+  a < b:  __ltsf2(a, b) < 0      a == b:  __eqsf2(a, b) == 0
+
+*/
+
+/// 带类型的表达式值，`long` 拆为低、高两个 `i32` 字
+#[derive(Debug, Clone, Copy)]
+pub struct TyVal {
+    pub ty: Ty,
+    pub lo: ir::Value,
+    pub hi: Option<ir::Value>,
+}
+
+impl TyVal {
+    pub fn word(ty: Ty, lo: ir::Value) -> TyVal {
+        TyVal { ty, lo, hi: None }
+    }
+
+    pub fn long(lo: ir::Value, hi: ir::Value) -> TyVal {
+        TyVal { ty: Ty::Long, lo, hi: Some(hi) }
+    }
+
+    /// 按低、高顺序排列的所有字
+    pub fn words(&self) -> Vec<ir::Value> {
+        std::iter::once(self.lo).chain(self.hi).collect()
+    }
+}
+
+impl<'a> Context<'a> {
+    /// 插入一条二元运算指令
+    pub fn binary(&mut self, op: ir::BinaryOp, lhs: ir::Value, rhs: ir::Value) -> ir::Value {
+        let inst = self.add_mid_value(val!(binary(op, lhs, rhs)));
+        self.insert_inst(inst, self.curr());
+        inst
+    }
+
+    /// 插入一次对内部函数的调用
+    pub fn call_intrinsic(&mut self, intrinsic: Intrinsic, args: Vec<ir::Value>) -> ir::Value {
+        let func = self.table().get_func(intrinsic.ident()).unwrap().func;
+        let call = if intrinsic.ret_ty().is_unit() {
+            self.add_value(val!(call(func, args)), None)
+        } else {
+            self.add_mid_value(val!(call(func, args)))
+        };
+        self.insert_inst(call, self.curr());
+        call
+    }
+
+    /// 将编译期常量放入当前函数
+    pub fn const_val(&mut self, c: ConstVal) -> TyVal {
+        let (lo, hi) = c.words();
+        let lo = self.add_value(val!(integer(lo)), None);
+        let hi = hi.map(|hi| self.add_value(val!(integer(hi)), None));
+        TyVal { ty: c.ty(), lo, hi }
+    }
+
+    /// 类型转换：`long` 截断为低位字，`int` 符号扩展、`unsigned int` 零扩展为 `long`，
+    /// 与 `float` 之间的转换调用运行时函数
+    pub fn convert(&mut self, v: TyVal, ty: Ty) -> TyVal {
+        match (v.ty, ty) {
+            (from, to) if from == to => v,
+            (Ty::Void, _) | (_, Ty::Void) => {
+                panic!("SemanticsError[VoidOperand]: void value cannot be converted.")
+            }
+            (Ty::Float, Ty::Long) => {
+                let lo = self.call_intrinsic(Intrinsic::Fixsfdi, vec![v.lo]);
+                let hi = self.call_intrinsic(Intrinsic::Hi, vec![lo]);
+                TyVal::long(lo, hi)
+            }
+            (Ty::Float, _) => {
+                let intrinsic = if ty.is_unsigned() { Intrinsic::Fixunssfsi } else { Intrinsic::Fixsfsi };
+                TyVal::word(ty, self.call_intrinsic(intrinsic, vec![v.lo]))
+            }
+            (_, Ty::Float) => {
+                let intrinsic = match v.ty {
+                    Ty::UInt => Intrinsic::Floatunsisf,
+                    Ty::Long => Intrinsic::Floatdisf,
+                    _ => Intrinsic::Floatsisf,
+                };
+                TyVal::word(ty, self.call_intrinsic(intrinsic, v.words()))
+            }
+            (Ty::Long, _) => TyVal::word(ty, v.lo),
+            (Ty::Int, Ty::Long) => {
+                let shamt = self.add_value(val!(integer(31)), None);
+                let hi = self.binary(ir::BinaryOp::Sar, v.lo, shamt);
+                TyVal::long(v.lo, hi)
+            }
+            (Ty::UInt, Ty::Long) => TyVal::long(v.lo, self.zero),
+            _ => TyVal::word(ty, v.lo),
+        }
+    }
+
+    /// 一元负号，`float` 只需翻转符号位
+    pub fn negate(&mut self, v: TyVal) -> TyVal {
+        if v.ty.is_float() {
+            let sign = self.add_value(val!(integer(i32::MIN)), None);
+            return TyVal::word(Ty::Float, self.binary(ir::BinaryOp::Xor, v.lo, sign));
+        }
+        let zero = TyVal { ty: v.ty, lo: self.zero, hi: v.hi.map(|_| self.zero) };
+        self.arith(ir::BinaryOp::Sub, zero, v)
+    }
+
+    /// 同类型操作数的二元运算，比较运算的结果为 `int`
+    pub fn arith(&mut self, op: ir::BinaryOp, lhs: TyVal, rhs: TyVal) -> TyVal {
+        use ir::BinaryOp::*;
+        debug_assert_eq!(lhs.ty, rhs.ty);
+        let ty = lhs.ty;
+        let res_ty = match op {
+            Eq | NotEq | Lt | Gt | Le | Ge => Ty::Int,
+            _ => ty,
+        };
+        match ty {
+            Ty::Long => self.arith_long(op, lhs, rhs),
+            Ty::Float => TyVal::word(res_ty, self.arith_float(op, lhs.lo, rhs.lo)),
+            Ty::UInt => TyVal::word(res_ty, self.arith_unsigned(op, lhs.lo, rhs.lo)),
+            _ => TyVal::word(res_ty, self.binary(op, lhs.lo, rhs.lo)),
+        }
+    }
+
+    fn arith_unsigned(&mut self, op: ir::BinaryOp, lhs: ir::Value, rhs: ir::Value) -> ir::Value {
+        use ir::BinaryOp::*;
+        match op {
+            Div => self.call_intrinsic(Intrinsic::Divu, vec![lhs, rhs]),
+            Mod => self.call_intrinsic(Intrinsic::Remu, vec![lhs, rhs]),
+            Lt => self.call_intrinsic(Intrinsic::Sltu, vec![lhs, rhs]),
+            Gt => self.call_intrinsic(Intrinsic::Sltu, vec![rhs, lhs]),
+            Le => {
+                let gt = self.call_intrinsic(Intrinsic::Sltu, vec![rhs, lhs]);
+                self.binary(Eq, gt, self.zero)
+            }
+            Ge => {
+                let lt = self.call_intrinsic(Intrinsic::Sltu, vec![lhs, rhs]);
+                self.binary(Eq, lt, self.zero)
+            }
+            _ => self.binary(op, lhs, rhs),
+        }
+    }
+
+    fn arith_float(&mut self, op: ir::BinaryOp, lhs: ir::Value, rhs: ir::Value) -> ir::Value {
+        use ir::BinaryOp::*;
+        let (intrinsic, cmp) = match op {
+            Add => (Intrinsic::Addsf3, None),
+            Sub => (Intrinsic::Subsf3, None),
+            Mul => (Intrinsic::Mulsf3, None),
+            Div => (Intrinsic::Divsf3, None),
+            Eq => (Intrinsic::Eqsf2, Some(Eq)),
+            NotEq => (Intrinsic::Nesf2, Some(NotEq)),
+            Lt => (Intrinsic::Ltsf2, Some(Lt)),
+            Le => (Intrinsic::Lesf2, Some(Le)),
+            Gt => (Intrinsic::Gtsf2, Some(Gt)),
+            Ge => (Intrinsic::Gesf2, Some(Ge)),
+            _ => unreachable!(),
+        };
+        let res = self.call_intrinsic(intrinsic, vec![lhs, rhs]);
+        // 比较函数的结果与 0 的关系即为比较的结果
+        match cmp {
+            Some(cmp) => self.binary(cmp, res, self.zero),
+            None => res,
+        }
+    }
+
+    fn arith_long(&mut self, op: ir::BinaryOp, lhs: TyVal, rhs: TyVal) -> TyVal {
+        use ir::BinaryOp::*;
+        let (al, ah, bl, bh) = (lhs.lo, lhs.hi.unwrap(), rhs.lo, rhs.hi.unwrap());
+        match op {
+            Add => {
+                let lo = self.binary(Add, al, bl);
+                let carry = self.call_intrinsic(Intrinsic::Sltu, vec![lo, al]);
+                let hi = self.binary(Add, ah, bh);
+                let hi = self.binary(Add, hi, carry);
+                TyVal::long(lo, hi)
+            }
+            Sub => {
+                let lo = self.binary(Sub, al, bl);
+                let borrow = self.call_intrinsic(Intrinsic::Sltu, vec![al, bl]);
+                let hi = self.binary(Sub, ah, bh);
+                let hi = self.binary(Sub, hi, borrow);
+                TyVal::long(lo, hi)
+            }
+            Mul => {
+                let lo = self.binary(Mul, al, bl);
+                let hi = self.call_intrinsic(Intrinsic::Mulhu, vec![al, bl]);
+                let cross_l = self.binary(Mul, al, bh);
+                let cross_r = self.binary(Mul, ah, bl);
+                let hi = self.binary(Add, hi, cross_l);
+                let hi = self.binary(Add, hi, cross_r);
+                TyVal::long(lo, hi)
+            }
+            Div | Mod => {
+                let intrinsic = if op == Div { Intrinsic::Divdi3 } else { Intrinsic::Moddi3 };
+                let lo = self.call_intrinsic(intrinsic, vec![al, ah, bl, bh]);
+                let hi = self.call_intrinsic(Intrinsic::Hi, vec![lo]);
+                TyVal::long(lo, hi)
+            }
+            Eq | NotEq => {
+                let diff_l = self.binary(Xor, al, bl);
+                let diff_h = self.binary(Xor, ah, bh);
+                let diff = self.binary(Or, diff_l, diff_h);
+                TyVal::word(Ty::Int, self.binary(op, diff, self.zero))
+            }
+            Lt => TyVal::word(Ty::Int, self.long_lt(lhs, rhs)),
+            Gt => TyVal::word(Ty::Int, self.long_lt(rhs, lhs)),
+            Le => {
+                let gt = self.long_lt(rhs, lhs);
+                TyVal::word(Ty::Int, self.binary(Eq, gt, self.zero))
+            }
+            Ge => {
+                let lt = self.long_lt(lhs, rhs);
+                TyVal::word(Ty::Int, self.binary(Eq, lt, self.zero))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn long_lt(&mut self, lhs: TyVal, rhs: TyVal) -> ir::Value {
+        use ir::BinaryOp::*;
+        let (al, ah, bl, bh) = (lhs.lo, lhs.hi.unwrap(), rhs.lo, rhs.hi.unwrap());
+        let hi_lt = self.binary(Lt, ah, bh);
+        let hi_eq = self.binary(Eq, ah, bh);
+        let lo_lt = self.call_intrinsic(Intrinsic::Sltu, vec![al, bl]);
+        let tie = self.binary(And, hi_eq, lo_lt);
+        self.binary(Or, hi_lt, tie)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::front::{into_ir, into_ir_text};
+
+    /// 生成 Koopa IR 文本，略去开头的声明
+    fn koopa(source: &str) -> String {
+        let text = into_ir_text(into_ir(source.to_string())).unwrap();
+        text[text.find("fun ").unwrap()..].to_string()
+    }
+
+    #[test]
+    fn long_arith() {
+        // `int` 操作数符号扩展为 `long`；加法的进位由 `sltu` 求出，除法调用 `__divdi3`，
+        // 比较先比较高位字，相等时无符号比较低位字；返回值的高位字由 `__sysy_set_hi` 放入 `a1`
+        let source = r#"
+long add(long a, int b) { return a + b; }
+long div(long a, long b) { return a / b; }
+int lt(long a, long b) { return a < b; }
+"#;
+        assert_eq!(
+            koopa(source),
+            r#"
+fun @add(@_a: i32, @_a_hi: i32, @_b: i32): i32 {
+%entry:
+  @a = alloc i32
+  @a_hi = alloc i32
+  store @_a, @a
+  store @_a_hi, @a_hi
+  @b = alloc i32
+  store @_b, @b
+  %0 = load @a
+  %1 = load @a_hi
+  %2 = load @b
+  %3 = sar %2, 31
+  %4 = add %0, %2
+  %5 = call @__sysy_sltu(%4, %0)
+  %6 = add %1, %3
+  %7 = add %6, %5
+  call @__sysy_set_hi(%7)
+  ret %4
+}
+
+fun @div(@_a: i32, @_a_hi: i32, @_b: i32, @_b_hi: i32): i32 {
+%entry:
+  @a = alloc i32
+  @a_hi = alloc i32
+  store @_a, @a
+  store @_a_hi, @a_hi
+  @b = alloc i32
+  @b_hi = alloc i32
+  store @_b, @b
+  store @_b_hi, @b_hi
+  %0 = load @a
+  %1 = load @a_hi
+  %2 = load @b
+  %3 = load @b_hi
+  %4 = call @__divdi3(%0, %1, %2, %3)
+  %5 = call @__sysy_hi(%4)
+  call @__sysy_set_hi(%5)
+  ret %4
+}
+
+fun @lt(@_a: i32, @_a_hi: i32, @_b: i32, @_b_hi: i32): i32 {
+%entry:
+  @a = alloc i32
+  @a_hi = alloc i32
+  store @_a, @a
+  store @_a_hi, @a_hi
+  @b = alloc i32
+  @b_hi = alloc i32
+  store @_b, @b
+  store @_b_hi, @b_hi
+  %0 = load @a
+  %1 = load @a_hi
+  %2 = load @b
+  %3 = load @b_hi
+  %4 = lt %1, %3
+  %5 = eq %1, %3
+  %6 = call @__sysy_sltu(%0, %2)
+  %7 = and %5, %6
+  %8 = or %4, %7
+  ret %8
+}
+"#
+            .trim_start()
+        );
+    }
+
+    #[test]
+    fn unsigned_arith() {
+        // `unsigned int` 与 `int` 运算时 `int` 转为 `unsigned int`，除法与比较调用内部函数；
+        // `unsigned int` 零扩展为 `long`，高位字为 0
+        let source = r#"
+unsigned div(unsigned a, unsigned b) { return a / b; }
+int lt(unsigned a, int b) { return a < b; }
+long widen(int a, unsigned b) { return a + b; }
+"#;
+        assert_eq!(
+            koopa(source),
+            r#"
+fun @div(@_a: i32, @_b: i32): i32 {
+%entry:
+  @a = alloc i32
+  store @_a, @a
+  @b = alloc i32
+  store @_b, @b
+  %0 = load @a
+  %1 = load @b
+  %2 = call @__sysy_divu(%0, %1)
+  ret %2
+}
+
+fun @lt(@_a: i32, @_b: i32): i32 {
+%entry:
+  @a = alloc i32
+  store @_a, @a
+  @b = alloc i32
+  store @_b, @b
+  %0 = load @a
+  %1 = load @b
+  %2 = call @__sysy_sltu(%0, %1)
+  ret %2
+}
+
+fun @widen(@_a: i32, @_b: i32): i32 {
+%entry:
+  @a = alloc i32
+  store @_a, @a
+  @b = alloc i32
+  store @_b, @b
+  %0 = load @a
+  %1 = load @b
+  %2 = add %0, %1
+  call @__sysy_set_hi(0)
+  ret %2
+}
+"#
+            .trim_start()
+        );
+    }
+
+    #[test]
+    fn float_arith() {
+        // `int`、`unsigned int` 操作数经运行时函数转换为 `float`，常量在编译期转换为位模式；
+        // 运算与比较调用软浮点函数，比较的结果与 0 比较；取负翻转符号位，转换为 `int` 向零取整
+        let source = r#"
+float mix(float x, int n, unsigned u) { return x * n + u; }
+float half(float x) { return x / 2.0; }
+int lt(float x, float y) { return x < y; }
+int trunc(float x) { return -x; }
+"#;
+        assert_eq!(
+            koopa(source),
+            r#"
+fun @mix(@_x: i32, @_n: i32, @_u: i32): i32 {
+%entry:
+  @x = alloc i32
+  store @_x, @x
+  @n = alloc i32
+  store @_n, @n
+  @u = alloc i32
+  store @_u, @u
+  %0 = load @x
+  %1 = load @n
+  %2 = call @__floatsisf(%1)
+  %3 = call @__mulsf3(%0, %2)
+  %4 = load @u
+  %5 = call @__floatunsisf(%4)
+  %6 = call @__addsf3(%3, %5)
+  ret %6
+}
+
+fun @half(@_x: i32): i32 {
+%entry:
+  @x = alloc i32
+  store @_x, @x
+  %0 = load @x
+  %1 = call @__divsf3(%0, 1073741824)
+  ret %1
+}
+
+fun @lt(@_x: i32, @_y: i32): i32 {
+%entry:
+  @x = alloc i32
+  store @_x, @x
+  @y = alloc i32
+  store @_y, @y
+  %0 = load @x
+  %1 = load @y
+  %2 = call @__ltsf2(%0, %1)
+  %3 = lt %2, 0
+  ret %3
+}
+
+fun @trunc(@_x: i32): i32 {
+%entry:
+  @x = alloc i32
+  store @_x, @x
+  %0 = load @x
+  %1 = xor %0, -2147483648
+  %2 = call @__fixsfsi(%1)
+  ret %2
+}
+"#
+            .trim_start()
+        );
+    }
+}
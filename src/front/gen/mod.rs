@@ -1,75 +1,63 @@
 // #[macro_use] use super::context;
 // use crate::auton;
-use crate::{WrapProgram, ty};
+use std::iter::zip;
+
+use crate::{ty, util::intrinsic::Intrinsic};
 
 use crate::front::{
     ast,
     context::Context,
+    hir::{self, ExprKind},
+    symtab::Sym,
 };
 use koopa::ir::{self, builder_traits::*};
 
-pub mod eval;
-
 pub mod prelude;
 
 pub mod lazy;
-pub use lazy::*;
 
-/// [`Generate`] 处理语句（[`ast::StmtKind`]），将每一条语句转化为 Koopa 内存形式
+pub mod arith;
+pub use arith::TyVal;
+
+/// [`Generate`] 处理 HIR 中的语句（[`hir::Stmt`]）和表达式（[`hir::Expr`]），将其转化为 Koopa 内存形式
 pub trait Generate<'f> {
     type Val;
     fn generate(&self, ctx: &'f mut Context) -> Self::Val;
 }
 
-impl<'f> Generate<'f> for ast::Block {
-    type Val = ();
-    fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        for item in &self.0 {
-            item.generate(ctx);
-        }
-    }
-}
-
-impl<'f> Generate<'f> for ast::BlockItem {
+impl<'f> Generate<'f> for [hir::Stmt] {
     type Val = ();
     fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        match self {
-            Self::Stmt(s) => s.generate(ctx),
-            Self::Decl(v) => v.iter().for_each(|d| d.generate(ctx))
+        for stmt in self {
+            stmt.generate(ctx);
         }
     }
 }
 
-impl<'f> Generate<'f> for ast::StmtKind {
+impl<'f> Generate<'f> for hir::Stmt {
     type Val = ();
     fn generate(&self, ctx: &'f mut Context) {
-        use ast::StmtKind::*;
+        use hir::Stmt::*;
         match self {
-            Unit => {}
-            Exp(e) => {
-                e.generate(ctx);
+            Let(id, init) => {
+                let ty = ctx.hir.symbol(*id).ty;
+                let v = match init {
+                    Some(e) => e.generate(ctx),
+                    None => {
+                        let undef = ctx.add_value(val!(undef(ty!(i32))), None);
+                        TyVal { ty, lo: undef, hi: (ty == ast::Ty::Long).then_some(undef) }
+                    }
+                };
+                let sym = alloc_sym(ctx, *id);
+                store_sym(ctx, v, sym);
             }
-            Block(b) => {
-                ctx.table_mut().push_scope();
-                b.generate(ctx);
-                ctx.table_mut().pop_scope();
+            Assign(id, e) => {
+                let sym = ctx.table().get_val(*id).unwrap();
+                let v = e.generate(ctx);
+                store_sym(ctx, v, sym);
             }
-            Assign(l, e) => {
-                let lval_handle = ctx.table().get_val(&l.0).unwrap_or_else(|| {
-                    panic!(
-                        "SemanticsError[UndefinedSymbol]: '{}' is used before definition.",
-                        &l.0
-                    )
-                });
-                let lval = ctx.value(lval_handle);
-                assert!(
-                    !lval.kind().is_const(),
-                    "SemanticsError[InvalidLValAssignment]: '{}' cannot be assigned to.",
-                    &l.0
-                );
-                let exp_handle = e.generate(ctx);
-                let store = ctx.add_value(val!(store(exp_handle, lval_handle)), None);
-                ctx.insert_inst(store, ctx.curr());
+            Expr(e) => {
+                e.generate(ctx);
             }
             If(exp, then, alt) => {
                 let block_name_then = ctx.block_namer.gen("then");
@@ -85,7 +73,7 @@ impl<'f> Generate<'f> for ast::StmtKind {
                 };
 
                 {
-                    let gate = exp.generate(ctx);
+                    let gate = exp.generate(ctx).lo;
                     let branch = ctx.add_value(val!(branch(gate, block_then, block_else)), None);
                     ctx.insert_inst(branch, ctx.curr());
                     ctx.seal_block(ctx.curr());
@@ -128,7 +116,7 @@ impl<'f> Generate<'f> for ast::StmtKind {
                 {
                     ctx.insert_block(block_while);
                     ctx.set_curr(block_while);
-                    let gate = exp.generate(ctx);
+                    let gate = exp.generate(ctx).lo;
                     let branch =
                         ctx.add_value(val!(branch(gate, block_loop, block_endwhile)), None);
                     ctx.insert_inst(branch, ctx.curr());
@@ -165,7 +153,10 @@ impl<'f> Generate<'f> for ast::StmtKind {
                 let ret = match option_r {
                     Some(r) => {
                         let ret_val = r.generate(ctx);
-                        ctx.add_value(val!(ret(Some(ret_val))), None)
+                        if let Some(hi) = ret_val.hi {
+                            ctx.call_intrinsic(Intrinsic::SetHi, vec![hi]);
+                        }
+                        ctx.add_value(val!(ret(Some(ret_val.lo))), None)
                     }
                     None => {
                         ctx.add_value(val!(ret(None)), None)
@@ -178,208 +169,87 @@ impl<'f> Generate<'f> for ast::StmtKind {
     }
 }
 
-impl<'f> Generate<'f> for ast::Decl {
-    type Val = ();
-    fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        use ast::SymKind;
-        use eval::Eval;
-        match self.kind {
-            SymKind::Const => {
-                let val = self.exp.as_ref().unwrap().eval(ctx).unwrap_or_else(|| panic!("SemanticsError[ConstEvalFailure]: '{}' cannot be evaluated during compile time.", self.ident));
-                let const_val = ctx.add_value(val!(integer(val)), None);
-                ctx.table_mut().insert_val(&self.ident, const_val);
-            }
-            SymKind::Var => {
-                let v = match &self.exp {
-                    Some(e) => match e.eval(ctx) {
-                        Some(v) => ctx.add_value(val!(integer(v)), None),
-                        None => e.generate(ctx),
-                    },
-                    None => ctx.add_value(val!(undef(ty!(i32))), None),
-                };
-                let alloc = ctx.add_value(
-                    val!(alloc(ty!(i32))),
-                    Some(format!("@{}", &self.ident)),
-                );
-                ctx.table_mut().insert_val(&self.ident, alloc);
-                ctx.insert_inst(alloc, ctx.curr());
-                let store = ctx.add_value(val!(store(v, alloc)), None);
-                ctx.insert_inst(store, ctx.curr());
-            }
-        };
-    }
-}
-
-impl<'f> Generate<'f> for (&ast::Param, ir::Value) {
-    type Val = ();
-    fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        let alloc = ctx.add_value(
+/// 为局部变量分配空间，`long` 的高位字另占一个 `alloc`
+pub fn alloc_sym(ctx: &mut Context, id: hir::SymId) -> Sym {
+    let symbol = ctx.hir.symbol(id);
+    let (ident, ty) = (&symbol.ident, symbol.ty);
+    let lo = ctx.add_value(
+        val!(alloc(ty!(i32))),
+        Some(format!("@{}", ident)),
+    );
+    ctx.insert_inst(lo, ctx.curr());
+    let hi = (ty == ast::Ty::Long).then(|| {
+        let hi = ctx.add_value(
             val!(alloc(ty!(i32))),
-            Some(format!("@{}", &self.0.ident)),
+            Some(format!("@{}_hi", ident)),
         );
-        ctx.table_mut().insert_val(&self.0.ident, alloc);
-        ctx.insert_inst(alloc, ctx.curr());
-        let store = ctx.add_value(val!(store(self.1, alloc)), None);
-        ctx.insert_inst(store, ctx.curr());
-    }
-}
-
-impl<'f> Generate<'f> for ast::LVal {
-    type Val = ir::Value;
-    fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        let lval_handle = ctx.table().get_val(&self.0).unwrap_or_else(|| {
-            panic!(
-                "SemanticsError[UndefinedSymbol]: '{}' is used before definition.",
-                &self.0
-            )
-        });
-        let lval = ctx.value(lval_handle);
-        if lval.kind().is_const() {
-            lval_handle
-        } else {
-            let load = ctx.add_mid_value(val!(load(lval_handle)));
-            ctx.insert_inst(load, ctx.curr());
-            load
-        }
-    }
-}
-
-impl<'f> Generate<'f> for ast::PrimaryExp {
-    type Val = ir::Value;
-    fn generate(&self, ctx: &'f mut Context) -> ir::Value {
-        match self {
-            Self::LVal(l) => l.generate(ctx),
-            Self::Literal(i) => ctx.add_value(val!(integer(*i)), None),
-            Self::Exp(b) => b.generate(ctx),
-        }
-    }
+        ctx.insert_inst(hi, ctx.curr());
+        hi
+    });
+    let sym = Sym { ty, lo, hi };
+    ctx.table_mut().insert_val(id, sym);
+    sym
 }
 
-impl<'f> Generate<'f> for ast::Exp {
-    type Val = ir::Value;
-    fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        self.0.generate(ctx)
+/// 将值的每个字存入变量
+pub fn store_sym(ctx: &mut Context, v: TyVal, sym: Sym) {
+    for (word, dest) in zip(v.words(), std::iter::once(sym.lo).chain(sym.hi)) {
+        let store = ctx.add_value(val!(store(word, dest)), None);
+        ctx.insert_inst(store, ctx.curr());
     }
 }
 
-impl<'f> Generate<'f> for ast::UnaryExp {
-    type Val = ir::Value;
+impl<'f> Generate<'f> for hir::Expr {
+    type Val = TyVal;
     fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        use ast::UnaryOp::*;
-        match self {
-            Self::Primary(p) => p.generate(ctx),
-            Self::Unary(o, b) => {
-                let v = b.generate(ctx);
-                let zero = ctx.zero;
-                let inst = match o {
-                    Minus => ctx.add_mid_value(val!(binary(ir::BinaryOp::Sub, zero, v))),
-                    LNot => ctx.add_mid_value(val!(binary(ir::BinaryOp::Eq, zero, v))),
-                };
-                ctx.insert_inst(inst, ctx.curr());
-                inst
-            },
-            Self::Call(ident, params) => {
-                let func = ctx.table().get_func(ident).unwrap_or_else(|| {
-                    panic!(
-                        "SemanticsError[UndefinedFunc]: '{}' is called before definition.",
-                        ident
-                    )
-                });
-                let ret_unit = match ctx.func(func).ty().kind() {
-                    ir::TypeKind::Function(_, ret_ty) => ret_ty.is_unit(),
-                    _ => unreachable!(),
+        match &self.kind {
+            ExprKind::Const(c) => ctx.const_val(*c),
+            ExprKind::Var(id) => {
+                let sym = ctx.table().get_val(*id).unwrap();
+                let mut load = |src: ir::Value| {
+                    let load = ctx.add_mid_value(val!(load(src)));
+                    ctx.insert_inst(load, ctx.curr());
+                    load
                 };
-                let param_values: Vec<_> = params.iter().map(|p| p.generate(ctx)).collect();
-                let call = if ret_unit {
+                let lo = load(sym.lo);
+                let hi = sym.hi.map(load);
+                TyVal { ty: sym.ty, lo, hi }
+            }
+            ExprKind::Cast(e) => {
+                let v = e.generate(ctx);
+                ctx.convert(v, self.ty)
+            }
+            ExprKind::Neg(e) => {
+                let v = e.generate(ctx);
+                ctx.negate(v)
+            }
+            ExprKind::Binary(op, l, r) => {
+                let l = l.generate(ctx);
+                let r = r.generate(ctx);
+                ctx.arith(*op, l, r)
+            }
+            ExprKind::And(l, r) => lazy::generate(ctx, lazy::Logic::And, l, r),
+            ExprKind::Or(l, r) => lazy::generate(ctx, lazy::Logic::Or, l, r),
+            ExprKind::Call(id, args) => {
+                let ident = &ctx.hir.func(*id).ident;
+                let func = ctx.table().get_func(ident).unwrap().func;
+                let param_values: Vec<_> = args
+                    .iter()
+                    .flat_map(|a| a.generate(ctx).words())
+                    .collect();
+                let call = if self.ty == ast::Ty::Void {
                     ctx.add_value(val!(call(func, param_values)), None)
                 } else {
                     ctx.add_mid_value(val!(call(func, param_values)))
                 };
                 ctx.insert_inst(call, ctx.curr());
-                call
-            },
-        }
-    }
-}
-
-impl<'f> Generate<'f> for ast::MulExp {
-    type Val = ir::Value;
-    fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        use ast::MulOp::*;
-        match self {
-            Self::Unary(p) => p.generate(ctx),
-            Self::Binary(b, o, u) => {
-                let v = b.generate(ctx);
-                let u = u.generate(ctx);
-                let inst = match o {
-                    Mul => ctx.add_mid_value(val!(binary(ir::BinaryOp::Mul, v, u))),
-                    Div => ctx.add_mid_value(val!(binary(ir::BinaryOp::Div, v, u))),
-                    Mod => ctx.add_mid_value(val!(binary(ir::BinaryOp::Mod, v, u))),
-                };
-                ctx.insert_inst(inst, ctx.curr());
-                inst
-            }
-        }
-    }
-}
-
-impl<'f> Generate<'f> for ast::AddExp {
-    type Val = ir::Value;
-    fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        use ast::AddOp::*;
-        match self {
-            Self::Unary(p) => p.generate(ctx),
-            Self::Binary(b, o, u) => {
-                let v = b.generate(ctx);
-                let u = u.generate(ctx);
-                let inst = match o {
-                    Add => ctx.add_mid_value(val!(binary(ir::BinaryOp::Add, v, u))),
-                    Sub => ctx.add_mid_value(val!(binary(ir::BinaryOp::Sub, v, u))),
-                };
-                ctx.insert_inst(inst, ctx.curr());
-                inst
-            }
-        }
-    }
-}
-
-impl<'f> Generate<'f> for ast::RelExp {
-    type Val = ir::Value;
-    fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        use ast::RelOp::*;
-        match self {
-            Self::Unary(p) => p.generate(ctx),
-            Self::Binary(b, o, u) => {
-                let v = b.generate(ctx);
-                let u = u.generate(ctx);
-                let inst = match o {
-                    Lt => ctx.add_mid_value(val!(binary(ir::BinaryOp::Lt, v, u))),
-                    Gt => ctx.add_mid_value(val!(binary(ir::BinaryOp::Gt, v, u))),
-                    Le => ctx.add_mid_value(val!(binary(ir::BinaryOp::Le, v, u))),
-                    Ge => ctx.add_mid_value(val!(binary(ir::BinaryOp::Ge, v, u))),
-                };
-                ctx.insert_inst(inst, ctx.curr());
-                inst
-            }
-        }
-    }
-}
-
-impl<'f> Generate<'f> for ast::EqExp {
-    type Val = ir::Value;
-    fn generate(&self, ctx: &'f mut Context) -> Self::Val {
-        use ast::EqOp::*;
-        match self {
-            Self::Unary(p) => p.generate(ctx),
-            Self::Binary(b, o, u) => {
-                let v = b.generate(ctx);
-                let u = u.generate(ctx);
-                let inst = match o {
-                    Eq => ctx.add_mid_value(val!(binary(ir::BinaryOp::Eq, v, u))),
-                    Ne => ctx.add_mid_value(val!(binary(ir::BinaryOp::NotEq, v, u))),
-                };
-                ctx.insert_inst(inst, ctx.curr());
-                inst
+                match self.ty {
+                    ast::Ty::Long => {
+                        let hi = ctx.call_intrinsic(Intrinsic::Hi, vec![call]);
+                        TyVal::long(call, hi)
+                    }
+                    ty => TyVal::word(ty, call),
+                }
             }
         }
     }
@@ -1,6 +1,14 @@
 use koopa::ir;
 
-use crate::{front::symtab::FuncTab, ty};
+use crate::{
+    front::{
+        ast::Ty,
+        hir::FuncSig,
+        symtab::{FuncSym, FuncTab},
+    },
+    ty,
+    util::intrinsic::Intrinsic,
+};
 
 fn decl_func<'a: 'b, 'b>(program: &'a mut ir::Program, name: &'static str, params_ty: &'b [ir::Type], ret_ty: ir::Type) -> ir::Function {
     let func_data = ir::FunctionData::new_decl(name.to_string(), params_ty.into(), ret_ty);
@@ -17,10 +25,21 @@ fn decl_func<'a: 'b, 'b>(program: &'a mut ir::Program, name: &'static str, param
  * decl @putarray(i32, *i32)
  * decl @starttime()
  * decl @stoptime()
+ *
+ * float 相关的库函数仅在被调用时保留：
+ *
+ * decl @getfloat(): i32
+ * decl @getfarray(*i32): i32
+ * decl @putfloat(i32)
+ * decl @putfarray(i32, *i32)
  */
 
-pub fn with_prelude(program: &mut ir::Program, func_tab: &mut FuncTab) {
-    let lib_funcs = [
+/// `float` 相关的库函数，`float` 以 `i32` 位模式传递
+const FLOAT_LIB_FUNCS: [&str; 4] = ["getfloat", "getfarray", "putfloat", "putfarray"];
+
+/// 库函数在 Koopa 中的签名
+fn lib_funcs() -> Vec<(&'static str, Vec<ir::Type>, ir::Type)> {
+    vec![
         ("@getint", vec![], ty!(i32)),
         ("@getch", vec![], ty!(i32)),
         ("@getarray", vec![ty!(*i32)], ty!(i32)),
@@ -29,14 +48,64 @@ pub fn with_prelude(program: &mut ir::Program, func_tab: &mut FuncTab) {
         ("@putarray", vec![ty!(i32), ty!(*i32)], ty!(())),
         ("@starttime", vec![], ty!(())),
         ("@stoptime", vec![], ty!(())),
-    ];
-    let lib_func_names: Vec<_> = lib_funcs.iter().map(|(h, ..)| unsafe { h.get_unchecked(1..) }.to_string()).collect();
-    lib_funcs.into_iter()
-    .map(|(n, ref p, r)| {
-        decl_func(program, n, p, r)
-    })
-    .zip(lib_func_names)
-    .for_each(|(h, n)| {
-        func_tab.insert(n, h);
-    });    
+        ("@getfloat", vec![], ty!(i32)),
+        ("@getfarray", vec![ty!(*i32)], ty!(i32)),
+        ("@putfloat", vec![ty!(i32)], ty!(())),
+        ("@putfarray", vec![ty!(i32), ty!(*i32)], ty!(())),
+    ]
+}
+
+/// 库函数在源语言层面的签名
+pub fn lib_sigs() -> Vec<FuncSig> {
+    lib_funcs()
+        .into_iter()
+        .map(|(n, p, r)| {
+            // 指针参数没有对应的源语言类型，暂记为 `int`
+            let mut sig = FuncSig {
+                ident: n[1..].to_string(),
+                params: vec![Ty::Int; p.len()],
+                output: if r.is_unit() { Ty::Void } else { Ty::Int },
+                span: None,
+            };
+            match sig.ident.as_str() {
+                "getfloat" => sig.output = Ty::Float,
+                "putfloat" => sig.params = vec![Ty::Float],
+                _ => (),
+            }
+            sig
+        })
+        .collect()
+}
+
+pub fn with_prelude(program: &mut ir::Program, func_tab: &mut FuncTab) {
+    for ((n, p, r), sig) in lib_funcs().into_iter().zip(lib_sigs()) {
+        let func = decl_func(program, n, &p, r);
+        func_tab.insert(sig.ident, FuncSym { func });
+    }
+
+    for intrinsic in Intrinsic::ALL {
+        let func = decl_func(program, intrinsic.name(), &intrinsic.params_ty(), intrinsic.ret_ty());
+        func_tab.insert(intrinsic.ident().to_string(), FuncSym { func });
+    }
+}
+
+/// 删除没有被调用的内部函数和 `float` 库函数的声明，使只用到 `int` 的程序的 IR 保持不变
+pub fn strip_unused_decls(program: &mut ir::Program, func_tab: &FuncTab) {
+    use std::collections::HashSet;
+    let called: HashSet<ir::Function> = program
+        .funcs()
+        .values()
+        .flat_map(|f| f.dfg().values().values())
+        .filter_map(|v| match v.kind() {
+            ir::ValueKind::Call(c) => Some(c.callee()),
+            _ => None,
+        })
+        .collect();
+    let decls = Intrinsic::ALL.iter().map(|i| i.ident()).chain(FLOAT_LIB_FUNCS);
+    for name in decls {
+        let func = func_tab[name].func;
+        if !called.contains(&func) {
+            program.remove_func(func);
+        }
+    }
 }
\ No newline at end of file
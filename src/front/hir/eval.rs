@@ -0,0 +1,233 @@
+use koopa::ir;
+
+use crate::front::ast::{Literal, Ty};
+
+use super::{Expr, ExprKind};
+
+/// 编译期常量值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstVal {
+    Int(i32),
+    UInt(u32),
+    Long(i64),
+    Float(f32),
+}
+
+/// 对同一类型的两个操作数做整数运算，溢出时回绕，除数为零时不是常量
+macro_rules! arith {
+    ($op:expr, $x:expr, $y:expr) => {{
+        use ir::BinaryOp::*;
+        let (x, y) = ($x, $y);
+        match $op {
+            Add => Some(x.wrapping_add(y)),
+            Sub => Some(x.wrapping_sub(y)),
+            Mul => Some(x.wrapping_mul(y)),
+            Div => (y != 0).then(|| x.wrapping_div(y)),
+            Mod => (y != 0).then(|| x.wrapping_rem(y)),
+            _ => unreachable!(),
+        }
+    }};
+}
+
+impl ConstVal {
+    pub fn ty(self) -> Ty {
+        match self {
+            ConstVal::Int(_) => Ty::Int,
+            ConstVal::UInt(_) => Ty::UInt,
+            ConstVal::Long(_) => Ty::Long,
+            ConstVal::Float(_) => Ty::Float,
+        }
+    }
+
+    /// 符号扩展或零扩展到 64 位的值，浮点数向零取整
+    fn widen(self) -> i64 {
+        match self {
+            ConstVal::Int(i) => i as i64,
+            ConstVal::UInt(u) => u as i64,
+            ConstVal::Long(l) => l,
+            ConstVal::Float(f) => f as i64,
+        }
+    }
+
+    /// 类型转换，截断或扩展
+    pub fn cast(self, ty: Ty) -> ConstVal {
+        match (self, ty) {
+            (ConstVal::Float(f), Ty::Int) => return ConstVal::Int(f as i32),
+            (ConstVal::Float(f), Ty::UInt) => return ConstVal::UInt(f as u32),
+            (ConstVal::Float(_), Ty::Float) => return self,
+            _ => (),
+        }
+        let v = self.widen();
+        match ty {
+            Ty::Int => ConstVal::Int(v as i32),
+            Ty::UInt => ConstVal::UInt(v as u32),
+            Ty::Long => ConstVal::Long(v),
+            Ty::Float => ConstVal::Float(v as f32),
+            Ty::Void => panic!("SemanticsError[VoidOperand]: cannot convert to void."),
+        }
+    }
+
+    pub fn is_true(self) -> bool {
+        match self {
+            ConstVal::Float(f) => f != 0.0,
+            _ => self.widen() != 0,
+        }
+    }
+
+    /// 低位字和（仅 `long` 有的）高位字
+    pub fn words(self) -> (i32, Option<i32>) {
+        match self {
+            ConstVal::Int(i) => (i, None),
+            ConstVal::UInt(u) => (u as i32, None),
+            ConstVal::Long(l) => (l as i32, Some((l >> 32) as i32)),
+            ConstVal::Float(f) => (f.to_bits() as i32, None),
+        }
+    }
+
+    pub fn from_words(ty: Ty, lo: i32, hi: Option<i32>) -> ConstVal {
+        match ty {
+            Ty::Int => ConstVal::Int(lo),
+            Ty::UInt => ConstVal::UInt(lo as u32),
+            Ty::Long => ConstVal::Long(((hi.unwrap() as i64) << 32) | (lo as u32 as i64)),
+            Ty::Float => ConstVal::Float(f32::from_bits(lo as u32)),
+            Ty::Void => unreachable!(),
+        }
+    }
+
+    /// 按寻常算术转换求值二元运算，比较运算的结果为 `int`
+    pub fn binary(op: ir::BinaryOp, lhs: ConstVal, rhs: ConstVal) -> Option<ConstVal> {
+        use ir::BinaryOp::*;
+        use ConstVal::*;
+        let ty = Ty::common(lhs.ty(), rhs.ty());
+        let (lhs, rhs) = (lhs.cast(ty), rhs.cast(ty));
+        if matches!(op, Eq | NotEq | Lt | Gt | Le | Ge) {
+            let ord = match (lhs, rhs) {
+                (Int(x), Int(y)) => x.partial_cmp(&y),
+                (UInt(x), UInt(y)) => x.partial_cmp(&y),
+                (Long(x), Long(y)) => x.partial_cmp(&y),
+                (Float(x), Float(y)) => x.partial_cmp(&y),
+                _ => unreachable!(),
+            };
+            // 与 NaN 的比较只有 `!=` 成立
+            let res = match (op, ord) {
+                (NotEq, None) => true,
+                (_, None) => false,
+                (Eq, Some(o)) => o.is_eq(),
+                (NotEq, Some(o)) => o.is_ne(),
+                (Lt, Some(o)) => o.is_lt(),
+                (Gt, Some(o)) => o.is_gt(),
+                (Le, Some(o)) => o.is_le(),
+                (Ge, Some(o)) => o.is_ge(),
+                _ => unreachable!(),
+            };
+            return Some(Int(res as i32));
+        }
+        match (lhs, rhs) {
+            (Int(x), Int(y)) => arith!(op, x, y).map(Int),
+            (UInt(x), UInt(y)) => arith!(op, x, y).map(UInt),
+            (Long(x), Long(y)) => arith!(op, x, y).map(Long),
+            (Float(x), Float(y)) => match op {
+                Add => Some(Float(x + y)),
+                Sub => Some(Float(x - y)),
+                Mul => Some(Float(x * y)),
+                Div => Some(Float(x / y)),
+                Mod => panic!("SemanticsError[InvalidOperand]: '%' cannot be applied to float."),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl std::ops::Neg for ConstVal {
+    type Output = ConstVal;
+
+    fn neg(self) -> ConstVal {
+        match self {
+            ConstVal::Int(i) => ConstVal::Int(i.wrapping_neg()),
+            ConstVal::UInt(u) => ConstVal::UInt(u.wrapping_neg()),
+            ConstVal::Long(l) => ConstVal::Long(l.wrapping_neg()),
+            ConstVal::Float(f) => ConstVal::Float(-f),
+        }
+    }
+}
+
+impl From<Literal> for ConstVal {
+    fn from(l: Literal) -> Self {
+        match l {
+            Literal::Int(i) => ConstVal::Int(i),
+            Literal::UInt(u) => ConstVal::UInt(u),
+            Literal::Long(l) => ConstVal::Long(l),
+            Literal::Float(f) => ConstVal::Float(f),
+        }
+    }
+}
+
+impl Expr {
+    /// 在编译期求值，包含变量或函数调用的表达式不是常量
+    pub fn eval(&self) -> Option<ConstVal> {
+        match &self.kind {
+            ExprKind::Const(c) => Some(*c),
+            ExprKind::Var(_) | ExprKind::Call(..) => None,
+            ExprKind::Cast(e) => e.eval().map(|c| c.cast(self.ty)),
+            ExprKind::Neg(e) => e.eval().map(|c| -c),
+            ExprKind::Binary(op, l, r) => ConstVal::binary(*op, l.eval()?, r.eval()?),
+            ExprKind::And(l, r) => Some(ConstVal::Int((l.eval()?.is_true() && r.eval()?.is_true()) as i32)),
+            ExprKind::Or(l, r) => Some(ConstVal::Int((l.eval()?.is_true() || r.eval()?.is_true()) as i32)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ConstVal::{self, *};
+    use crate::front::{hir::Item, into_hir};
+
+    /// 各个全局变量的初始值
+    fn globals(source: &str) -> Vec<ConstVal> {
+        let program = into_hir(source.to_string());
+        program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Global(g) => g.init,
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn float_constants() {
+        // 运算按单精度进行：`0.1 + 0.2` 恰好舍入为 `0.3`；`int` 与 `float` 之间的转换向零取整或舍入
+        let values = globals(
+            r#"
+const float half = 1 / 2.0;
+float a = half * 3 + 1;
+float b = 7 / 2;
+int c = 7 / 2.0;
+int d = -2.7;
+unsigned e = 3e9;
+float f = 16777217;
+long g = 1e10;
+int h = 0.1 + 0.2 == 0.3;
+int i = half < 1 && half;
+int main() { return 0; }
+"#,
+        );
+        assert_eq!(
+            values,
+            [
+                Float(0.5),
+                Float(2.5),
+                Float(3.0),
+                Int(3),
+                Int(-2),
+                UInt(3_000_000_000),
+                Float(16777216.0),
+                Long(10_000_000_000),
+                Int(1),
+                Int(1),
+            ]
+        );
+    }
+}
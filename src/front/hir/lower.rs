@@ -0,0 +1,586 @@
+use std::collections::HashMap;
+
+use koopa::ir::BinaryOp;
+
+use crate::front::{
+    ast::{self, SymKind, Ty},
+    gen::prelude::lib_sigs,
+};
+
+use super::*;
+
+/// 将 AST 降为 HIR，语义错误在此报告
+pub fn lower(items: &[ast::Item]) -> Program {
+    let mut lower = Lower::new();
+    let items = items.iter().flat_map(|item| lower.item(item)).collect();
+    Program {
+        symbols: lower.symbols,
+        funcs: lower.funcs,
+        items,
+        refs: lower.refs,
+    }
+}
+
+struct Lower {
+    symbols: Vec<Symbol>,
+    funcs: Vec<FuncSig>,
+    func_ids: HashMap<String, FuncId>,
+    /// 作用域栈，最外层为全局作用域
+    scopes: Vec<HashMap<String, SymId>>,
+    refs: Vec<(Span, Ref)>,
+    ret_ty: Ty,
+    loop_depth: usize,
+}
+
+impl Lower {
+    fn new() -> Lower {
+        let mut lower = Lower {
+            symbols: vec![],
+            funcs: vec![],
+            func_ids: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            refs: vec![],
+            ret_ty: Ty::Void,
+            loop_depth: 0,
+        };
+        for sig in lib_sigs() {
+            lower.declare_func(sig);
+        }
+        lower
+    }
+
+    fn declare_func(&mut self, sig: FuncSig) -> FuncId {
+        let id = FuncId(self.funcs.len());
+        if let Some(span) = sig.span {
+            self.refs.push((span, Ref::Func(id)));
+        }
+        self.func_ids.insert(sig.ident.clone(), id);
+        self.funcs.push(sig);
+        id
+    }
+
+    fn declare_sym(&mut self, ident: &ast::Ident, ty: Ty, kind: SymKind, value: Option<ConstVal>) -> SymId {
+        let id = SymId(self.symbols.len());
+        self.symbols.push(Symbol {
+            ident: ident.name.clone(),
+            ty,
+            kind,
+            value,
+            global: self.scopes.len() == 1,
+            span: ident.span,
+        });
+        self.scopes.last_mut().unwrap().insert(ident.name.clone(), id);
+        self.refs.push((ident.span, Ref::Sym(id)));
+        id
+    }
+
+    fn resolve(&mut self, ident: &ast::Ident) -> SymId {
+        let id = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&ident.name).copied())
+            .unwrap_or_else(|| {
+                panic!("SemanticsError[UndefinedSymbol]: '{}' is used before definition.", ident)
+            });
+        self.refs.push((ident.span, Ref::Sym(id)));
+        id
+    }
+
+    fn resolve_func(&mut self, ident: &ast::Ident) -> FuncId {
+        let id = *self.func_ids.get(&ident.name).unwrap_or_else(|| {
+            panic!("SemanticsError[UndefinedFunc]: '{}' is called before definition.", ident)
+        });
+        self.refs.push((ident.span, Ref::Func(id)));
+        id
+    }
+
+    /// 一条全局声明可能定义多个符号，各自成为一个条目
+    fn item(&mut self, item: &ast::Item) -> Vec<Item> {
+        match &item.kind {
+            ast::ItemKind::Global(decls) => decls.iter().map(|d| Item::Global(self.global(d))).collect(),
+            ast::ItemKind::Func(f) => vec![Item::Func(self.func(f))],
+        }
+    }
+
+    fn global(&mut self, d: &ast::Decl) -> Global {
+        let init = d.exp.as_ref().map(|e| {
+            let e = self.exp(e);
+            e.eval().map(|c| c.cast(d.ty)).unwrap_or_else(|| {
+                panic!("SemanticsError[ConstEvalFailure]: '{}' cannot be evaluated during compile time.", d.ident)
+            })
+        });
+        let value = if d.kind == SymKind::Const { init } else { None };
+        let sym = self.declare_sym(&d.ident, d.ty, d.kind, value);
+        Global { sym, init }
+    }
+
+    fn func(&mut self, f: &ast::Func) -> Func {
+        let id = self.declare_func(FuncSig {
+            ident: f.ident.name.clone(),
+            params: f.params.iter().map(|p| p.ty).collect(),
+            output: f.output,
+            span: Some(f.ident.span),
+        });
+        self.ret_ty = f.output;
+        self.scopes.push(HashMap::new());
+        let params = f
+            .params
+            .iter()
+            .map(|p| self.declare_sym(&p.ident, p.ty, SymKind::Var, None))
+            .collect();
+        let body = self.block(&f.block);
+        self.scopes.pop();
+        Func { id, params, body, span: f.span }
+    }
+
+    fn block(&mut self, block: &ast::Block) -> Vec<Stmt> {
+        self.scopes.push(HashMap::new());
+        let mut stmts = vec![];
+        for item in &block.0 {
+            match item {
+                ast::BlockItem::Stmt(s) => self.stmt(s, &mut stmts),
+                ast::BlockItem::Decl(decls) => decls.iter().for_each(|d| self.local(d, &mut stmts)),
+            }
+        }
+        self.scopes.pop();
+        stmts
+    }
+
+    /// 单条语句作为分支或循环体时，同样有自己的作用域
+    fn body(&mut self, stmt: &ast::Stmt) -> Vec<Stmt> {
+        let mut stmts = vec![];
+        self.scopes.push(HashMap::new());
+        self.stmt(stmt, &mut stmts);
+        self.scopes.pop();
+        stmts
+    }
+
+    fn local(&mut self, d: &ast::Decl, stmts: &mut Vec<Stmt>) {
+        let init = d.exp.as_ref().map(|e| {
+            let e = self.exp(e);
+            self.cast(e, d.ty)
+        });
+        match d.kind {
+            SymKind::Const => {
+                let value = init.and_then(|e| e.eval()).unwrap_or_else(|| {
+                    panic!("SemanticsError[ConstEvalFailure]: '{}' cannot be evaluated during compile time.", d.ident)
+                });
+                self.declare_sym(&d.ident, d.ty, SymKind::Const, Some(value));
+            }
+            SymKind::Var => {
+                // 初始值须在符号进入作用域之前解析
+                let init = init.map(|e| match e.eval() {
+                    Some(c) => Expr::constant(c),
+                    None => e,
+                });
+                let sym = self.declare_sym(&d.ident, d.ty, SymKind::Var, None);
+                stmts.push(Stmt::Let(sym, init));
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt, stmts: &mut Vec<Stmt>) {
+        use ast::StmtKind;
+        match &stmt.kind {
+            StmtKind::Unit => {}
+            StmtKind::Exp(e) => stmts.push(Stmt::Expr(self.exp(e))),
+            StmtKind::Block(b) => stmts.extend(self.block(b)),
+            StmtKind::Assign(l, e) => {
+                let sym = self.resolve(&l.0);
+                let symbol = &self.symbols[sym.0];
+                assert!(
+                    symbol.kind == SymKind::Var,
+                    "SemanticsError[InvalidLValAssignment]: '{}' cannot be assigned to.",
+                    &l.0
+                );
+                let ty = symbol.ty;
+                let e = self.exp(e);
+                stmts.push(Stmt::Assign(sym, self.cast(e, ty)));
+            }
+            StmtKind::If(cond, then, alt) => {
+                let cond = self.exp(cond);
+                let cond = self.cond(cond);
+                let then = self.body(then);
+                let alt = alt.as_ref().map(|alt| self.body(alt));
+                stmts.push(Stmt::If(cond, then, alt));
+            }
+            StmtKind::While(cond, body) => {
+                let cond = self.exp(cond);
+                let cond = self.cond(cond);
+                self.loop_depth += 1;
+                let body = self.body(body);
+                self.loop_depth -= 1;
+                stmts.push(Stmt::While(cond, body));
+            }
+            StmtKind::Break | StmtKind::Continue => {
+                assert!(
+                    self.loop_depth > 0,
+                    "SemanticsError[OutsideLoop]: 'break' or 'continue' is used outside a loop."
+                );
+                stmts.push(if matches!(stmt.kind, StmtKind::Break) { Stmt::Break } else { Stmt::Continue });
+            }
+            StmtKind::Return(r) => {
+                let r = r.as_ref().map(|r| {
+                    let r = self.exp(r);
+                    self.cast(r, self.ret_ty)
+                });
+                stmts.push(Stmt::Return(r));
+            }
+        }
+    }
+
+    /// 隐式类型转换，常量直接转换
+    fn cast(&self, e: Expr, ty: Ty) -> Expr {
+        if e.ty == Ty::Void || ty == Ty::Void {
+            panic!("SemanticsError[VoidOperand]: void value cannot be converted.");
+        }
+        match e.kind {
+            _ if e.ty == ty => e,
+            ExprKind::Const(c) => Expr::constant(c.cast(ty)),
+            _ => Expr::new(ty, ExprKind::Cast(Box::new(e))),
+        }
+    }
+
+    /// 作为条件的表达式，非 `int` 的值与 0 比较
+    fn cond(&self, e: Expr) -> Expr {
+        match e.ty {
+            Ty::Int => e,
+            Ty::Void => panic!("SemanticsError[VoidOperand]: void value used as a condition."),
+            ty => {
+                let zero = Expr::constant(ConstVal::Int(0).cast(ty));
+                Expr::new(Ty::Int, ExprKind::Binary(BinaryOp::NotEq, Box::new(e), Box::new(zero)))
+            }
+        }
+    }
+
+    /// 经过寻常算术转换的二元运算
+    fn binary(&self, op: BinaryOp, l: Expr, r: Expr) -> Expr {
+        use BinaryOp::*;
+        let ty = Ty::common(l.ty, r.ty);
+        if ty == Ty::Float && op == Mod {
+            panic!("SemanticsError[InvalidOperand]: '%' cannot be applied to float.");
+        }
+        let res_ty = match op {
+            Eq | NotEq | Lt | Gt | Le | Ge => Ty::Int,
+            _ => ty,
+        };
+        let (l, r) = (self.cast(l, ty), self.cast(r, ty));
+        Expr::new(res_ty, ExprKind::Binary(op, Box::new(l), Box::new(r)))
+    }
+
+    fn exp(&mut self, e: &ast::Exp) -> Expr {
+        self.lor(&e.0)
+    }
+
+    fn lor(&mut self, e: &ast::LOrExp) -> Expr {
+        match e {
+            ast::LOrExp::Unary(e) => self.land(e),
+            ast::LOrExp::Binary(l, r) => {
+                let l = self.lor(l);
+                let r = self.land(r);
+                let (l, r) = (self.cond(l), self.cond(r));
+                Expr::new(Ty::Int, ExprKind::Or(Box::new(l), Box::new(r)))
+            }
+        }
+    }
+
+    fn land(&mut self, e: &ast::LAndExp) -> Expr {
+        match e {
+            ast::LAndExp::Unary(e) => self.eq(e),
+            ast::LAndExp::Binary(l, r) => {
+                let l = self.land(l);
+                let r = self.eq(r);
+                let (l, r) = (self.cond(l), self.cond(r));
+                Expr::new(Ty::Int, ExprKind::And(Box::new(l), Box::new(r)))
+            }
+        }
+    }
+
+    fn eq(&mut self, e: &ast::EqExp) -> Expr {
+        match e {
+            ast::EqExp::Unary(e) => self.rel(e),
+            ast::EqExp::Binary(l, o, r) => {
+                let op = match o {
+                    ast::EqOp::Eq => BinaryOp::Eq,
+                    ast::EqOp::Ne => BinaryOp::NotEq,
+                };
+                let l = self.eq(l);
+                let r = self.rel(r);
+                self.binary(op, l, r)
+            }
+        }
+    }
+
+    fn rel(&mut self, e: &ast::RelExp) -> Expr {
+        match e {
+            ast::RelExp::Unary(e) => self.add(e),
+            ast::RelExp::Binary(l, o, r) => {
+                let op = match o {
+                    ast::RelOp::Lt => BinaryOp::Lt,
+                    ast::RelOp::Gt => BinaryOp::Gt,
+                    ast::RelOp::Le => BinaryOp::Le,
+                    ast::RelOp::Ge => BinaryOp::Ge,
+                };
+                let l = self.rel(l);
+                let r = self.add(r);
+                self.binary(op, l, r)
+            }
+        }
+    }
+
+    fn add(&mut self, e: &ast::AddExp) -> Expr {
+        match e {
+            ast::AddExp::Unary(e) => self.mul(e),
+            ast::AddExp::Binary(l, o, r) => {
+                let op = match o {
+                    ast::AddOp::Add => BinaryOp::Add,
+                    ast::AddOp::Sub => BinaryOp::Sub,
+                };
+                let l = self.add(l);
+                let r = self.mul(r);
+                self.binary(op, l, r)
+            }
+        }
+    }
+
+    fn mul(&mut self, e: &ast::MulExp) -> Expr {
+        match e {
+            ast::MulExp::Unary(e) => self.unary(e),
+            ast::MulExp::Binary(l, o, r) => {
+                let op = match o {
+                    ast::MulOp::Mul => BinaryOp::Mul,
+                    ast::MulOp::Div => BinaryOp::Div,
+                    ast::MulOp::Mod => BinaryOp::Mod,
+                };
+                let l = self.mul(l);
+                let r = self.unary(r);
+                self.binary(op, l, r)
+            }
+        }
+    }
+
+    fn unary(&mut self, e: &ast::UnaryExp) -> Expr {
+        match e {
+            ast::UnaryExp::Primary(p) => self.primary(p),
+            ast::UnaryExp::Unary(ast::UnaryOp::Minus, e) => {
+                let e = self.unary(e);
+                if e.ty == Ty::Void {
+                    panic!("SemanticsError[VoidOperand]: void value used in an expression.");
+                }
+                Expr::new(e.ty, ExprKind::Neg(Box::new(e)))
+            }
+            ast::UnaryExp::Unary(ast::UnaryOp::LNot, e) => {
+                // `!e` 即 `0 == e`
+                let e = self.unary(e);
+                let zero = Expr::constant(ConstVal::Int(0));
+                self.binary(BinaryOp::Eq, zero, e)
+            }
+            ast::UnaryExp::Call(ident, args) => {
+                let id = self.resolve_func(ident);
+                let sig = self.funcs[id.0].clone();
+                assert!(
+                    sig.params.len() == args.len(),
+                    "SemanticsError[ArgumentMismatch]: '{}' expects {} arguments but {} are given.",
+                    ident,
+                    sig.params.len(),
+                    args.len()
+                );
+                let args = args
+                    .iter()
+                    .zip(&sig.params)
+                    .map(|(a, ty)| {
+                        let a = self.exp(a);
+                        self.cast(a, *ty)
+                    })
+                    .collect();
+                Expr::new(sig.output, ExprKind::Call(id, args))
+            }
+        }
+    }
+
+    fn primary(&mut self, e: &ast::PrimaryExp) -> Expr {
+        match e {
+            ast::PrimaryExp::Exp(e) => self.exp(e),
+            ast::PrimaryExp::Literal(l) => Expr::constant((*l).into()),
+            ast::PrimaryExp::LVal(l) => {
+                let id = self.resolve(&l.0);
+                let symbol = &self.symbols[id.0];
+                match symbol.value {
+                    Some(c) => Expr::constant(c),
+                    None => Expr::new(symbol.ty, ExprKind::Var(id)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::front::into_hir;
+
+    use super::*;
+
+    /// 以 S 表达式逐条输出最后一个函数的语句，符号写作 `名字#编号`
+    fn dump(source: &str) -> Vec<String> {
+        let program = into_hir(source.to_string());
+        let Some(Item::Func(f)) = program.items.last() else {
+            panic!("no function")
+        };
+        f.body.iter().map(|s| stmt(&program, s)).collect()
+    }
+
+    fn sym(p: &Program, id: SymId) -> String {
+        format!("{}#{}", p.symbol(id).ident, id.0)
+    }
+
+    fn stmts(p: &Program, body: &[Stmt]) -> String {
+        let body: Vec<String> = body.iter().map(|s| stmt(p, s)).collect();
+        format!("({})", body.join(" "))
+    }
+
+    fn stmt(p: &Program, s: &Stmt) -> String {
+        match s {
+            Stmt::Let(id, None) => format!("(let {})", sym(p, *id)),
+            Stmt::Let(id, Some(e)) => format!("(let {} {})", sym(p, *id), expr(p, e)),
+            Stmt::Assign(id, e) => format!("(set {} {})", sym(p, *id), expr(p, e)),
+            Stmt::Expr(e) => expr(p, e),
+            Stmt::If(c, then, None) => format!("(if {} {})", expr(p, c), stmts(p, then)),
+            Stmt::If(c, then, Some(alt)) => format!("(if {} {} {})", expr(p, c), stmts(p, then), stmts(p, alt)),
+            Stmt::While(c, body) => format!("(while {} {})", expr(p, c), stmts(p, body)),
+            Stmt::Break => "(break)".to_string(),
+            Stmt::Continue => "(continue)".to_string(),
+            Stmt::Return(None) => "(return)".to_string(),
+            Stmt::Return(Some(e)) => format!("(return {})", expr(p, e)),
+        }
+    }
+
+    fn expr(p: &Program, e: &Expr) -> String {
+        match &e.kind {
+            ExprKind::Const(c) => format!("{:?}", c),
+            ExprKind::Var(id) => sym(p, *id),
+            ExprKind::Cast(x) => format!("(cast {:?} {})", e.ty, expr(p, x)),
+            ExprKind::Neg(x) => format!("(neg {})", expr(p, x)),
+            ExprKind::Binary(op, l, r) => format!("({:?} {} {})", op, expr(p, l), expr(p, r)),
+            ExprKind::And(l, r) => format!("(and {} {})", expr(p, l), expr(p, r)),
+            ExprKind::Or(l, r) => format!("(or {} {})", expr(p, l), expr(p, r)),
+            ExprKind::Call(id, args) => {
+                let args: Vec<String> = args.iter().map(|a| expr(p, a)).collect();
+                format!("(call {} {})", p.func(*id).ident, args.join(" "))
+            }
+        }
+    }
+
+    #[test]
+    fn casts() {
+        // 寻常算术转换、赋值、返回和实参处的隐式转换都显式化为 `Cast`，常量直接转换
+        let body = dump(
+            r#"
+float f(int n, unsigned u, long l) {
+  float x = n;
+  x = x + u * l;
+  putint(x);
+  return n + 1;
+}
+"#,
+        );
+        assert_eq!(
+            body,
+            [
+                "(let x#3 (cast Float n#0))",
+                "(set x#3 (Add x#3 (cast Float (Mul (cast Long u#1) l#2))))",
+                "(call putint (cast Int x#3))",
+                "(return (cast Float (Add n#0 Int(1))))",
+            ]
+        );
+    }
+
+    #[test]
+    fn not() {
+        // `!e` 化为 `0 == e`，`0` 按 `e` 的类型转换；非 `int` 的条件与 0 比较
+        let body = dump(
+            r#"
+int g(int a, float x) {
+  if (!a) return !x;
+  while (x && a) x = x - 1;
+  return !!a;
+}
+"#,
+        );
+        assert_eq!(
+            body,
+            [
+                "(if (Eq Int(0) a#0) ((return (Eq Float(0.0) x#1))))",
+                "(while (and (NotEq x#1 Float(0.0)) a#0) ((set x#1 (Sub x#1 Float(1.0)))))",
+                "(return (Eq Int(0) (Eq Int(0) a#0)))",
+            ]
+        );
+    }
+
+    #[test]
+    fn shadowing() {
+        // 同名的全局变量、参数和各层局部变量解析为不同的符号；初始值在变量进入作用域之前解析，
+        // 常量的引用替换为其值
+        let body = dump(
+            r#"
+int a = 1;
+int h(int a) {
+  int b = a;
+  {
+    int a = 2;
+    b = b + a;
+  }
+  const int c = 3;
+  if (b) {
+    int c = c + 1;
+    b = c;
+  }
+  return a + b + c;
+}
+"#,
+        );
+        assert_eq!(
+            body,
+            [
+                "(let b#2 a#1)",
+                "(let a#3 Int(2))",
+                "(set b#2 (Add b#2 a#3))",
+                "(if b#2 ((let c#5 Int(4)) (set b#2 c#5)))",
+                "(return (Add (Add a#1 b#2) Int(3)))",
+            ]
+        );
+    }
+
+    #[test]
+    fn refs() {
+        // 每个标识符都记录了位置与解析结果，包括定义处的名字；按在源代码中出现的顺序列出
+        let source = r#"
+int a = 1;
+int f(int a) {
+  int b = a;
+  { int a = b; }
+  putint(a);
+  return f(b);
+}
+"#;
+        let program = into_hir(source.to_string());
+        let mut refs = program.refs.clone();
+        refs.sort_by_key(|(span, _)| span.lo);
+        let refs: Vec<String> = refs
+            .into_iter()
+            .map(|(span, r)| {
+                let (ident, target) = match r {
+                    Ref::Sym(id) => (program.symbol(id).ident.clone(), sym(&program, id)),
+                    Ref::Func(id) => (program.func(id).ident.clone(), program.func(id).ident.clone()),
+                };
+                assert_eq!(&source[span.lo..span.hi], ident);
+                target
+            })
+            .collect();
+        assert_eq!(refs, ["a#0", "f", "a#1", "b#2", "a#1", "a#3", "b#2", "putint", "a#1", "f", "b#2"]);
+        // 用户函数排在库函数之后，其定义处的名字也在引用之列
+        let f = Ref::Func(FuncId(program.funcs.len() - 1));
+        assert_eq!(program.references(f).len(), 2);
+        assert_eq!(program.def_span(f), program.references(f).first().copied());
+    }
+}
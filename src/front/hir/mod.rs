@@ -0,0 +1,171 @@
+//! 高层中间表示（HIR）
+//!
+//! 介于 AST 与 Koopa IR 之间。由 [`lower`] 从 AST 得到，此时已经完成：
+//!
+//! - 名字解析：变量、参数、常量解析为唯一的 [`SymId`]，函数解析为 [`FuncId`]，作用域不复存在，块被展平；
+//!   每个标识符的位置及其解析结果记录在 [`Program::refs`] 中；
+//! - 类型检查：每个表达式都带有类型，隐式类型转换显式化为 [`ExprKind::Cast`]；
+//! - 常量求值：对常量的引用替换为 [`ExprKind::Const`]，能在编译期求值的初始值被折叠；
+//! - 去糖：条件一律为 `int`，`!e` 化为 `0 == e`。
+//!
+//! Koopa IR 的生成（[`crate::front::gen`]）只面向 HIR。
+
+use koopa::ir;
+
+use super::ast::{Span, SymKind, Ty};
+
+pub mod eval;
+mod lower;
+
+pub use eval::ConstVal;
+pub use lower::lower;
+
+/// 变量、参数或常量的唯一编号，为 [`Program::symbols`] 的下标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymId(pub usize);
+
+/// 函数的唯一编号，为 [`Program::funcs`] 的下标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FuncId(pub usize);
+
+/// 标识符解析到的符号或函数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ref {
+    Sym(SymId),
+    Func(FuncId),
+}
+
+#[derive(Debug)]
+pub struct Program {
+    pub symbols: Vec<Symbol>,
+    /// 所有可调用的函数的签名，包括库函数
+    pub funcs: Vec<FuncSig>,
+    pub items: Vec<Item>,
+    /// 源代码中每个标识符（包括定义处的名字）的位置及其解析结果，按降级的顺序排列
+    pub refs: Vec<(Span, Ref)>,
+}
+
+impl Program {
+    pub fn symbol(&self, id: SymId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+
+    pub fn func(&self, id: FuncId) -> &FuncSig {
+        &self.funcs[id.0]
+    }
+
+    /// 定义处的名字的位置，库函数没有
+    pub fn def_span(&self, r: Ref) -> Option<Span> {
+        match r {
+            Ref::Sym(id) => Some(self.symbol(id).span),
+            Ref::Func(id) => self.func(id).span,
+        }
+    }
+
+    /// 位于字节偏移 `offset` 的标识符及其解析结果，偏移紧跟在标识符之后也算
+    pub fn ref_at(&self, offset: usize) -> Option<(Span, Ref)> {
+        self.refs.iter().find(|(span, _)| span.contains(offset)).copied()
+    }
+
+    /// 解析到 `r` 的所有标识符的位置，按在源代码中出现的顺序排列
+    pub fn references(&self, r: Ref) -> Vec<Span> {
+        let mut spans: Vec<Span> = self.refs.iter().filter(|&&(_, x)| x == r).map(|&(span, _)| span).collect();
+        spans.sort_by_key(|span| span.lo);
+        spans
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    /// 源代码中的名字，不同作用域中的符号可能重名
+    pub ident: String,
+    pub ty: Ty,
+    pub kind: SymKind,
+    /// 常量在编译期的值
+    pub value: Option<ConstVal>,
+    pub global: bool,
+    /// 定义处的名字的位置
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct FuncSig {
+    pub ident: String,
+    pub params: Vec<Ty>,
+    pub output: Ty,
+    /// 定义处的名字的位置，库函数为 `None`
+    pub span: Option<Span>,
+}
+
+#[derive(Debug)]
+pub enum Item {
+    Global(Global),
+    Func(Func),
+}
+
+/// 全局常量或变量，初始值在编译期求得
+#[derive(Debug)]
+pub struct Global {
+    pub sym: SymId,
+    pub init: Option<ConstVal>,
+}
+
+#[derive(Debug)]
+pub struct Func {
+    pub id: FuncId,
+    pub params: Vec<SymId>,
+    pub body: Vec<Stmt>,
+    /// 整个定义的位置
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    /// 局部变量的定义，初始值已转换为变量的类型；局部常量不产生语句
+    Let(SymId, Option<Expr>),
+    /// 赋值，右侧已转换为变量的类型
+    Assign(SymId, Expr),
+    Expr(Expr),
+    /// 条件为 `int`
+    If(Expr, Vec<Stmt>, Option<Vec<Stmt>>),
+    /// 条件为 `int`
+    While(Expr, Vec<Stmt>),
+    Break,
+    Continue,
+    /// 返回值已转换为函数的返回类型
+    Return(Option<Expr>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub ty: Ty,
+    pub kind: ExprKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Const(ConstVal),
+    /// 读取变量或参数
+    Var(SymId),
+    /// 转换为 [`Expr::ty`]
+    Cast(Box<Expr>),
+    Neg(Box<Expr>),
+    /// 两侧的类型相同；比较运算的结果为 `int`，其余与操作数同类型
+    Binary(ir::BinaryOp, Box<Expr>, Box<Expr>),
+    /// 短路与，两侧均为 `int` 的条件
+    And(Box<Expr>, Box<Expr>),
+    /// 短路或，两侧均为 `int` 的条件
+    Or(Box<Expr>, Box<Expr>),
+    /// 实参已转换为形参的类型
+    Call(FuncId, Vec<Expr>),
+}
+
+impl Expr {
+    pub fn new(ty: Ty, kind: ExprKind) -> Expr {
+        Expr { ty, kind }
+    }
+
+    pub fn constant(c: ConstVal) -> Expr {
+        Expr::new(c.ty(), ExprKind::Const(c))
+    }
+}
@@ -0,0 +1,273 @@
+//! 可单独开关的警告，大多在 HIR 上检查，`uninitialized` 在生成的 Koopa IR 上检查
+//!
+//! 每条警告有一个名字（[`Lint::name`]），命令行中以 `-W<name>` 开启、`-Wno-<name>` 关闭，
+//! `-Wall` 开启全部警告，`-Werror` 将警告视为错误。
+
+use std::{collections::HashSet, fmt};
+
+use super::{hir, Ir};
+
+mod rules;
+mod uninit;
+
+pub struct Lint {
+    pub name: &'static str,
+    /// 不给出任何 `-W` 时是否开启
+    pub default: bool,
+    pub desc: &'static str,
+}
+
+pub const LINTS: &[Lint] = &[
+    Lint { name: "unused-variable", default: true, desc: "local variable that is never used" },
+    Lint { name: "unused-but-set-variable", default: true, desc: "local variable that is assigned but never read" },
+    Lint { name: "unused-parameter", default: false, desc: "function parameter that is never read" },
+    Lint { name: "unused-function", default: true, desc: "function that is never called" },
+    Lint { name: "unreachable-code", default: true, desc: "statement after 'return', 'break' or 'continue'" },
+    Lint { name: "constant-condition", default: false, desc: "condition of 'if' or 'while' that is a compile-time constant" },
+    Lint { name: "uninitialized", default: true, desc: "local variable that may be read before it is assigned" },
+    Lint { name: "return-type", default: true, desc: "control reaches the end of a non-void function" },
+];
+
+#[derive(Debug, Clone)]
+pub struct LintConfig {
+    enabled: HashSet<&'static str>,
+    pub werror: bool,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            enabled: LINTS.iter().filter(|l| l.default).map(|l| l.name).collect(),
+            werror: false,
+        }
+    }
+}
+
+impl LintConfig {
+    /// 处理一个 `-W...` 参数
+    pub fn apply(&mut self, flag: &str) {
+        let flag = flag.strip_prefix("-W").expect("Not a warning option!");
+        match flag {
+            "all" => self.enabled.extend(LINTS.iter().map(|l| l.name)),
+            "error" => self.werror = true,
+            "no-error" => self.werror = false,
+            _ => {
+                let (name, on) = match flag.strip_prefix("no-") {
+                    Some(name) => (name, false),
+                    None => (flag, true),
+                };
+                let lint = LINTS
+                    .iter()
+                    .find(|l| l.name == name)
+                    .unwrap_or_else(|| panic!("Unknown warning option '-W{}'.", flag));
+                if on {
+                    self.enabled.insert(lint.name);
+                } else {
+                    self.enabled.remove(lint.name);
+                }
+            }
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.enabled.contains(name)
+    }
+
+    fn filter(&self, mut diags: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diags.retain(|d| self.is_enabled(d.lint));
+        for d in diags.iter_mut() {
+            d.error = self.werror;
+        }
+        diags
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub lint: &'static str,
+    /// 所在的函数，全局条目为 `None`
+    pub func: Option<String>,
+    pub message: String,
+    pub error: bool,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(func) = &self.func {
+            write!(f, "in function '{}': ", func)?;
+        }
+        if self.error {
+            write!(f, "error: {} [-Werror={}]", self.message, self.lint)
+        } else {
+            write!(f, "warning: {} [-W{}]", self.message, self.lint)
+        }
+    }
+}
+
+/// 输出警告；`-Werror` 时只要有警告就返回错误
+pub fn emit(diags: &[Diagnostic], config: &LintConfig) -> Result<(), String> {
+    for d in diags.iter() {
+        eprintln!("{}", d);
+    }
+    if config.werror && !diags.is_empty() {
+        return Err(format!("{} warning(s) treated as errors", diags.len()));
+    }
+    Ok(())
+}
+
+/// 按条目的顺序返回所有开启的、在 HIR 上检查的警告
+pub fn check(program: &hir::Program, config: &LintConfig) -> Vec<Diagnostic> {
+    config.filter(rules::check(program))
+}
+
+/// 在生成的 Koopa IR 上检查的警告，目前只有 `uninitialized`
+pub fn check_ir(ir: &Ir, config: &LintConfig) -> Vec<Diagnostic> {
+    config.filter(uninit::check(&ir.program, &ir.long_halves))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::front::{into_hir, into_ir_text};
+
+    /// 按给定的 `-W` 参数检查源程序，返回输出的警告
+    fn lint(source: &str, flags: &[&str]) -> Vec<String> {
+        let mut config = LintConfig::default();
+        for flag in flags {
+            config.apply(flag);
+        }
+        let hir = into_hir(source.to_string());
+        let mut diags = check(&hir, &config);
+        let ir: Ir = hir.try_into().unwrap();
+        diags.extend(check_ir(&ir, &config));
+        diags.iter().map(|d| d.to_string()).collect()
+    }
+
+    const UNUSED: &str = r#"
+int g;
+int f(int n) { if (n) return f(n - 1); return 0; }
+int h(int a, int b) { return a; }
+int main() {
+    int x;
+    int y = 1;
+    y = 2;
+    int z = h(1, 2);
+    return z;
+}
+"#;
+
+    #[test]
+    fn unused() {
+        assert_eq!(
+            lint(UNUSED, &[]),
+            [
+                "in function 'f': warning: 'f' is defined but not used [-Wunused-function]",
+                "in function 'main': warning: unused variable 'x' [-Wunused-variable]",
+                "in function 'main': warning: variable 'y' set but not used [-Wunused-but-set-variable]",
+            ]
+        );
+        assert_eq!(
+            lint(UNUSED, &["-Wunused-parameter", "-Wno-unused-variable"]),
+            [
+                "in function 'f': warning: 'f' is defined but not used [-Wunused-function]",
+                "in function 'h': warning: unused parameter 'b' [-Wunused-parameter]",
+                "in function 'main': warning: variable 'y' set but not used [-Wunused-but-set-variable]",
+            ]
+        );
+    }
+
+    const FLOW: &str = r#"
+int main() {
+    int i = 0;
+    while (1) {
+        if (i > 3) break;
+        i = i + 1;
+        continue;
+        i = i + 2;
+    }
+    if (0) i = 1;
+    return i;
+    i = 3;
+}
+"#;
+
+    #[test]
+    fn unreachable_and_constant() {
+        assert_eq!(
+            lint(FLOW, &[]),
+            [
+                "in function 'main': warning: statement will never be executed [-Wunreachable-code]",
+                "in function 'main': warning: statement will never be executed [-Wunreachable-code]",
+            ]
+        );
+        assert_eq!(
+            lint(FLOW, &["-Wall", "-Wno-unreachable-code"]),
+            [
+                "in function 'main': warning: condition of 'while' is always true [-Wconstant-condition]",
+                "in function 'main': warning: condition of 'if' is always false [-Wconstant-condition]",
+            ]
+        );
+    }
+
+    #[test]
+    fn return_type() {
+        let source = r#"
+int no_else(int n) { if (n) return 1; }
+int both(int n) { if (n) return 1; else return 2; }
+int loop_return(int n) { while (1) { if (n) return n; n = n + 1; } }
+int loop_break(int n) { while (1) { if (n) break; n = n + 1; } }
+void nothing() {}
+int main() { nothing(); return no_else(1) + both(1) + loop_return(1) + loop_break(1); }
+"#;
+        assert_eq!(
+            lint(source, &[]),
+            [
+                "in function 'no_else': warning: control reaches end of non-void function [-Wreturn-type]",
+                "in function 'loop_break': warning: control reaches end of non-void function [-Wreturn-type]",
+            ]
+        );
+        // 警告之外，落到末尾时仍然补上 `ret 0`
+        let ir: Ir = into_hir(source.to_string()).try_into().unwrap();
+        let text = into_ir_text(ir).unwrap();
+        let no_else = &text[text.find("fun @no_else").unwrap()..text.find("fun @both").unwrap()];
+        assert!(no_else.contains("ret 0\n"), "{}", no_else);
+    }
+
+    #[test]
+    fn uninitialized() {
+        let source = "int main() { int x; int y; y = x + 1; return y; }";
+        assert_eq!(
+            lint(source, &[]),
+            ["in function 'main': warning: 'x' may be used uninitialized [-Wuninitialized]"]
+        );
+        assert!(lint(source, &["-Wno-uninitialized"]).is_empty());
+    }
+
+    #[test]
+    fn werror() {
+        let source = "int main() { int x; return 0; }";
+        let mut config = LintConfig::default();
+        config.apply("-Werror");
+        let diags = check(&into_hir(source.to_string()), &config);
+        assert_eq!(
+            diags,
+            [Diagnostic {
+                lint: "unused-variable",
+                func: Some("main".to_string()),
+                message: "unused variable 'x'".to_string(),
+                error: true,
+            }]
+        );
+        assert_eq!(
+            diags[0].to_string(),
+            "in function 'main': error: unused variable 'x' [-Werror=unused-variable]"
+        );
+        assert_eq!(emit(&diags, &config), Err("1 warning(s) treated as errors".to_string()));
+        assert_eq!(emit(&[], &config), Ok(()));
+
+        config.apply("-Wno-error");
+        let diags = check(&into_hir(source.to_string()), &config);
+        assert!(!diags[0].error);
+        assert_eq!(emit(&diags, &config), Ok(()));
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+
+use crate::front::{
+    ast::Ty,
+    hir::{Expr, ExprKind, Func, FuncId, Item, Program, Stmt, SymId},
+};
+
+use super::Diagnostic;
+
+pub fn check(program: &Program) -> Vec<Diagnostic> {
+    let funcs: Vec<&Func> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Func(f) => Some(f),
+            Item::Global(_) => None,
+        })
+        .collect();
+
+    // 被其他函数调用过的函数，只有递归调用的函数仍视为未使用
+    let mut called = HashSet::new();
+    let usages: Vec<Usage> = funcs
+        .iter()
+        .map(|f| {
+            let mut usage = Usage::default();
+            usage.stmts(&f.body);
+            called.extend(usage.calls.iter().copied().filter(|id| *id != f.id));
+            usage
+        })
+        .collect();
+
+    let mut diags = vec![];
+    for (f, usage) in funcs.into_iter().zip(usages) {
+        let mut lints = FuncLints {
+            program,
+            ident: &program.func(f.id).ident,
+            diags: &mut diags,
+        };
+        if !called.contains(&f.id) && lints.ident != "main" {
+            lints.report("unused-function", format!("'{}' is defined but not used", lints.ident));
+        }
+        lints.usage(f, usage);
+        lints.reachability(&f.body);
+        if program.func(f.id).output != Ty::Void && falls_through(&f.body) {
+            lints.report("return-type", "control reaches end of non-void function".to_string());
+        }
+    }
+    diags
+}
+
+struct FuncLints<'a> {
+    program: &'a Program,
+    ident: &'a str,
+    diags: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> FuncLints<'a> {
+    fn report(&mut self, lint: &'static str, message: String) {
+        self.diags.push(Diagnostic {
+            lint,
+            func: Some(self.ident.to_string()),
+            message,
+            error: false,
+        });
+    }
+
+    fn usage(&mut self, f: &Func, usage: Usage) {
+        for p in f.params.iter() {
+            if !usage.read.contains(p) {
+                let ident = &self.program.symbol(*p).ident;
+                self.report("unused-parameter", format!("unused parameter '{}'", ident));
+            }
+        }
+        for id in usage.locals {
+            let ident = &self.program.symbol(id).ident;
+            if usage.read.contains(&id) {
+                continue;
+            }
+            if usage.assigned.contains(&id) {
+                self.report("unused-but-set-variable", format!("variable '{}' set but not used", ident));
+            } else {
+                self.report("unused-variable", format!("unused variable '{}'", ident));
+            }
+        }
+    }
+
+    /// 每个语句序列中第一条不可达的语句报告一次；常量条件也在此报告
+    fn reachability(&mut self, stmts: &[Stmt]) {
+        let mut terminated = false;
+        for s in stmts {
+            if terminated {
+                self.report("unreachable-code", "statement will never be executed".to_string());
+                break;
+            }
+            match s {
+                Stmt::Return(_) | Stmt::Break | Stmt::Continue => terminated = true,
+                Stmt::If(cond, then, alt) => {
+                    self.constant_condition("if", cond);
+                    self.reachability(then);
+                    if let Some(alt) = alt {
+                        self.reachability(alt);
+                    }
+                }
+                Stmt::While(cond, body) => {
+                    self.constant_condition("while", cond);
+                    self.reachability(body);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn constant_condition(&mut self, keyword: &str, cond: &Expr) {
+        if let Some(c) = cond.eval() {
+            let value = if c.is_true() { "true" } else { "false" };
+            self.report("constant-condition", format!("condition of '{}' is always {}", keyword, value));
+        }
+    }
+}
+
+/// 函数中对符号的读写和调用
+#[derive(Default)]
+struct Usage {
+    /// 按定义顺序排列的局部变量
+    locals: Vec<SymId>,
+    read: HashSet<SymId>,
+    assigned: HashSet<SymId>,
+    calls: HashSet<FuncId>,
+}
+
+impl Usage {
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        for s in stmts {
+            match s {
+                Stmt::Let(id, init) => {
+                    self.locals.push(*id);
+                    if let Some(e) = init {
+                        self.expr(e);
+                    }
+                }
+                Stmt::Assign(id, e) => {
+                    self.assigned.insert(*id);
+                    self.expr(e);
+                }
+                Stmt::Expr(e) | Stmt::Return(Some(e)) => self.expr(e),
+                Stmt::If(cond, then, alt) => {
+                    self.expr(cond);
+                    self.stmts(then);
+                    if let Some(alt) = alt {
+                        self.stmts(alt);
+                    }
+                }
+                Stmt::While(cond, body) => {
+                    self.expr(cond);
+                    self.stmts(body);
+                }
+                Stmt::Break | Stmt::Continue | Stmt::Return(None) => {}
+            }
+        }
+    }
+
+    fn expr(&mut self, e: &Expr) {
+        match &e.kind {
+            ExprKind::Const(_) => {}
+            ExprKind::Var(id) => {
+                self.read.insert(*id);
+            }
+            ExprKind::Cast(e) | ExprKind::Neg(e) => self.expr(e),
+            ExprKind::Binary(_, l, r) | ExprKind::And(l, r) | ExprKind::Or(l, r) => {
+                self.expr(l);
+                self.expr(r);
+            }
+            ExprKind::Call(id, args) => {
+                self.calls.insert(*id);
+                args.iter().for_each(|a| self.expr(a));
+            }
+        }
+    }
+}
+
+/// 控制流能否到达语句序列的末尾
+fn falls_through(stmts: &[Stmt]) -> bool {
+    stmts.iter().all(|s| match s {
+        Stmt::Return(_) | Stmt::Break | Stmt::Continue => false,
+        Stmt::If(cond, then, alt) => {
+            let alt = || alt.as_ref().is_none_or(|alt| falls_through(alt));
+            match cond.eval() {
+                Some(c) if c.is_true() => falls_through(then),
+                Some(_) => alt(),
+                None => falls_through(then) || alt(),
+            }
+        }
+        // 条件恒真的循环只能由 `break` 离开
+        Stmt::While(cond, body) => !cond.eval().is_some_and(|c| c.is_true()) || breaks(body),
+        _ => true,
+    })
+}
+
+/// 语句序列中是否有跳出当前循环的 `break`，不计内层循环中的
+fn breaks(stmts: &[Stmt]) -> bool {
+    stmts.iter().any(|s| match s {
+        Stmt::Break => true,
+        Stmt::If(_, then, alt) => breaks(then) || alt.as_ref().is_some_and(|alt| breaks(alt)),
+        _ => false,
+    })
+}
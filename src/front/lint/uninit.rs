@@ -0,0 +1,229 @@
+//! 未初始化变量的使用：在 Koopa 的控制流图上做前向数据流分析
+//!
+//! 没有初始值的局部变量在定义处被存入 `undef`。对每个基本块求入口处「可能仍为 `undef`」的 `alloc` 集合，
+//! 前驱的出口集合取并；`store undef` 将 `alloc` 加入集合，其他 `store` 将其移出。
+//! 从集合中的 `alloc` 读取即可能读到未初始化的值。分支、循环和短路求值产生的基本块都在控制流图中，无需特殊处理。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{BasicBlock, FunctionData, Program, Value, ValueKind};
+
+use crate::util::cfg;
+
+use super::Diagnostic;
+
+/// `long_halves` 为 `long` 变量高位字的 `alloc` 到低位字的 `alloc`，两半归为同一个变量
+pub fn check(program: &Program, long_halves: &HashMap<Value, Value>) -> Vec<Diagnostic> {
+    let mut diags = vec![];
+    for &func in program.func_layout() {
+        let func = program.func(func);
+        if func.layout().entry_bb().is_none() {
+            continue;
+        }
+        for var in uninit_loads(func, long_halves) {
+            diags.push(Diagnostic {
+                lint: "uninitialized",
+                func: Some(func.name()[1..].to_string()),
+                message: format!("'{}' may be used uninitialized", &var[1..]),
+                error: false,
+            });
+        }
+    }
+    diags
+}
+
+fn step(func: &FunctionData, inst: Value, state: &mut HashSet<Value>) {
+    if let ValueKind::Store(s) = func.dfg().value(inst).kind() {
+        if matches!(func.dfg().value(s.value()).kind(), ValueKind::Undef(_)) {
+            state.insert(s.dest());
+        } else {
+            state.remove(&s.dest());
+        }
+    }
+}
+
+fn transfer(func: &FunctionData, bb: BasicBlock, state: &mut HashSet<Value>) {
+    for &inst in func.layout().bbs().node(&bb).unwrap().insts().keys() {
+        step(func, inst, state);
+    }
+}
+
+/// 可能读到 `undef` 的变量名，每个变量只出现一次，按首次读取的顺序排列
+fn uninit_loads(func: &FunctionData, long_halves: &HashMap<Value, Value>) -> Vec<String> {
+    let bbs: Vec<BasicBlock> = func.layout().bbs().keys().copied().collect();
+    let preds = cfg::predecessors(func);
+    let mut outs: HashMap<BasicBlock, HashSet<Value>> = bbs.iter().map(|&bb| (bb, HashSet::new())).collect();
+
+    let entry_in = |outs: &HashMap<BasicBlock, HashSet<Value>>, bb: BasicBlock| {
+        let mut state = HashSet::new();
+        for pred in preds[&bb].iter() {
+            state.extend(outs[pred].iter().copied());
+        }
+        state
+    };
+
+    // 集合只增不减，迭代到不动点
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &bb in bbs.iter() {
+            let mut state = entry_in(&outs, bb);
+            transfer(func, bb, &mut state);
+            if state != outs[&bb] {
+                outs.insert(bb, state);
+                changed = true;
+            }
+        }
+    }
+
+    let mut reported = HashSet::new();
+    let mut vars = vec![];
+    for &bb in bbs.iter() {
+        let mut state = entry_in(&outs, bb);
+        for &inst in func.layout().bbs().node(&bb).unwrap().insts().keys() {
+            if let ValueKind::Load(l) = func.dfg().value(inst).kind() {
+                if state.contains(&l.src()) {
+                    let var = long_halves.get(&l.src()).copied().unwrap_or(l.src());
+                    if reported.insert(var) {
+                        vars.push(func.dfg().value(var).name().clone().unwrap_or_default());
+                    }
+                }
+            }
+            step(func, inst, &mut state);
+        }
+    }
+    vars
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 检查手写的 Koopa 文本，`halves` 按名字给出高位字与低位字的 `alloc`；返回报告的变量名
+    fn uninit_with(source: &str, halves: &[(&str, &str)]) -> Vec<String> {
+        let program = koopa::front::Driver::from(source).generate_program().unwrap();
+        let func = program.func(program.func_layout()[0]);
+        let named = |name: &str| {
+            let name = Some(name.to_string());
+            *func.dfg().values().iter().find(|(_, data)| *data.name() == name).unwrap().0
+        };
+        let long_halves = halves.iter().map(|&(hi, lo)| (named(hi), named(lo))).collect();
+        uninit_loads(func, &long_halves).into_iter().map(|v| v[1..].to_string()).collect()
+    }
+
+    fn uninit(source: &str) -> Vec<String> {
+        uninit_with(source, &[])
+    }
+
+    #[test]
+    fn branch() {
+        // 只在一个分支上赋值的 `x` 可能未初始化，两个分支都赋值的 `y` 不会
+        let source = r#"
+fun @f(@c: i32): i32 {
+%entry:
+  @x = alloc i32
+  store undef, @x
+  @y = alloc i32
+  store undef, @y
+  br @c, %then, %else
+%then:
+  store 1, @x
+  store 1, @y
+  jump %end
+%else:
+  store 2, @y
+  jump %end
+%end:
+  %0 = load @x
+  %1 = load @y
+  %2 = add %0, %1
+  ret %2
+}
+"#;
+        assert_eq!(uninit(source), ["x"]);
+    }
+
+    #[test]
+    fn loop_body() {
+        // 循环可能一次也不执行，循环后读取 `x` 可能未初始化；循环体内先赋值后读取的 `y` 不会
+        let source = r#"
+fun @f(@n: i32): i32 {
+%entry:
+  @x = alloc i32
+  store undef, @x
+  @y = alloc i32
+  store undef, @y
+  jump %cond
+%cond:
+  br @n, %body, %end
+%body:
+  store 1, @y
+  %0 = load @y
+  store %0, @x
+  jump %cond
+%end:
+  %1 = load @x
+  ret %1
+}
+"#;
+        assert_eq!(uninit(source), ["x"]);
+    }
+
+    #[test]
+    fn short_circuit() {
+        // `a && (x = 1)`：右操作数可能不求值，之后读取 `x` 可能未初始化
+        let source = r#"
+fun @f(@a: i32): i32 {
+%entry:
+  @x = alloc i32
+  store undef, @x
+  @t = alloc i32
+  store 0, @t
+  br @a, %rhs, %end
+%rhs:
+  store 1, @x
+  store 1, @t
+  jump %end
+%end:
+  %0 = load @t
+  %1 = load @x
+  %2 = add %0, %1
+  ret %2
+}
+"#;
+        assert_eq!(uninit(source), ["x"]);
+    }
+
+    #[test]
+    fn long_halves() {
+        // 按记录的对应关系归并两半：`@hi` 与 `@x` 只报告一次 `x`；`@x_hi` 虽然名字相似、紧跟 `@x`，
+        // 没有记录，单独报告
+        let source = r#"
+fun @f(): i32 {
+%entry:
+  @x = alloc i32
+  @x_hi = alloc i32
+  @hi = alloc i32
+  store undef, @x
+  store undef, @x_hi
+  store undef, @hi
+  %0 = load @hi
+  %1 = load @x
+  %2 = load @x_hi
+  %3 = add %0, %1
+  %4 = add %3, %2
+  ret %4
+}
+"#;
+        assert_eq!(uninit_with(source, &[("@hi", "@x")]), ["x", "x_hi"]);
+    }
+
+    #[test]
+    fn long_lowering() {
+        // 生成 Koopa IR 时记录 `long` 变量的两半，读取 `x` 时两半都可能未初始化，只报告一次
+        let ir = crate::front::into_ir("int main() { long x; int y; return x + y; }".to_string());
+        let diags = check(&ir.program, &ir.long_halves);
+        let messages: Vec<&str> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, ["'x' may be used uninitialized", "'y' may be used uninitialized"]);
+    }
+}
@@ -2,7 +2,10 @@ pub mod ast;
 #[macro_use]
 mod context;
 mod declare;
+pub mod fmt;
 mod gen;
+pub mod hir;
+pub mod lint;
 mod symtab;
 
 use lalrpop_util::lalrpop_mod;
@@ -13,16 +16,17 @@ lalrpop_mod! {
 
 use koopa::{
     back::KoopaGenerator,
-    ir::Program,
+    ir::{Program, Value},
 };
 use std::{
+    collections::HashMap,
     error::Error,
     io,
     ops::{Deref, DerefMut},
     result,
 };
 
-use self::{symtab::{FuncTab, ValTab}, gen::prelude::with_prelude};
+use self::{symtab::{FuncTab, ValTab}, gen::prelude::{with_prelude, strip_unused_decls}};
 use self::declare::Declare;
 
 
@@ -32,6 +36,10 @@ pub fn into_ast(source: String) -> Vec<ast::Item> {
     ast.unwrap()
 }
 
+pub fn into_hir(source: String) -> hir::Program {
+    hir::lower(&into_ast(source))
+}
+
 pub fn into_ir(source: String) -> Ir {
     let parser = parser::CompUnitParser::new();
     let ast = parser.parse(&source);
@@ -43,18 +51,28 @@ pub fn into_ir_text(ir: Ir) -> result::Result<String, Box<dyn Error>> {
     Ok(ir.try_into()?)
 }
 
-pub struct Ir(pub Program);
+pub struct Ir {
+    pub program: Program,
+    /// `long` 局部变量高位字的 `alloc` 到低位字的 `alloc`，生成 Koopa IR 时记录；优化变换不维护
+    pub long_halves: HashMap<Value, Value>,
+}
+
+impl From<Program> for Ir {
+    fn from(program: Program) -> Self {
+        Ir { program, long_halves: HashMap::new() }
+    }
+}
 
 impl Deref for Ir {
     type Target = Program;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.program
     }
 }
 
 impl DerefMut for Ir {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.program
     }
 }
 
@@ -62,14 +80,24 @@ impl TryFrom<Vec<ast::Item>> for Ir {
     type Error = Box<dyn Error>;
 
     fn try_from(value: Vec<ast::Item>) -> result::Result<Self, Self::Error> {
+        hir::lower(&value).try_into()
+    }
+}
+
+impl TryFrom<hir::Program> for Ir {
+    type Error = Box<dyn Error>;
+
+    fn try_from(hir: hir::Program) -> result::Result<Self, Self::Error> {
         let mut program = Program::new();
         let mut func_tab = FuncTab::new();
         let mut global_val_tab = ValTab::new();
+        let mut long_halves = HashMap::new();
         with_prelude(&mut program, &mut func_tab);
-        for item in value {
-            item.declare(&mut program, &mut func_tab, &mut global_val_tab);
+        for item in hir.items.iter() {
+            item.declare(&mut program, &hir, &mut func_tab, &mut global_val_tab, &mut long_halves);
         }
-        Ok(Ir(program))
+        strip_unused_decls(&mut program, &func_tab);
+        Ok(Ir { program, long_halves })
     }
 }
 
@@ -77,7 +105,7 @@ impl TryFrom<Ir> for String {
     type Error = io::Error;
     fn try_from(value: Ir) -> result::Result<Self, Self::Error> {
         let mut gen = KoopaGenerator::new(Vec::new());
-        gen.generate_on(&value.0)?;
+        gen.generate_on(&value.program)?;
         Ok(std::str::from_utf8(&gen.writer()).unwrap().to_string())
     }
 }
@@ -2,13 +2,32 @@ use std::collections::HashMap;
 
 use koopa::ir;
 
-pub type FuncTab = HashMap<String, ir::Function>;
-pub type ValTab = HashMap<String, ir::Value>;
+use super::{ast::Ty, hir::SymId};
+
+pub type FuncTab = HashMap<String, FuncSym>;
+pub type ValTab = HashMap<SymId, Sym>;
+
+/// HIR 中的变量在 Koopa 中的存储位置
+///
+/// `lo`、`hi` 为 `alloc`（全局变量为 `global_alloc`）；
+/// `long` 拆为低、高两个字，`hi` 仅对 `long` 存在。
+#[derive(Debug, Clone, Copy)]
+pub struct Sym {
+    pub ty: Ty,
+    pub lo: ir::Value,
+    pub hi: Option<ir::Value>,
+}
+
+/// 函数符号，源语言层面的签名见 HIR 中的 [`crate::front::hir::FuncSig`]
+#[derive(Debug, Clone)]
+pub struct FuncSym {
+    pub func: ir::Function,
+}
 
 pub struct Symtab<'a> {
     pub func: &'a FuncTab,
     pub global: &'a ValTab,
-    pub scope: Vec<ValTab>,
+    pub local: ValTab,
 }
 
 impl<'a> Symtab<'a> {
@@ -16,47 +35,19 @@ impl<'a> Symtab<'a> {
         Symtab {
             func,
             global,
-            scope: vec![HashMap::new()],
+            local: HashMap::new(),
         }
     }
 
-    pub fn push_scope(&mut self) {
-        self.scope.push(HashMap::new());
+    pub fn insert_val(&mut self, id: SymId, sym: Sym) {
+        self.local.insert(id, sym);
     }
 
-    pub fn pop_scope(&mut self) {
-        self.scope.pop();
+    pub fn get_val(&self, id: SymId) -> Option<Sym> {
+        self.local.get(&id).or_else(|| self.global.get(&id)).copied()
     }
 
-    pub fn insert_val(&mut self, name: &str, value: ir::Value) {
-        self.scope
-            .last_mut()
-            .unwrap()
-            .insert(name.to_string(), value);
+    pub fn get_func(&self, name: &str) -> Option<&FuncSym> {
+        self.func.get(name)
     }
-
-    pub fn get_val(&self, name: &str) -> Option<ir::Value> {
-        for scope in self.scope.iter().rev() {
-            /* match scope.get(name) {
-                Some(val) => return Some(*val),
-                None => (),
-            } */
-            if let Some(val) = scope.get(name) {
-                return Some(*val);
-            }
-        }
-        if let Some(val) = self.global.get(name) {
-            return Some(*val);
-        }
-        None
-    }
-
-    pub fn get_func(&self, name: &str) -> Option<ir::Function> {
-        self.func.get(name).cloned()
-    }
-}
-
-pub trait FetchVal<'a> {
-    fn fetch_val(&self, name: &str) -> Option<ir::Value>;
-    fn fetch_val_kind(&self, val: ir::Value) -> ir::entities::ValueKind;
 }
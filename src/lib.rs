@@ -1,6 +1,8 @@
 pub mod back;
 pub mod cli;
 pub mod front;
+pub mod lsp;
+pub mod opt;
 pub mod util;
 
 use koopa::ir;
@@ -0,0 +1,53 @@
+//! 协议中的行列号与源代码字节偏移的换算
+//!
+//! 名字解析由 HIR 的降级完成，标识符的位置及其解析结果见 [`crate::front::hir::Program::refs`]，
+//! 位置均为字节偏移；协议中的位置则是行号与行内的列号，列以字符计。
+
+#[derive(Debug)]
+pub struct LineIndex {
+    source: String,
+    /// 每一行行首的字节偏移
+    lines: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> LineIndex {
+        let lines = std::iter::once(0).chain(source.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        LineIndex {
+            source: source.to_string(),
+            lines,
+        }
+    }
+
+    /// 字节偏移所在的行列号
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.lines.partition_point(|&start| start <= offset) - 1;
+        let col = self.source[self.lines[line]..offset].chars().count();
+        (line, col)
+    }
+
+    /// 行列号对应的字节偏移，列超出行尾时取行尾
+    pub fn offset(&self, line: usize, col: usize) -> Option<usize> {
+        let start = *self.lines.get(line)?;
+        let end = self.lines.get(line + 1).map_or(self.source.len(), |&next| next - 1);
+        let text = &self.source[start..end];
+        Some(start + text.char_indices().nth(col).map_or(text.len(), |(i, _)| i))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let index = LineIndex::new("int a;\n\nint b;\n");
+        assert_eq!(index.position(0), (0, 0));
+        assert_eq!(index.position(7), (1, 0));
+        assert_eq!(index.position(12), (2, 4));
+        assert_eq!(index.offset(2, 4), Some(12));
+        assert_eq!(index.offset(0, 100), Some(6));
+        assert_eq!(index.offset(4, 0), None);
+    }
+}
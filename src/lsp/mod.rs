@@ -0,0 +1,615 @@
+//! 基于标准输入输出的语言服务器（Language Server Protocol）
+//!
+//! 支持的功能：
+//!
+//! - 文档打开和修改时发布诊断：语法错误、语义错误（降级或生成 IR 时的 `SemanticsError`）与默认开启的警告；
+//! - 跳转到定义、查找引用：名字解析取自 HIR 的降级（见 [`hir::Program::refs`]），文档有语法或语义错误时不可用；
+//! - 悬停显示声明的类型，常量还显示其编译期的值；
+//! - 列出文档中的函数与全局变量、常量。
+//!
+//! 只支持全量同步。位置中的列以字符计，源代码只含 ASCII 字符时与协议要求的 UTF-16 码元一致。
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{self, BufRead, Write},
+    panic::{self, AssertUnwindSafe},
+};
+
+use lalrpop_util::ParseError;
+
+use crate::front::{
+    ast::{self, visit::*, Ident, Span, SymKind},
+    hir::{self, ConstVal, Ref},
+    lint::{self, LintConfig},
+    parser::CompUnitParser,
+    Ir,
+};
+
+use self::{index::LineIndex, json::Json};
+
+pub mod index;
+pub mod json;
+
+/// 打开的文档及其分析结果
+struct Document {
+    lines: LineIndex,
+    /// 没有语法和语义错误时降级得到的 HIR，跳转、悬停等都通过它完成
+    hir: Option<hir::Program>,
+    diagnostics: Vec<Json>,
+}
+
+/// 诊断的严重程度，取值同协议
+#[derive(Clone, Copy)]
+enum Severity {
+    Error = 1,
+    Warning = 2,
+}
+
+fn position(line: usize, col: usize) -> Json {
+    Json::object([("line", line.into()), ("character", col.into())])
+}
+
+fn range(start: (usize, usize), end: (usize, usize)) -> Json {
+    Json::object([("start", position(start.0, start.1)), ("end", position(end.0, end.1))])
+}
+
+fn span_range(lines: &LineIndex, span: Span) -> Json {
+    range(lines.position(span.lo), lines.position(span.hi))
+}
+
+fn diagnostic(range: Json, severity: Severity, code: &str, message: String) -> Json {
+    Json::object([
+        ("range", range),
+        ("severity", (severity as usize).into()),
+        ("code", code.into()),
+        ("source", "sysy".into()),
+        ("message", message.into()),
+    ])
+}
+
+fn parse_error<T: Display, E: Display>(lines: &LineIndex, e: ParseError<usize, T, E>) -> Json {
+    let (start, end) = match &e {
+        ParseError::InvalidToken { location } | ParseError::UnrecognizedEOF { location, .. } => {
+            (*location, *location)
+        }
+        ParseError::UnrecognizedToken { token: (l, _, r), .. } | ParseError::ExtraToken { token: (l, _, r) } => (*l, *r),
+        ParseError::User { .. } => (0, 0),
+    };
+    diagnostic(span_range(lines, Span { lo: start, hi: end }), Severity::Error, "syntax", e.to_string())
+}
+
+/// 提取消息中第一个被单引号括起的名字
+fn quoted(message: &str) -> Option<&str> {
+    let mut parts = message.split('\'');
+    parts.next()?;
+    parts.next()
+}
+
+/// AST 中的标识符，分为引用和定义处的名字，各自按出现的顺序排列
+#[derive(Default)]
+struct Idents<'ast> {
+    uses: Vec<&'ast Ident>,
+    defs: Vec<&'ast Ident>,
+}
+
+impl<'ast> Visit<'ast> for Idents<'ast> {
+    fn visit_func(&mut self, f: &'ast ast::Func) {
+        self.defs.push(&f.ident);
+        walk_func(self, f)
+    }
+    fn visit_param(&mut self, p: &'ast ast::Param) {
+        self.defs.push(&p.ident);
+    }
+    fn visit_decl(&mut self, d: &'ast ast::Decl) {
+        walk_decl(self, d);
+        self.defs.push(&d.ident);
+    }
+    fn visit_lval(&mut self, l: &'ast ast::LVal) {
+        self.uses.push(&l.0);
+    }
+    fn visit_unary_exp(&mut self, e: &'ast ast::UnaryExp) {
+        if let ast::UnaryExp::Call(ident, _) = e {
+            self.uses.push(ident);
+        }
+        walk_unary_exp(self, e)
+    }
+}
+
+/// `SemanticsError[Kind]: message` 形式的 panic
+///
+/// 降级没有完成，没有 HIR 可用，位置取消息中提到的名字在 AST 中第一次出现之处，引用优先于定义。
+fn semantic_error(lines: &LineIndex, ast: &[ast::Item], payload: Box<dyn std::any::Any + Send>) -> Json {
+    let message = payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "internal compiler error".to_string());
+    let (code, message) = match message.strip_prefix("SemanticsError[").and_then(|m| m.split_once("]: ")) {
+        Some((code, message)) => (code.to_string(), message.to_string()),
+        None => ("internal".to_string(), message),
+    };
+    let mut idents = Idents::default();
+    ast.iter().for_each(|item| idents.visit_item(item));
+    let ident = quoted(&message).and_then(|name| {
+        let named = |i: &&&Ident| i.name == name;
+        idents.uses.iter().find(named).or_else(|| idents.defs.iter().find(named))
+    });
+    let range = match ident {
+        Some(ident) => span_range(lines, ident.span),
+        None => range((0, 0), (0, 0)),
+    };
+    diagnostic(range, Severity::Error, &code, message)
+}
+
+/// 警告的位置：函数中提到的名字的定义，没有时为函数名
+fn lint_warning(lines: &LineIndex, hir: &hir::Program, d: &lint::Diagnostic) -> Json {
+    let func = d.func.as_ref().and_then(|name| {
+        hir.items.iter().find_map(|item| match item {
+            hir::Item::Func(f) if hir.func(f.id).ident == *name => Some(f),
+            _ => None,
+        })
+    });
+    let named = quoted(&d.message).and_then(|name| {
+        let local = |s: &&hir::Symbol| match func {
+            Some(f) => f.span.contains(s.span.lo),
+            None => s.global,
+        };
+        let sym = hir.symbols.iter().filter(local).find(|s| s.ident == name).map(|s| s.span);
+        sym.or_else(|| hir.funcs.iter().find(|sig| sig.ident == name).and_then(|sig| sig.span))
+    });
+    let range = match named.or_else(|| func.and_then(|f| hir.func(f.id).span)) {
+        Some(span) => span_range(lines, span),
+        None => range((0, 0), (0, 0)),
+    };
+    let severity = if d.error { Severity::Error } else { Severity::Warning };
+    diagnostic(range, severity, d.lint, d.message.clone())
+}
+
+impl Document {
+    fn new(source: &str) -> Document {
+        let lines = LineIndex::new(source);
+        let ast = match CompUnitParser::new().parse(source) {
+            Ok(ast) => ast,
+            Err(e) => {
+                let diagnostics = vec![parse_error(&lines, e)];
+                return Document { lines, hir: None, diagnostics };
+            }
+        };
+        let hir = match panic::catch_unwind(AssertUnwindSafe(|| hir::lower(&ast))) {
+            Ok(hir) => hir,
+            Err(payload) => {
+                let diagnostics = vec![semantic_error(&lines, &ast, payload)];
+                return Document { lines, hir: None, diagnostics };
+            }
+        };
+
+        let config = LintConfig::default();
+        let warning = |d: &lint::Diagnostic| lint_warning(&lines, &hir, d);
+        let mut diagnostics: Vec<Json> = lint::check(&hir, &config).iter().map(warning).collect();
+        // 生成 IR 会消耗 HIR，另行降级一次
+        match panic::catch_unwind(AssertUnwindSafe(|| Ir::try_from(hir::lower(&ast)))) {
+            Ok(Ok(ir)) => diagnostics.extend(lint::check_ir(&ir, &config).iter().map(warning)),
+            Ok(Err(e)) => diagnostics.push(diagnostic(range((0, 0), (0, 0)), Severity::Error, "internal", e.to_string())),
+            Err(payload) => diagnostics.push(semantic_error(&lines, &ast, payload)),
+        }
+        Document { lines, hir: Some(hir), diagnostics }
+    }
+
+    fn location(&self, uri: &str, span: Span) -> Json {
+        Json::object([("uri", uri.into()), ("range", span_range(&self.lines, span))])
+    }
+
+    /// 声明形式，如 `const int N = 10`、`int fib(int n)`；库函数只有参数的类型，如 `void putint(int)`
+    fn signature(&self, hir: &hir::Program, r: Ref) -> String {
+        let id = match r {
+            Ref::Sym(id) => id,
+            Ref::Func(id) => {
+                let sig = hir.func(id);
+                let def = hir.items.iter().find_map(|item| match item {
+                    hir::Item::Func(f) if f.id == id => Some(f),
+                    _ => None,
+                });
+                let params: Vec<String> = match def {
+                    Some(f) => f
+                        .params
+                        .iter()
+                        .map(|&p| format!("{} {}", hir.symbol(p).ty.name(), hir.symbol(p).ident))
+                        .collect(),
+                    None => sig.params.iter().map(|ty| ty.name().to_string()).collect(),
+                };
+                return format!("{} {}({})", sig.output.name(), sig.ident, params.join(", "));
+            }
+        };
+        let symbol = hir.symbol(id);
+        let (konst, value) = match (symbol.kind, symbol.value) {
+            (SymKind::Const, Some(value)) => {
+                let value = match value {
+                    ConstVal::Int(i) => i.to_string(),
+                    ConstVal::UInt(u) => u.to_string(),
+                    ConstVal::Long(l) => l.to_string(),
+                    ConstVal::Float(f) => format!("{:?}", f),
+                };
+                ("const ", format!(" = {}", value))
+            }
+            _ => ("", String::new()),
+        };
+        format!("{}{} {}{}", konst, symbol.ty.name(), symbol.ident, value)
+    }
+
+    fn hover(&self, offset: usize) -> Option<Json> {
+        let hir = self.hir.as_ref()?;
+        let (span, r) = hir.ref_at(offset)?;
+        let text = self.signature(hir, r);
+        let contents = Json::object([("kind", "markdown".into()), ("value", format!("```c\n{}\n```", text).into())]);
+        Some(Json::object([("contents", contents), ("range", span_range(&self.lines, span))]))
+    }
+
+    /// 函数与全局变量、常量，按定义的顺序排列
+    fn symbols(&self) -> Json {
+        let Some(hir) = &self.hir else {
+            return Json::Array(vec![]);
+        };
+        let symbols = hir
+            .items
+            .iter()
+            .map(|item| {
+                // 协议中的 SymbolKind：Function = 12、Variable = 13、Constant = 14
+                let (name, r, kind, span): (&str, _, usize, _) = match item {
+                    hir::Item::Func(f) => (&hir.func(f.id).ident, Ref::Func(f.id), 12, f.span),
+                    hir::Item::Global(g) => {
+                        let symbol = hir.symbol(g.sym);
+                        let kind = if symbol.kind == SymKind::Const { 14 } else { 13 };
+                        (&symbol.ident, Ref::Sym(g.sym), kind, symbol.span)
+                    }
+                };
+                let selection = hir.def_span(r).unwrap_or(span);
+                Json::object([
+                    ("name", name.into()),
+                    ("detail", self.signature(hir, r).into()),
+                    ("kind", kind.into()),
+                    ("range", span_range(&self.lines, span)),
+                    ("selectionRange", span_range(&self.lines, selection)),
+                ])
+            })
+            .collect();
+        Json::Array(symbols)
+    }
+}
+
+/// 协议中的错误码
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Default)]
+pub struct Server {
+    docs: HashMap<String, Document>,
+    shutdown: bool,
+    exit: Option<i32>,
+}
+
+fn response(id: &Json, result: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("id", id.clone()), ("result", result)])
+}
+
+fn error(id: &Json, code: i64, message: &str) -> Json {
+    let error = Json::object([("code", code.into()), ("message", message.into())]);
+    Json::object([("jsonrpc", "2.0".into()), ("id", id.clone()), ("error", error)])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("method", method.into()), ("params", params)])
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::default()
+    }
+
+    /// 收到 `exit` 后的退出码：此前收到过 `shutdown` 为 0，否则为 1
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit
+    }
+
+    /// 处理一条消息，返回要发给客户端的消息
+    pub fn handle(&mut self, msg: &Json) -> Vec<Json> {
+        let Some(method) = msg["method"].as_str() else {
+            // 客户端对服务器请求的响应，服务器不发请求，直接忽略
+            return vec![];
+        };
+        let params = &msg["params"];
+        let id = &msg["id"];
+        if id.is_null() {
+            return self.notify(method, params);
+        }
+        if self.shutdown {
+            return vec![error(id, INVALID_REQUEST, "server is shut down")];
+        }
+        let result = match method {
+            "initialize" => Some(Json::object([
+                (
+                    "capabilities",
+                    Json::object([
+                        ("textDocumentSync", 1usize.into()),
+                        ("definitionProvider", true.into()),
+                        ("referencesProvider", true.into()),
+                        ("hoverProvider", true.into()),
+                        ("documentSymbolProvider", true.into()),
+                    ]),
+                ),
+                (
+                    "serverInfo",
+                    Json::object([("name", "sysy-lsp".into()), ("version", env!("CARGO_PKG_VERSION").into())]),
+                ),
+            ])),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Json::Null)
+            }
+            "textDocument/definition" => self.at(params).map(|(doc, uri, offset)| {
+                let def = doc.hir.as_ref().and_then(|hir| hir.def_span(hir.ref_at(offset)?.1));
+                match def {
+                    Some(span) => doc.location(uri, span),
+                    None => Json::Null,
+                }
+            }),
+            "textDocument/references" => self.at(params).map(|(doc, uri, offset)| {
+                let Some((hir, (_, r))) = doc.hir.as_ref().and_then(|hir| Some((hir, hir.ref_at(offset)?))) else {
+                    return Json::Array(vec![]);
+                };
+                let declaration = params["context"]["includeDeclaration"].as_bool().unwrap_or(true);
+                let def = hir.def_span(r);
+                hir.references(r)
+                    .into_iter()
+                    .filter(|&span| declaration || Some(span) != def)
+                    .map(|span| doc.location(uri, span))
+                    .collect::<Vec<_>>()
+                    .into()
+            }),
+            "textDocument/hover" => self.at(params).map(|(doc, _, offset)| doc.hover(offset).unwrap_or(Json::Null)),
+            "textDocument/documentSymbol" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.docs.get(uri).map(Document::symbols)
+            }
+            _ => return vec![error(id, METHOD_NOT_FOUND, &format!("unknown method '{}'", method))],
+        };
+        match result {
+            Some(result) => vec![response(id, result)],
+            None => vec![error(id, INVALID_PARAMS, "unknown document or position")],
+        }
+    }
+
+    /// 请求参数中的文档和位置对应的字节偏移；位置上没有标识符时结果为空而不是错误
+    fn at<'a>(&'a self, params: &'a Json) -> Option<(&'a Document, &'a str, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let doc = self.docs.get(uri)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let col = params["position"]["character"].as_u64()? as usize;
+        Some((doc, uri, doc.lines.offset(line, col)?))
+    }
+
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        let text = match method {
+            "exit" => {
+                self.exit = Some(if self.shutdown { 0 } else { 1 });
+                return vec![];
+            }
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // 全量同步，最后一项即为完整的文本
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.docs.remove(&uri);
+                return vec![self.publish(&uri, vec![])];
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return vec![];
+        };
+        let doc = Document::new(text);
+        let diagnostics = doc.diagnostics.clone();
+        self.docs.insert(uri.clone(), doc);
+        vec![self.publish(&uri, diagnostics)]
+    }
+
+    fn publish(&self, uri: &str, diagnostics: Vec<Json>) -> Json {
+        let params = Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]);
+        notification("textDocument/publishDiagnostics", params)
+    }
+}
+
+/// 读入一条消息的内容，输入结束时为 `None`
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    String::from_utf8(content).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut impl Write, msg: &Json) -> io::Result<()> {
+    let content = msg.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    output.flush()
+}
+
+/// 在给定的输入输出上运行服务器，返回退出码
+pub fn run(input: &mut impl BufRead, output: &mut impl Write) -> io::Result<i32> {
+    let mut server = Server::new();
+    while let Some(content) = read_message(input)? {
+        let replies = match Json::parse(&content) {
+            Ok(msg) => server.handle(&msg),
+            Err(e) => vec![error(&Json::Null, PARSE_ERROR, &e)],
+        };
+        for reply in replies.iter() {
+            write_message(output, reply)?;
+        }
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+    // 输入意外结束，视同未经 `shutdown` 的 `exit`
+    Ok(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const URI: &str = "file:///test.c";
+
+    const SOURCE: &str = r#"const int N = 2 + 3;
+int g;
+
+int fib(int n) {
+    if (n < 2) return n;
+    return fib(n - 1) + fib(n - 2);
+}
+
+int main() {
+    int x = N;
+    {
+        int x = 1;
+        g = x;
+    }
+    putint(fib(x));
+    return g;
+}
+"#;
+
+    fn request(server: &mut Server, id: usize, method: &str, params: Json) -> Json {
+        let msg = Json::object([("jsonrpc", "2.0".into()), ("id", id.into()), ("method", method.into()), ("params", params)]);
+        let mut replies = server.handle(&msg);
+        assert_eq!(replies.len(), 1);
+        let reply = replies.pop().unwrap();
+        assert_eq!(reply["id"].as_u64(), Some(id as u64));
+        reply
+    }
+
+    fn open(server: &mut Server, text: &str) -> Json {
+        let doc = Json::object([("uri", URI.into()), ("languageId", "c".into()), ("version", 1usize.into()), ("text", text.into())]);
+        let mut replies = server.handle(&notification("textDocument/didOpen", Json::object([("textDocument", doc)])));
+        assert_eq!(replies.len(), 1);
+        let reply = replies.pop().unwrap();
+        assert_eq!(reply["method"].as_str(), Some("textDocument/publishDiagnostics"));
+        reply["params"]["diagnostics"].clone()
+    }
+
+    fn at(line: usize, col: usize) -> Json {
+        Json::object([("textDocument", Json::object([("uri", URI.into())])), ("position", position(line, col))])
+    }
+
+    fn start(range: &Json) -> (u64, u64) {
+        (range["start"]["line"].as_u64().unwrap(), range["start"]["character"].as_u64().unwrap())
+    }
+
+    #[test]
+    fn navigation() {
+        let mut server = Server::new();
+        let init = request(&mut server, 1, "initialize", Json::object([]));
+        assert_eq!(init["result"]["capabilities"]["hoverProvider"], Json::Bool(true));
+        assert_eq!(open(&mut server, SOURCE), Json::Array(vec![]));
+
+        // 内层块中的 `x` 指向内层的定义
+        let def = request(&mut server, 2, "textDocument/definition", at(12, 12));
+        assert_eq!(start(&def["result"]["range"]), (11, 12));
+        let def = request(&mut server, 3, "textDocument/definition", at(14, 16));
+        assert_eq!(start(&def["result"]["range"]), (9, 8));
+
+        let mut params = at(3, 5);
+        if let Json::Object(fields) = &mut params {
+            fields.push(("context".into(), Json::object([("includeDeclaration", false.into())])));
+        }
+        let refs = request(&mut server, 4, "textDocument/references", params);
+        let refs: Vec<_> = refs["result"].as_array().unwrap().iter().map(|l| start(&l["range"])).collect();
+        assert_eq!(refs, vec![(5, 11), (5, 24), (14, 11)]);
+
+        let hover = request(&mut server, 5, "textDocument/hover", at(9, 12));
+        assert_eq!(hover["result"]["contents"]["value"].as_str(), Some("```c\nconst int N = 5\n```"));
+        let hover = request(&mut server, 6, "textDocument/hover", at(14, 5));
+        assert_eq!(hover["result"]["contents"]["value"].as_str(), Some("```c\nvoid putint(int)\n```"));
+        let hover = request(&mut server, 7, "textDocument/hover", at(4, 2));
+        assert!(hover["result"].is_null());
+
+        let symbols = request(&mut server, 8, "textDocument/documentSymbol", Json::object([("textDocument", Json::object([("uri", URI.into())]))]));
+        let symbols: Vec<_> = symbols["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| (s["name"].as_str().unwrap().to_string(), s["kind"].as_u64().unwrap(), s["detail"].as_str().unwrap().to_string()))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                ("N".to_string(), 14, "const int N = 5".to_string()),
+                ("g".to_string(), 13, "int g".to_string()),
+                ("fib".to_string(), 12, "int fib(int n)".to_string()),
+                ("main".to_string(), 12, "int main()".to_string()),
+            ]
+        );
+
+        assert!(request(&mut server, 9, "shutdown", Json::Null)["result"].is_null());
+        assert!(server.handle(&notification("exit", Json::Null)).is_empty());
+        assert_eq!(server.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut server = Server::new();
+        let diags = open(&mut server, "int main() {\n    return 0\n}\n");
+        assert_eq!(diags[0]["code"].as_str(), Some("syntax"));
+        assert_eq!(start(&diags[0]["range"]), (2, 0));
+
+        let diags = open(&mut server, "int main() {\n    return y;\n}\n");
+        assert_eq!(diags[0]["code"].as_str(), Some("UndefinedSymbol"));
+        assert_eq!(start(&diags[0]["range"]), (1, 11));
+
+        let diags = open(&mut server, "int main() {\n    int a;\n    int b;\n    return b;\n}\n");
+        let diags: Vec<_> = diags.as_array().unwrap().iter().map(|d| (d["code"].as_str().unwrap(), start(&d["range"]))).collect();
+        assert_eq!(diags, vec![("unused-variable", (1, 8)), ("uninitialized", (2, 8))]);
+    }
+
+    #[test]
+    fn transport() {
+        let messages = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/unknown","params":{}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","method":"exit"}"#,
+        ];
+        let input: String = messages.iter().map(|m| format!("Content-Length: {}\r\n\r\n{}", m.len(), m)).collect();
+        let mut output = vec![];
+        assert_eq!(run(&mut input.as_bytes(), &mut output).unwrap(), 0);
+
+        let mut output = output.as_slice();
+        let mut replies = vec![];
+        while let Some(content) = read_message(&mut output).unwrap() {
+            replies.push(Json::parse(&content).unwrap());
+        }
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0]["result"]["serverInfo"]["name"].as_str(), Some("sysy-lsp"));
+        assert_eq!(replies[1]["error"]["code"], Json::from(METHOD_NOT_FOUND));
+        assert!(replies[2]["result"].is_null() && replies[2]["error"].is_null());
+    }
+}
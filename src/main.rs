@@ -1,12 +1,41 @@
 use std::fs;
 
-use compiler::{back, cli, front};
+use compiler::{back, cli, front, opt::PassManager, util::verify};
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let config = cli::Config::new();
     let source = String::from_utf8(fs::read(&config.input)?)?;
-    let ir = front::into_ir(source);
+    if let cli::CompilerMode::Ast(format) = config.mode {
+        let ast = front::ast::dump::dump(&front::into_ast(source), format);
+        if config.output.is_empty() {
+            print!("{}", ast);
+        } else {
+            fs::write(&config.output, ast)?;
+        }
+        return Ok(());
+    }
+    let hir = front::into_hir(source);
+    let mut diags = front::lint::check(&hir, &config.lints);
+    let mut ir: front::Ir = hir.try_into()?;
+    diags.extend(front::lint::check_ir(&ir, &config.lints));
+    front::lint::emit(&diags, &config.lints)?;
+    if config.verify || cfg!(debug_assertions) {
+        let issues = verify::verify(&ir);
+        for issue in issues.iter().filter(|i| i.error || config.verify) {
+            eprintln!("{}", issue);
+        }
+        let errors = issues.iter().filter(|i| i.error).count();
+        if errors > 0 {
+            return Err(format!("generated Koopa IR is malformed: {} error(s)", errors).into());
+        }
+    }
+    let mut pm = match &config.passes {
+        Some(names) => PassManager::with_names(names)?,
+        None => PassManager::with_level(config.opt_level),
+    };
+    pm.verify = config.verify || cfg!(debug_assertions);
+    pm.run(&mut ir)?;
     match &config.mode {
         cli::CompilerMode::Koopa => {
             let koopa = front::into_ir_text(ir)?;
@@ -14,10 +43,11 @@ fn main() -> Result<(), Box<dyn Error>> {
             Ok(())
         }
         cli::CompilerMode::Riscv => {
-            let riscv = back::into_riscv(ir)?;
+            let riscv = back::into_riscv_with(ir, config.target)?;
             fs::write(&config.output, riscv)?;
             Ok(())
         }
+        cli::CompilerMode::Ast(_) => unreachable!(),
     }
 }
 
@@ -34,7 +64,7 @@ mod test {
     fn ast() {
         let source = read_test_file();
         let ast = front::into_ast(source);
-        dbg!(&ast);
+        print!("{}", front::ast::dump::dump(&ast, front::ast::dump::Format::Sexp));
     }
 
     #[test]
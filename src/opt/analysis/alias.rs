@@ -0,0 +1,149 @@
+//! 地址的别名分析
+//!
+//! 地址的来源为局部的 `alloc`、全局变量，或未知（参数传入的指针等）。来源不同的局部变量与全局变量不会重叠；
+//! 未知的指针不会指向地址没有传出过的局部变量；来源相同时，某一层下标为不同的常量、
+//! 此后只有 `getelemptr` 的两个地址不会重叠。
+//!
+//! 结果依赖于函数中的指令，不随控制流图缓存，由变换在需要时计算。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{FunctionData, Value, ValueKind};
+
+use crate::opt::edit;
+
+/// 地址的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    Local(Value),
+    Global(Value),
+    Unknown,
+}
+
+/// 从来源计算地址的一步中的下标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Index {
+    Const(i32),
+    Value(Value),
+}
+
+/// 从来源计算地址的一步：是否为 `getptr`，以及下标
+pub type Step = (bool, Index);
+
+pub struct AliasInfo {
+    /// 地址传出过（作为 `store` 的值、`call` 或跳转的实参、返回值）的局部变量
+    escaped: HashSet<Value>,
+}
+
+impl AliasInfo {
+    pub fn new(func: &FunctionData) -> AliasInfo {
+        AliasInfo { escaped: escaped(func) }
+    }
+
+    /// 地址的来源，以及从来源计算地址的各步
+    pub fn base(&self, func: &FunctionData, mut addr: Value) -> (Base, Vec<Step>) {
+        let index = |v: Value| match func.dfg().value(v).kind() {
+            ValueKind::Integer(i) => Index::Const(i.value()),
+            _ => Index::Value(v),
+        };
+        let mut path = vec![];
+        let base = loop {
+            if addr.is_global() {
+                break Base::Global(addr);
+            }
+            match func.dfg().value(addr).kind() {
+                ValueKind::Alloc(_) => break Base::Local(addr),
+                ValueKind::GetElemPtr(g) => {
+                    path.push((false, index(g.index())));
+                    addr = g.src();
+                }
+                ValueKind::GetPtr(g) => {
+                    path.push((true, index(g.index())));
+                    addr = g.src();
+                }
+                _ => break Base::Unknown,
+            }
+        };
+        path.reverse();
+        (base, path)
+    }
+
+    /// 两个地址指向的内存是否可能重叠
+    pub fn may_alias(&self, func: &FunctionData, a: Value, b: Value) -> bool {
+        if a == b {
+            return true;
+        }
+        let ((base_a, path_a), (base_b, path_b)) = (self.base(func, a), self.base(func, b));
+        match (base_a, base_b) {
+            (Base::Unknown, Base::Unknown) => true,
+            (Base::Unknown, Base::Local(v)) | (Base::Local(v), Base::Unknown) => self.escaped.contains(&v),
+            (Base::Unknown, Base::Global(_)) | (Base::Global(_), Base::Unknown) => true,
+            _ if base_a != base_b => false,
+            // 来源相同：第一处不同的一步若是同类的、下标为不同的常量，且此后各步都是 `getelemptr`，
+            // 两个地址落在不同的元素中，不会重叠；此后的 `getptr` 可能越过元素的边界
+            _ => {
+                let Some(at) = path_a.iter().zip(path_b.iter()).position(|(x, y)| x != y) else {
+                    return true;
+                };
+                let within = |path: &[Step]| path[at + 1..].iter().all(|&(ptr, _)| !ptr);
+                match (path_a[at], path_b[at]) {
+                    ((ptr_a, Index::Const(_)), (ptr_b, Index::Const(_))) if ptr_a == ptr_b => {
+                        !(within(&path_a) && within(&path_b))
+                    }
+                    _ => true,
+                }
+            }
+        }
+    }
+
+    /// 调用是否可能读写该地址：被调用者只能访问全局变量、参数传入的指针与传出过的局部变量
+    pub fn call_may_access(&self, func: &FunctionData, addr: Value) -> bool {
+        match self.base(func, addr).0 {
+            Base::Local(v) => self.escaped.contains(&v),
+            _ => true,
+        }
+    }
+}
+
+/// 地址传出过的局部变量
+fn escaped(func: &FunctionData) -> HashSet<Value> {
+    let insts = edit::insts(func);
+    // 每个由局部变量计算出的地址的来源
+    let mut locals: HashMap<Value, Value> = HashMap::new();
+    let mut escaped = HashSet::new();
+    for &inst in insts.iter() {
+        let kind = func.dfg().value(inst).kind();
+        match kind {
+            ValueKind::Alloc(_) => {
+                locals.insert(inst, inst);
+                continue;
+            }
+            ValueKind::GetElemPtr(g) => {
+                if let Some(&base) = locals.get(&g.src()) {
+                    locals.insert(inst, base);
+                }
+                continue;
+            }
+            ValueKind::GetPtr(g) => {
+                if let Some(&base) = locals.get(&g.src()) {
+                    locals.insert(inst, base);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        for operand in kind.value_uses() {
+            let used_as_address = match kind {
+                ValueKind::Load(_) => true,
+                ValueKind::Store(s) => s.dest() == operand && s.value() != operand,
+                _ => false,
+            };
+            if !used_as_address {
+                if let Some(&base) = locals.get(&operand) {
+                    escaped.insert(base);
+                }
+            }
+        }
+    }
+    escaped
+}
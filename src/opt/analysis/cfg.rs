@@ -0,0 +1,70 @@
+//! 控制流图
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use koopa::ir::{BasicBlock, FunctionData};
+
+use crate::util::cfg;
+
+pub struct Cfg {
+    entry: BasicBlock,
+    succs: HashMap<BasicBlock, Vec<BasicBlock>>,
+    preds: HashMap<BasicBlock, Vec<BasicBlock>>,
+    /// 可达基本块的逆后序，第一个为入口
+    rpo: Vec<BasicBlock>,
+}
+
+impl Cfg {
+    pub fn new(func: &FunctionData) -> Cfg {
+        let entry = func.layout().entry_bb().expect("function has no body");
+        let succs: HashMap<BasicBlock, Vec<BasicBlock>> =
+            func.layout().bbs().keys().map(|&bb| (bb, cfg::successors(func, bb))).collect();
+        let rpo = reverse_postorder(entry, |bb| succs[&bb].clone());
+        Cfg { entry, succs, preds: cfg::predecessors(func), rpo }
+    }
+
+    pub fn entry(&self) -> BasicBlock {
+        self.entry
+    }
+
+    /// 后继，按终结指令中出现的顺序排列
+    pub fn succs(&self, bb: BasicBlock) -> &[BasicBlock] {
+        &self.succs[&bb]
+    }
+
+    /// 前驱，按布局顺序排列；包括不可达的前驱
+    pub fn preds(&self, bb: BasicBlock) -> &[BasicBlock] {
+        &self.preds[&bb]
+    }
+
+    /// 可达基本块的逆后序，第一个为入口
+    pub fn rpo(&self) -> &[BasicBlock] {
+        &self.rpo
+    }
+
+    /// 可达的、没有后继的基本块，即以 `ret` 结尾的基本块
+    pub fn exits(&self) -> Vec<BasicBlock> {
+        self.rpo.iter().copied().filter(|bb| self.succs[bb].is_empty()).collect()
+    }
+}
+
+/// 从 `root` 出发可达的结点的逆后序
+pub(super) fn reverse_postorder<N: Copy + Eq + Hash>(root: N, succs: impl Fn(N) -> Vec<N>) -> Vec<N> {
+    let mut visited = HashSet::from([root]);
+    let mut post = vec![];
+    let mut stack = vec![(root, succs(root), 0)];
+    while let Some((node, next_succs, next)) = stack.last_mut() {
+        if let Some(&succ) = next_succs.get(*next) {
+            *next += 1;
+            if visited.insert(succ) {
+                stack.push((succ, succs(succ), 0));
+            }
+        } else {
+            post.push(*node);
+            stack.pop();
+        }
+    }
+    post.reverse();
+    post
+}
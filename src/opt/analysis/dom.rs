@@ -0,0 +1,245 @@
+//! 支配树、支配边界与后支配树
+//!
+//! 支配树按 Cooper、Harvey 与 Kennedy 的迭代算法求得（“A Simple, Fast Dominance Algorithm”），
+//! 只包含从入口可达的基本块。后支配树是反向的控制流图上的支配树，以一个虚拟的出口为根，
+//! 所有以 `ret` 结尾的基本块都跳转到它；不能到达出口的基本块（如无限循环中的）不在其中。
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use koopa::ir::BasicBlock;
+
+use super::cfg::{reverse_postorder, Cfg};
+
+/// 按逆后序排列的结点上的支配关系，第一个结点为根
+struct Tree<N> {
+    order: Vec<N>,
+    index: HashMap<N, usize>,
+    /// 以逆后序编号表示的直接支配者，根的直接支配者为其自身
+    idom: Vec<usize>,
+    children: Vec<Vec<N>>,
+}
+
+impl<N: Copy + Eq + Hash> Tree<N> {
+    fn new(order: Vec<N>, preds: impl Fn(N) -> Vec<N>) -> Tree<N> {
+        let index: HashMap<N, usize> = order.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+        let preds: Vec<Vec<usize>> =
+            order.iter().map(|&n| preds(n).iter().filter_map(|p| index.get(p).copied()).collect()).collect();
+
+        const UNDEF: usize = usize::MAX;
+        let mut idom = vec![UNDEF; order.len()];
+        idom[0] = 0;
+        let intersect = |idom: &[usize], mut a: usize, mut b: usize| {
+            while a != b {
+                while a > b {
+                    a = idom[a];
+                }
+                while b > a {
+                    b = idom[b];
+                }
+            }
+            a
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for b in 1..order.len() {
+                let mut new = UNDEF;
+                for &p in preds[b].iter().filter(|&&p| idom[p] != UNDEF) {
+                    new = if new == UNDEF { p } else { intersect(&idom, p, new) };
+                }
+                if idom[b] != new {
+                    idom[b] = new;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut children = vec![vec![]; order.len()];
+        for b in 1..order.len() {
+            children[idom[b]].push(order[b]);
+        }
+        Tree { order, index, idom, children }
+    }
+
+    fn idom(&self, n: N) -> Option<N> {
+        match self.index.get(&n) {
+            Some(0) | None => None,
+            Some(&i) => Some(self.order[self.idom[i]]),
+        }
+    }
+
+    fn children(&self, n: N) -> &[N] {
+        self.index.get(&n).map_or(&[], |&i| &self.children[i])
+    }
+
+    fn dominates(&self, a: N, b: N) -> bool {
+        let (Some(&a), Some(&(mut b))) = (self.index.get(&a), self.index.get(&b)) else {
+            return false;
+        };
+        // 直接支配者的逆后序编号总是更小
+        while b > a {
+            b = self.idom[b];
+        }
+        a == b
+    }
+}
+
+pub struct DomTree {
+    tree: Tree<BasicBlock>,
+}
+
+impl DomTree {
+    pub fn new(cfg: &Cfg) -> DomTree {
+        DomTree { tree: Tree::new(cfg.rpo().to_vec(), |bb| cfg.preds(bb).to_vec()) }
+    }
+
+    pub fn entry(&self) -> BasicBlock {
+        self.tree.order[0]
+    }
+
+    /// 可达基本块的逆后序
+    pub fn rpo(&self) -> &[BasicBlock] {
+        &self.tree.order
+    }
+
+    pub fn is_reachable(&self, bb: BasicBlock) -> bool {
+        self.tree.index.contains_key(&bb)
+    }
+
+    /// 直接支配者，入口和不可达的基本块为 `None`
+    pub fn idom(&self, bb: BasicBlock) -> Option<BasicBlock> {
+        self.tree.idom(bb)
+    }
+
+    /// 支配树上的子结点
+    pub fn children(&self, bb: BasicBlock) -> &[BasicBlock] {
+        self.tree.children(bb)
+    }
+
+    /// `a` 是否支配 `b`（每个可达的基本块都支配其自身）
+    pub fn dominates(&self, a: BasicBlock, b: BasicBlock) -> bool {
+        self.tree.dominates(a, b)
+    }
+
+    /// 每个可达基本块的支配边界
+    pub fn frontiers(&self, cfg: &Cfg) -> HashMap<BasicBlock, HashSet<BasicBlock>> {
+        let mut df: HashMap<BasicBlock, HashSet<BasicBlock>> =
+            self.rpo().iter().map(|&bb| (bb, HashSet::new())).collect();
+        for &bb in self.rpo() {
+            let preds: Vec<BasicBlock> = cfg.preds(bb).iter().copied().filter(|p| self.is_reachable(*p)).collect();
+            if preds.len() < 2 {
+                continue;
+            }
+            let idom = self.idom(bb);
+            for p in preds {
+                let mut runner = Some(p);
+                while runner.is_some() && runner != idom {
+                    let r = runner.unwrap();
+                    df.get_mut(&r).unwrap().insert(bb);
+                    runner = self.idom(r);
+                }
+            }
+        }
+        df
+    }
+}
+
+/// 后支配树，结点 `None` 为虚拟的出口
+pub struct PostDomTree {
+    tree: Tree<Option<BasicBlock>>,
+}
+
+impl PostDomTree {
+    pub fn new(cfg: &Cfg) -> PostDomTree {
+        let reachable: HashSet<BasicBlock> = cfg.rpo().iter().copied().collect();
+        let exits = cfg.exits();
+        // 反向的控制流图上，出口的后继为以 `ret` 结尾的基本块，基本块的后继为其可达的前驱
+        let order = reverse_postorder(None, |n| match n {
+            None => exits.iter().copied().map(Some).collect(),
+            Some(bb) => cfg.preds(bb).iter().copied().filter(|p| reachable.contains(p)).map(Some).collect(),
+        });
+        let tree = Tree::new(order, |n| match n {
+            None => vec![],
+            Some(bb) if cfg.succs(bb).is_empty() => vec![None],
+            Some(bb) => cfg.succs(bb).iter().copied().map(Some).collect(),
+        });
+        PostDomTree { tree }
+    }
+
+    /// 是否能到达出口
+    pub fn reaches_exit(&self, bb: BasicBlock) -> bool {
+        self.tree.index.contains_key(&Some(bb))
+    }
+
+    /// 直接后支配者；以 `ret` 结尾的、不能到达出口的，以及有多条到出口的路径而没有共同后支配者的基本块为 `None`
+    pub fn ipdom(&self, bb: BasicBlock) -> Option<BasicBlock> {
+        self.tree.idom(Some(bb)).flatten()
+    }
+
+    /// `a` 是否后支配 `b`，即从 `b` 到出口的路径都经过 `a`
+    pub fn post_dominates(&self, a: BasicBlock, b: BasicBlock) -> bool {
+        self.tree.dominates(Some(a), Some(b))
+    }
+
+    /// 后支配树上的子结点，`None` 的子结点为直接后支配者是出口的基本块
+    pub fn children(&self, bb: Option<BasicBlock>) -> Vec<BasicBlock> {
+        self.tree.children(bb).iter().map(|n| n.unwrap()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use koopa::front::Driver;
+
+    use super::{DomTree, PostDomTree};
+    use crate::opt::analysis::cfg::Cfg;
+
+    #[test]
+    fn dominators() {
+        let program = Driver::from(
+            r#"
+fun @f(@c: i32): i32 {
+%entry:
+  br @c, %then, %else
+%then:
+  br @c, %ret, %join
+%else:
+  br @c, %join, %spin
+%join:
+  ret 0
+%ret:
+  ret 1
+%spin:
+  jump %spin
+}
+"#,
+        )
+        .generate_program()
+        .unwrap();
+        let func = program.func(program.func_layout()[0]);
+        let bb = |name: &str| {
+            *func.dfg().bbs().iter().find(|(_, data)| data.name().as_deref() == Some(name)).unwrap().0
+        };
+        let (entry, then, els, join, ret, spin) =
+            (bb("%entry"), bb("%then"), bb("%else"), bb("%join"), bb("%ret"), bb("%spin"));
+
+        let cfg = Cfg::new(func);
+        let dom = DomTree::new(&cfg);
+        assert_eq!(dom.idom(join), Some(entry));
+        assert_eq!(dom.idom(ret), Some(then));
+        assert!(dom.dominates(entry, ret) && !dom.dominates(then, join));
+        assert_eq!(dom.idom(spin), Some(els));
+        assert_eq!(dom.frontiers(&cfg)[&then], [join].into());
+
+        let post_dom = PostDomTree::new(&cfg);
+        assert_eq!(post_dom.ipdom(els), Some(join));
+        // `%then` 之后可能到达两个 `ret`；`%spin` 不影响 `%else` 的后支配者
+        assert_eq!(post_dom.ipdom(then), None);
+        assert!(post_dom.post_dominates(join, els) && !post_dom.post_dominates(join, then));
+        assert!(!post_dom.reaches_exit(spin));
+        let mut roots = post_dom.children(None);
+        roots.sort_by_key(|bb| [entry, then, els, join, ret].iter().position(|b| b == bb));
+        assert_eq!(roots, [entry, then, join, ret]);
+    }
+}
@@ -0,0 +1,160 @@
+//! 归纳变量
+//!
+//! 基本归纳变量是循环头的 `i32` 参数 `i`：前置块传入初值，每条回边都传入同一个 `i + s`（或 `i - c`），
+//! 步长 `s` 为常量或在循环之外定义。派生归纳变量是循环中由某个基本归纳变量在同一次循环中的值
+//! 经加、减、乘以常量、左移常量位得到的值，形如 `c * i + k`：系数 `c` 为常量，`k` 循环不变，
+//! 不必显式求出——需要时按原样重新计算即可。基本归纳变量本身是系数为 1 的派生归纳变量。
+//!
+//! 结果依赖于函数中的指令，不随控制流图缓存，由变换在需要时计算。要求循环有前置块。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{BasicBlock, BinaryOp, FunctionData, Value, ValueKind};
+
+use crate::opt::edit;
+
+use super::{cfg::Cfg, loops::Loop};
+
+/// 每次循环的增量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Const(i32),
+    /// 在循环之外定义的值
+    Value(Value),
+}
+
+#[derive(Debug)]
+pub struct BasicIv {
+    /// 循环头的参数
+    pub param: Value,
+    /// 前置块传入的初值
+    pub init: Value,
+    /// 回边传入的下一次的值
+    pub next: Value,
+    pub step: Step,
+}
+
+/// 派生归纳变量：[`IndVars::basics`] 中第 `iv` 个基本归纳变量的 `coef` 倍加上循环不变的值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Derived {
+    pub iv: usize,
+    pub coef: i32,
+}
+
+pub struct IndVars {
+    pub basics: Vec<BasicIv>,
+    /// 循环中的派生归纳变量，包括基本归纳变量的参数
+    pub derived: HashMap<Value, Derived>,
+    /// 循环中定义的值
+    defined: HashSet<Value>,
+}
+
+impl IndVars {
+    pub fn new(func: &FunctionData, cfg: &Cfg, l: &Loop) -> IndVars {
+        let order: Vec<BasicBlock> = cfg.rpo().iter().copied().filter(|bb| l.contains(*bb)).collect();
+        let mut defined = HashSet::new();
+        for &bb in order.iter() {
+            defined.extend(func.dfg().bb(bb).params());
+            defined.extend(edit::block_insts(func, bb));
+        }
+        let mut ivs = IndVars { basics: vec![], derived: HashMap::new(), defined };
+        ivs.find_basics(func, cfg, l);
+        // 按逆后序，操作数先于使用者确定；经过回边的值只有循环头的参数
+        for &bb in order.iter() {
+            for inst in edit::block_insts(func, bb) {
+                if let Some(d) = ivs.derive(func, inst) {
+                    ivs.derived.insert(inst, d);
+                }
+            }
+        }
+        ivs
+    }
+
+    /// 是否为循环不变的值
+    pub fn is_invariant(&self, v: Value) -> bool {
+        !self.defined.contains(&v)
+    }
+
+    fn find_basics(&mut self, func: &FunctionData, cfg: &Cfg, l: &Loop) {
+        let pre = l.preheader(cfg).expect("loop has no preheader");
+        let params = func.dfg().bb(l.header).params();
+        let init = header_args(func, pre, l.header);
+        let latches: Vec<Vec<Value>> = l.latches.iter().map(|&bb| header_args(func, bb, l.header)).collect();
+        for (i, &param) in params.iter().enumerate() {
+            if !func.dfg().value(param).ty().is_i32() {
+                continue;
+            }
+            let next = latches[0][i];
+            if latches.iter().any(|args| args[i] != next) || !self.defined.contains(&next) {
+                continue;
+            }
+            let ValueKind::Binary(b) = func.dfg().value(next).kind() else {
+                continue;
+            };
+            let step = match (b.op(), b.lhs(), b.rhs()) {
+                (BinaryOp::Add, lhs, s) | (BinaryOp::Add, s, lhs) if lhs == param && self.is_invariant(s) => {
+                    match func.dfg().value(s).kind() {
+                        ValueKind::Integer(c) => Step::Const(c.value()),
+                        _ => Step::Value(s),
+                    }
+                }
+                (BinaryOp::Sub, lhs, s) if lhs == param => match func.dfg().value(s).kind() {
+                    ValueKind::Integer(c) => Step::Const(c.value().wrapping_neg()),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            self.derived.insert(param, Derived { iv: self.basics.len(), coef: 1 });
+            self.basics.push(BasicIv { param, init: init[i], next, step });
+        }
+    }
+
+    /// 若 `inst` 为派生归纳变量，返回其所属的基本归纳变量与系数
+    fn derive(&self, func: &FunctionData, inst: Value) -> Option<Derived> {
+        let ValueKind::Binary(b) = func.dfg().value(inst).kind() else {
+            return None;
+        };
+        let constant = |v: Value| match func.dfg().value(v).kind() {
+            ValueKind::Integer(i) => Some(i.value()),
+            _ => None,
+        };
+        // 循环不变的操作数视为系数为 0
+        let term = |v: Value| match self.derived.get(&v) {
+            Some(d) => Some(Some(*d)),
+            None if self.is_invariant(v) => Some(None),
+            None => None,
+        };
+        let (lhs, rhs) = (term(b.lhs())?, term(b.rhs())?);
+        let combine = |f: fn(i32, i32) -> i32| match (lhs, rhs) {
+            (Some(l), Some(r)) if l.iv == r.iv => Some(Derived { iv: l.iv, coef: f(l.coef, r.coef) }),
+            (Some(l), None) => Some(Derived { iv: l.iv, coef: f(l.coef, 0) }),
+            (None, Some(r)) => Some(Derived { iv: r.iv, coef: f(0, r.coef) }),
+            _ => None,
+        };
+        match b.op() {
+            BinaryOp::Add => combine(i32::wrapping_add),
+            BinaryOp::Sub => combine(i32::wrapping_sub),
+            BinaryOp::Mul => match (lhs, rhs) {
+                (Some(d), None) => constant(b.rhs()).map(|c| Derived { coef: d.coef.wrapping_mul(c), ..d }),
+                (None, Some(d)) => constant(b.lhs()).map(|c| Derived { coef: d.coef.wrapping_mul(c), ..d }),
+                _ => None,
+            },
+            BinaryOp::Shl => match (lhs, constant(b.rhs())) {
+                (Some(d), Some(k)) if (0..32).contains(&k) => Some(Derived { coef: d.coef.wrapping_shl(k as u32), ..d }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// `bb` 的终结指令跳转到 `header` 时的实参
+pub fn header_args(func: &FunctionData, bb: BasicBlock, header: BasicBlock) -> Vec<Value> {
+    let term = *func.layout().bbs().node(&bb).unwrap().insts().back_key().unwrap();
+    match func.dfg().value(term).kind() {
+        ValueKind::Jump(j) if j.target() == header => j.args().to_vec(),
+        ValueKind::Branch(b) if b.true_bb() == header => b.true_args().to_vec(),
+        ValueKind::Branch(b) if b.false_bb() == header => b.false_args().to_vec(),
+        _ => unreachable!("block does not jump to the loop header"),
+    }
+}
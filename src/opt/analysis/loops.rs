@@ -0,0 +1,324 @@
+//! 自然循环
+//!
+//! 回边是到支配其起点的基本块的边，该基本块为循环头。同一个循环头的所有回边构成一个自然循环：
+//! 循环头，以及不经过循环头就能到达某条回边的起点的基本块。两个循环要么不相交，要么一个包含另一个，
+//! 由此得到嵌套关系；最外层的循环深度为 1。
+//!
+//! 循环前置块是循环头唯一的、位于循环之外的前驱，且只跳转到循环头；
+//! 循环不变的计算可以移到这里。[`insert_preheader`] 在没有这样的基本块时插入一个，
+//! [`insert_preheaders`] 为所有的循环插入。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{builder_traits::*, BasicBlock, FunctionData, Type, Value, ValueKind};
+
+use crate::opt::edit;
+
+use super::{cfg::Cfg, dom::DomTree, Analyses};
+
+pub struct Loop {
+    pub header: BasicBlock,
+    /// 循环中的基本块，包括循环头与内层循环中的基本块
+    pub blocks: HashSet<BasicBlock>,
+    /// 回边的起点，按逆后序排列
+    pub latches: Vec<BasicBlock>,
+    /// 外层循环在 [`LoopInfo::loops`] 中的下标
+    pub parent: Option<usize>,
+    pub depth: usize,
+}
+
+impl Loop {
+    pub fn contains(&self, bb: BasicBlock) -> bool {
+        self.blocks.contains(&bb)
+    }
+
+    /// 循环之外、有来自循环中的前驱的基本块，按逆后序排列
+    pub fn exits(&self, cfg: &Cfg) -> Vec<BasicBlock> {
+        cfg.rpo()
+            .iter()
+            .copied()
+            .filter(|&bb| !self.contains(bb) && cfg.preds(bb).iter().any(|p| self.contains(*p)))
+            .collect()
+    }
+
+    /// 循环之外的、跳转到循环头的前驱
+    pub fn entries(&self, cfg: &Cfg) -> Vec<BasicBlock> {
+        cfg.preds(self.header).iter().copied().filter(|p| !self.contains(*p)).collect()
+    }
+
+    /// 循环前置块，没有时为 `None`
+    pub fn preheader(&self, cfg: &Cfg) -> Option<BasicBlock> {
+        match self.entries(cfg)[..] {
+            [pred] if cfg.succs(pred) == [self.header] => Some(pred),
+            _ => None,
+        }
+    }
+}
+
+pub struct LoopInfo {
+    /// 按循环头的逆后序排列，外层循环在内层循环之前
+    loops: Vec<Loop>,
+    /// 包含各基本块的最内层循环
+    innermost: HashMap<BasicBlock, usize>,
+}
+
+impl LoopInfo {
+    pub fn new(cfg: &Cfg, dom: &DomTree) -> LoopInfo {
+        let mut loops: Vec<Loop> = vec![];
+        for &header in cfg.rpo() {
+            let latches: Vec<BasicBlock> = cfg
+                .rpo()
+                .iter()
+                .copied()
+                .filter(|&bb| cfg.succs(bb).contains(&header) && dom.dominates(header, bb))
+                .collect();
+            if latches.is_empty() {
+                continue;
+            }
+            let mut blocks = HashSet::from([header]);
+            let mut work = latches.clone();
+            while let Some(bb) = work.pop() {
+                if blocks.insert(bb) {
+                    work.extend(cfg.preds(bb).iter().copied().filter(|p| dom.is_reachable(*p)));
+                }
+            }
+            // 外层循环已在前面，包含循环头的最小的一个即为直接的外层循环
+            let parent = (0..loops.len()).filter(|&i| loops[i].contains(header)).min_by_key(|&i| loops[i].blocks.len());
+            let depth = parent.map_or(1, |p| loops[p].depth + 1);
+            loops.push(Loop { header, blocks, latches, parent, depth });
+        }
+        // 内层循环在后，覆盖外层循环
+        let mut innermost = HashMap::new();
+        for (i, l) in loops.iter().enumerate() {
+            for &bb in l.blocks.iter() {
+                innermost.insert(bb, i);
+            }
+        }
+        LoopInfo { loops, innermost }
+    }
+
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    /// 包含 `bb` 的最内层循环在 [`LoopInfo::loops`] 中的下标
+    pub fn innermost(&self, bb: BasicBlock) -> Option<usize> {
+        self.innermost.get(&bb).copied()
+    }
+
+    /// 循环嵌套深度，不在循环中为 0
+    pub fn depth(&self, bb: BasicBlock) -> usize {
+        self.innermost(bb).map_or(0, |i| self.loops[i].depth)
+    }
+}
+
+/// 返回循环前置块，没有时插入一个；插入后控制流图改变，之前的分析结果失效
+///
+/// 插入的基本块紧接在循环头之前，名字为循环头的名字加上 `_preheader`（重名时再加编号），所有从循环之外到循环头的边改为到它。
+/// 只有一条这样的边时，实参由它传给循环头；否则它带有与循环头相同的参数，原样传给循环头。
+pub fn insert_preheader(func: &mut FunctionData, cfg: &Cfg, l: &Loop) -> BasicBlock {
+    if let Some(pre) = l.preheader(cfg) {
+        return pre;
+    }
+    let header = l.header;
+    let entries = l.entries(cfg);
+    // 每条从循环之外到循环头的边的实参
+    let mut edges: Vec<Vec<Value>> = vec![];
+    for &pred in entries.iter() {
+        let term = *func.layout().bbs().node(&pred).unwrap().insts().back_key().unwrap();
+        match func.dfg().value(term).kind() {
+            ValueKind::Jump(j) => edges.push(j.args().to_vec()),
+            ValueKind::Branch(b) => {
+                if b.true_bb() == header {
+                    edges.push(b.true_args().to_vec());
+                }
+                if b.false_bb() == header {
+                    edges.push(b.false_args().to_vec());
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    let name = edit::Namer::new(func).derive(func, header, "preheader");
+    let (pre, args) = if let [args] = &edges[..] {
+        (func.dfg_mut().new_bb().basic_block(name), args.clone())
+    } else {
+        let tys: Vec<Type> = func.dfg().bb(header).params().iter().map(|&p| func.dfg().value(p).ty().clone()).collect();
+        let pre = func.dfg_mut().new_bb().basic_block_with_params(name, tys);
+        (pre, func.dfg().bb(pre).params().to_vec())
+    };
+    let single = edges.len() == 1;
+    let jump = func.dfg_mut().new_value().jump_with_args(header, args);
+    func.layout_mut().bbs_mut().cursor_mut(header).insert_key_before(pre).unwrap();
+    func.layout_mut().bb_mut(pre).insts_mut().push_key_back(jump).unwrap();
+
+    for pred in entries {
+        let term = *func.layout().bbs().node(&pred).unwrap().insts().back_key().unwrap();
+        let redirect = |target: &mut BasicBlock, args: &mut Vec<Value>| {
+            if *target == header {
+                *target = pre;
+                if single {
+                    args.clear();
+                }
+            }
+        };
+        edit::rewrite(func, term, |kind| match kind {
+            ValueKind::Jump(j) => {
+                let (mut target, mut args) = (j.target(), j.args().to_vec());
+                redirect(&mut target, &mut args);
+                *j.target_mut() = target;
+                *j.args_mut() = args;
+            }
+            ValueKind::Branch(b) => {
+                let (mut target, mut args) = (b.true_bb(), b.true_args().to_vec());
+                redirect(&mut target, &mut args);
+                *b.true_bb_mut() = target;
+                *b.true_args_mut() = args;
+                let (mut target, mut args) = (b.false_bb(), b.false_args().to_vec());
+                redirect(&mut target, &mut args);
+                *b.false_bb_mut() = target;
+                *b.false_args_mut() = args;
+            }
+            _ => unreachable!(),
+        });
+    }
+    pre
+}
+
+/// 为没有前置块的循环插入前置块，返回是否有改动
+pub fn insert_preheaders(func: &mut FunctionData, analyses: &mut Analyses) -> bool {
+    let mut changed = false;
+    loop {
+        let (cfg, loops) = (analyses.cfg(func), analyses.loops(func));
+        let Some(l) = loops.loops().iter().find(|l| l.preheader(&cfg).is_none()) else {
+            return changed;
+        };
+        insert_preheader(func, &cfg, l);
+        analyses.invalidate();
+        changed = true;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use koopa::{back::KoopaGenerator, front::Driver, ir::BasicBlock};
+
+    use super::{insert_preheader, LoopInfo};
+    use crate::opt::analysis::{cfg::Cfg, dom::DomTree};
+
+    fn parse(source: &str) -> koopa::ir::Program {
+        Driver::from(source).generate_program().unwrap()
+    }
+
+    fn print(program: &koopa::ir::Program) -> String {
+        let mut gen = KoopaGenerator::new(Vec::new());
+        gen.generate_on(program).unwrap();
+        String::from_utf8(gen.writer()).unwrap()
+    }
+
+    #[test]
+    fn nesting_and_preheaders() {
+        // `%outer` 从入口和 `%skip` 两处进入；`%inner` 的前驱 `%outer` 以 `br` 结尾，也须插入前置块
+        let mut program = parse(
+            r#"
+fun @f(@n: i32): i32 {
+%entry:
+  br @n, %outer(0), %skip
+%skip:
+  jump %outer(1)
+%outer(%i: i32):
+  %0 = lt %i, @n
+  br %0, %inner(0), %end
+%inner(%j: i32):
+  %1 = add %j, 1
+  %2 = lt %1, %i
+  br %2, %inner(%1), %latch
+%latch:
+  %3 = add %i, 1
+  jump %outer(%3)
+%end:
+  ret %i
+}
+"#,
+        );
+        let f = program.func_layout()[0];
+        let func = program.func_mut(f);
+        let bb = |name: &str| -> BasicBlock {
+            *func.dfg().bbs().iter().find(|(_, data)| data.name().as_deref() == Some(name)).unwrap().0
+        };
+        let (skip, outer, inner, latch, end) = (bb("%skip"), bb("%outer"), bb("%inner"), bb("%latch"), bb("%end"));
+
+        let cfg = Cfg::new(func);
+        let loops = LoopInfo::new(&cfg, &DomTree::new(&cfg));
+        assert_eq!(loops.loops().len(), 2);
+        let (o, i) = (&loops.loops()[0], &loops.loops()[1]);
+        assert_eq!((o.header, o.depth, o.parent, o.latches.clone()), (outer, 1, None, vec![latch]));
+        assert_eq!((i.header, i.depth, i.parent, i.latches.clone()), (inner, 2, Some(0), vec![inner]));
+        assert_eq!(o.blocks, [outer, inner, latch].into());
+        assert_eq!((loops.depth(inner), loops.depth(latch), loops.depth(skip)), (2, 1, 0));
+        assert_eq!(o.exits(&cfg), [end]);
+        assert_eq!(o.preheader(&cfg), None);
+
+        insert_preheader(func, &cfg, i);
+        insert_preheader(func, &cfg, o);
+        let cfg = Cfg::new(func);
+        let loops = LoopInfo::new(&cfg, &DomTree::new(&cfg));
+        assert!(loops.loops().iter().all(|l| l.preheader(&cfg).is_some()));
+        // 解析时基本块按广度优先的顺序排列，两边都重新解析以便比较
+        assert_eq!(
+            print(&parse(&print(&program))),
+            print(&parse(
+                r#"
+fun @f(@n: i32): i32 {
+%entry:
+  br @n, %outer_preheader(0), %skip
+%skip:
+  jump %outer_preheader(1)
+%outer_preheader(%0: i32):
+  jump %outer(%0)
+%outer(%i: i32):
+  %1 = lt %i, @n
+  br %1, %inner_preheader, %end
+%inner_preheader:
+  jump %inner(0)
+%inner(%j: i32):
+  %2 = add %j, 1
+  %3 = lt %2, %i
+  br %3, %inner(%2), %latch
+%latch:
+  %4 = add %i, 1
+  jump %outer(%4)
+%end:
+  ret %i
+}
+"#
+            ))
+        );
+    }
+
+    #[test]
+    fn preheader_name() {
+        // 已有同名的基本块时，前置块的名字再加上编号
+        let mut program = parse(
+            r#"
+fun @f(@n: i32): i32 {
+%h_preheader:
+  br @n, %h(0), %end
+%h(%i: i32):
+  %0 = add %i, 1
+  %1 = lt %0, @n
+  br %1, %h(%0), %end
+%end:
+  ret 0
+}
+"#,
+        );
+        let f = program.func_layout()[0];
+        let func = program.func_mut(f);
+        let cfg = Cfg::new(func);
+        let loops = LoopInfo::new(&cfg, &DomTree::new(&cfg));
+        let pre = insert_preheader(func, &cfg, &loops.loops()[0]);
+        assert_eq!(func.dfg().bb(pre).name().as_deref(), Some("%h_preheader_1"));
+    }
+}
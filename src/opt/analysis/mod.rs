@@ -0,0 +1,69 @@
+//! 函数的控制流与别名分析
+//!
+//! - [`cfg::Cfg`]：前驱、后继与逆后序；
+//! - [`dom::DomTree`]、[`dom::PostDomTree`]：支配树、支配边界与后支配树；
+//! - [`loops::LoopInfo`]：自然循环及其嵌套，以及插入循环前置块的 [`loops::insert_preheader`]；
+//! - [`alias::AliasInfo`]：两个地址是否可能指向同一处，不缓存；
+//! - [`indvars::IndVars`]：循环中的基本与派生归纳变量，不缓存。
+//!
+//! 分析结果由 [`Analyses`] 按需计算并缓存。[`PassManager`](super::PassManager) 为每个函数保存一份，
+//! 变换报告有改动、且可能改变了控制流图时将其清空。变换若在使用分析结果之后自己修改了控制流图，
+//! 须调用 [`Analyses::invalidate`]。
+
+use std::rc::Rc;
+
+use koopa::ir::FunctionData;
+
+pub mod alias;
+pub mod cfg;
+pub mod dom;
+pub mod indvars;
+pub mod loops;
+
+use cfg::Cfg;
+use dom::{DomTree, PostDomTree};
+use loops::LoopInfo;
+
+/// 一个函数的分析结果的缓存
+#[derive(Default)]
+pub struct Analyses {
+    cfg: Option<Rc<Cfg>>,
+    dom: Option<Rc<DomTree>>,
+    post_dom: Option<Rc<PostDomTree>>,
+    loops: Option<Rc<LoopInfo>>,
+}
+
+impl Analyses {
+    pub fn cfg(&mut self, func: &FunctionData) -> Rc<Cfg> {
+        self.cfg.get_or_insert_with(|| Rc::new(Cfg::new(func))).clone()
+    }
+
+    pub fn dom(&mut self, func: &FunctionData) -> Rc<DomTree> {
+        if self.dom.is_none() {
+            let cfg = self.cfg(func);
+            self.dom = Some(Rc::new(DomTree::new(&cfg)));
+        }
+        self.dom.clone().unwrap()
+    }
+
+    pub fn post_dom(&mut self, func: &FunctionData) -> Rc<PostDomTree> {
+        if self.post_dom.is_none() {
+            let cfg = self.cfg(func);
+            self.post_dom = Some(Rc::new(PostDomTree::new(&cfg)));
+        }
+        self.post_dom.clone().unwrap()
+    }
+
+    pub fn loops(&mut self, func: &FunctionData) -> Rc<LoopInfo> {
+        if self.loops.is_none() {
+            let (cfg, dom) = (self.cfg(func), self.dom(func));
+            self.loops = Some(Rc::new(LoopInfo::new(&cfg, &dom)));
+        }
+        self.loops.clone().unwrap()
+    }
+
+    /// 丢弃所有分析结果
+    pub fn invalidate(&mut self) {
+        *self = Analyses::default();
+    }
+}
@@ -0,0 +1,282 @@
+//! 死代码删除
+//!
+//! 依次进行：
+//! - 删除从入口不可达的基本块，如前端在 `return`、`break` 之后留下的基本块；
+//! - 删除只被写入、从不被读取的局部变量，以及对它的 `store`；
+//! - 从有副作用的指令（`store`、`call` 与终结指令）出发标记活跃的值，删除其余的指令和基本块参数。
+//!   跳转的实参只在对应的参数活跃时才活跃，因此只在循环中传来传去的参数也会被删除；
+//! - 从数据流图中删除不再被引用的值，如前端生成后未使用的常量。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{builder_traits::*, BasicBlock, FunctionData, Value, ValueKind};
+
+use crate::util::cfg;
+
+use super::{analysis::Analyses, edit, FunctionPass};
+
+pub struct Dce;
+
+impl FunctionPass for Dce {
+    fn run(&mut self, func: &mut FunctionData, _: &mut Analyses) -> bool {
+        let mut changed = remove_unreachable(func);
+        changed |= remove_dead_stores(func);
+        changed |= remove_dead_values(func);
+        changed | sweep(func)
+    }
+}
+
+/// 从入口可达的基本块
+pub(super) fn reachable(func: &FunctionData) -> HashSet<BasicBlock> {
+    let entry = func.layout().entry_bb().unwrap();
+    let mut visited = HashSet::from([entry]);
+    let mut work = vec![entry];
+    while let Some(bb) = work.pop() {
+        for succ in cfg::successors(func, bb) {
+            if visited.insert(succ) {
+                work.push(succ);
+            }
+        }
+    }
+    visited
+}
+
+/// 删除从入口不可达的基本块，返回是否有改动
+pub(super) fn remove_unreachable(func: &mut FunctionData) -> bool {
+    let reachable = reachable(func);
+    let dead: Vec<BasicBlock> = func.layout().bbs().keys().copied().filter(|bb| !reachable.contains(bb)).collect();
+    if dead.is_empty() {
+        return false;
+    }
+    // 合法的 IR 中可达的代码不会使用不可达的基本块中的值，以防万一，这样的使用替换为 `undef`
+    let mut defined: HashSet<Value> = HashSet::new();
+    for &bb in dead.iter() {
+        defined.extend(edit::block_insts(func, bb));
+        defined.extend(func.dfg().bb(bb).params());
+    }
+    let escaped: HashSet<Value> = reachable
+        .iter()
+        .flat_map(|&bb| edit::block_insts(func, bb))
+        .flat_map(|inst| func.dfg().value(inst).kind().value_uses().collect::<Vec<_>>())
+        .filter(|v| defined.contains(v))
+        .collect();
+    let map: HashMap<Value, Value> = escaped
+        .into_iter()
+        .map(|v| {
+            let ty = func.dfg().value(v).ty().clone();
+            (v, func.dfg_mut().new_value().undef(ty))
+        })
+        .collect();
+    edit::replace_uses(func, &map);
+    edit::remove_bbs(func, &dead);
+    true
+}
+
+fn remove_dead_stores(func: &mut FunctionData) -> bool {
+    let insts = edit::insts(func);
+    let mut unread: HashSet<Value> = insts
+        .iter()
+        .copied()
+        .filter(|&v| matches!(func.dfg().value(v).kind(), ValueKind::Alloc(_)))
+        .collect();
+    for &inst in insts.iter() {
+        let kind = func.dfg().value(inst).kind();
+        for operand in kind.value_uses() {
+            if !matches!(kind, ValueKind::Store(s) if s.dest() == operand && s.value() != operand) {
+                unread.remove(&operand);
+            }
+        }
+    }
+    if unread.is_empty() {
+        return false;
+    }
+    let dead: Vec<Value> = insts
+        .into_iter()
+        .filter(|&v| match func.dfg().value(v).kind() {
+            ValueKind::Store(s) => unread.contains(&s.dest()),
+            _ => unread.contains(&v),
+        })
+        .collect();
+    edit::remove_insts(func, &dead);
+    true
+}
+
+fn has_side_effect(kind: &ValueKind) -> bool {
+    matches!(
+        kind,
+        ValueKind::Store(_) | ValueKind::Call(_) | ValueKind::Jump(_) | ValueKind::Branch(_) | ValueKind::Return(_)
+    )
+}
+
+pub(super) fn remove_dead_values(func: &mut FunctionData) -> bool {
+    let insts = edit::insts(func);
+    // 每个基本块参数收到的实参
+    let mut incoming: HashMap<Value, Vec<Value>> = HashMap::new();
+    let mut pass = |target: BasicBlock, args: &[Value]| {
+        for (&param, &arg) in func.dfg().bb(target).params().iter().zip(args) {
+            incoming.entry(param).or_default().push(arg);
+        }
+    };
+    for &inst in insts.iter() {
+        match func.dfg().value(inst).kind() {
+            ValueKind::Jump(j) => pass(j.target(), j.args()),
+            ValueKind::Branch(b) => {
+                pass(b.true_bb(), b.true_args());
+                pass(b.false_bb(), b.false_args());
+            }
+            _ => {}
+        }
+    }
+
+    let mut work: Vec<Value> =
+        insts.iter().copied().filter(|&v| has_side_effect(func.dfg().value(v).kind())).collect();
+    let mut live: HashSet<Value> = work.iter().copied().collect();
+    while let Some(v) = work.pop() {
+        let uses: Vec<Value> = match func.dfg().value(v).kind() {
+            ValueKind::Jump(_) => vec![],
+            ValueKind::Branch(b) => vec![b.cond()],
+            ValueKind::BlockArgRef(_) => incoming.get(&v).cloned().unwrap_or_default(),
+            kind => kind.value_uses().collect(),
+        };
+        for u in uses {
+            if !u.is_global() && live.insert(u) {
+                work.push(u);
+            }
+        }
+    }
+
+    let mut dead: Vec<Value> = insts.into_iter().filter(|v| !live.contains(v)).collect();
+    let bbs: Vec<BasicBlock> = func.layout().bbs().keys().copied().collect();
+    for bb in bbs {
+        dead.extend(edit::retain_params(func, bb, |p| live.contains(&p)));
+    }
+    if dead.is_empty() {
+        return false;
+    }
+    edit::remove_insts(func, &dead);
+    true
+}
+
+/// 删除数据流图中既不在布局中、也不被布局中的指令使用的值
+fn sweep(func: &mut FunctionData) -> bool {
+    let mut used: HashSet<Value> = func.params().iter().copied().collect();
+    for data in func.dfg().bbs().values() {
+        used.extend(data.params());
+    }
+    for inst in edit::insts(func) {
+        used.insert(inst);
+        used.extend(func.dfg().value(inst).kind().value_uses());
+    }
+    let garbage: Vec<Value> = func.dfg().values().keys().copied().filter(|v| !used.contains(v)).collect();
+    let before = func.dfg().values().len();
+    edit::remove_insts(func, &garbage);
+    func.dfg().values().len() != before
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn unreachable_and_dead_values() {
+        assert_passes(
+            &["dce"],
+            r#"
+decl @putint(i32)
+
+fun @f(@a: i32): i32 {
+%entry:
+  %0 = add @a, 1
+  %1 = mul %0, 2
+  call @putint(%0)
+  ret %0
+%ghost:
+  %2 = add %0, 3
+  call @putint(%2)
+  jump %end
+%end:
+  ret 0
+}
+"#,
+            r#"
+decl @putint(i32)
+
+fun @f(@a: i32): i32 {
+%entry:
+  %0 = add @a, 1
+  call @putint(%0)
+  ret %0
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn dead_stores() {
+        // `%x` 只被写入；`%y` 被读取，对它的写入都保留
+        assert_passes(
+            &["dce"],
+            r#"
+fun @f(@a: i32): i32 {
+%entry:
+  %x = alloc i32
+  %y = alloc i32
+  %0 = add @a, 1
+  store %0, %x
+  store 2, %y
+  store @a, %x
+  store 3, %y
+  %1 = load %y
+  ret %1
+}
+"#,
+            r#"
+fun @f(@a: i32): i32 {
+%entry:
+  %y = alloc i32
+  store 2, %y
+  store 3, %y
+  %0 = load %y
+  ret %0
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn dead_params() {
+        // 第二个参数只在循环中传递，从不用于计算结果
+        assert_passes(
+            &["dce"],
+            r#"
+fun @f(@n: i32): i32 {
+%entry:
+  jump %cond(0, 0)
+%cond(%i: i32, %j: i32):
+  %0 = lt %i, @n
+  br %0, %body, %end
+%body:
+  %1 = add %i, 1
+  %2 = add %j, %i
+  jump %cond(%1, %2)
+%end:
+  ret %i
+}
+"#,
+            r#"
+fun @f(@n: i32): i32 {
+%entry:
+  jump %cond(0)
+%cond(%i: i32):
+  %0 = lt %i, @n
+  br %0, %body, %end
+%body:
+  %1 = add %i, 1
+  jump %cond(%1)
+%end:
+  ret %i
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! 修改函数的辅助函数
+//!
+//! koopa 的 `replace_value_with` 以 `ValueData` 的 clone 替换原值，clone 不带 `used_by`，
+//! 因此被改写的值丢失了自己的使用者。各个变换都不依赖 `used_by`，使用关系一律扫描布局求得。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{builder_traits::*, BasicBlock, FunctionData, Type, Value, ValueKind};
+
+/// 基本块中的指令
+pub fn block_insts(func: &FunctionData, bb: BasicBlock) -> Vec<Value> {
+    func.layout().bbs().node(&bb).unwrap().insts().keys().copied().collect()
+}
+
+/// 布局中的所有指令，按布局顺序排列
+pub fn insts(func: &FunctionData) -> Vec<Value> {
+    func.layout().bbs().iter().flat_map(|(_, node)| node.insts().keys().copied()).collect()
+}
+
+/// 对指令的每个值操作数调用 `f`，包括跳转的实参
+pub fn map_operands(kind: &mut ValueKind, mut f: impl FnMut(Value) -> Value) {
+    let mut apply = |v: &mut Value| *v = f(*v);
+    match kind {
+        ValueKind::Load(l) => apply(l.src_mut()),
+        ValueKind::Store(s) => {
+            apply(s.value_mut());
+            apply(s.dest_mut());
+        }
+        ValueKind::GetPtr(g) => {
+            apply(g.src_mut());
+            apply(g.index_mut());
+        }
+        ValueKind::GetElemPtr(g) => {
+            apply(g.src_mut());
+            apply(g.index_mut());
+        }
+        ValueKind::Binary(b) => {
+            apply(b.lhs_mut());
+            apply(b.rhs_mut());
+        }
+        ValueKind::Branch(b) => {
+            apply(b.cond_mut());
+            b.true_args_mut().iter_mut().for_each(&mut apply);
+            b.false_args_mut().iter_mut().for_each(&mut apply);
+        }
+        ValueKind::Jump(j) => j.args_mut().iter_mut().for_each(&mut apply),
+        ValueKind::Call(c) => c.args_mut().iter_mut().for_each(&mut apply),
+        ValueKind::Return(r) => {
+            if let Some(v) = r.value_mut() {
+                apply(v);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 以 `f` 修改指令的内容，保留其类型与名字
+pub fn rewrite(func: &mut FunctionData, inst: Value, f: impl FnOnce(&mut ValueKind)) {
+    let mut data = func.dfg().value(inst).clone();
+    f(data.kind_mut());
+    func.dfg_mut().replace_value_with(inst).raw(data);
+}
+
+/// 将布局中所有指令对 `map` 的键的使用替换为对应的值
+pub fn replace_uses(func: &mut FunctionData, map: &HashMap<Value, Value>) {
+    if map.is_empty() {
+        return;
+    }
+    for inst in insts(func) {
+        if func.dfg().value(inst).kind().value_uses().any(|v| map.contains_key(&v)) {
+            rewrite(func, inst, |kind| map_operands(kind, |v| map.get(&v).copied().unwrap_or(v)));
+        }
+    }
+}
+
+/// 在基本块的参数列表末尾加入类型为 `tys` 的参数，返回新的参数
+pub fn add_params(func: &mut FunctionData, bb: BasicBlock, tys: Vec<Type>) -> Vec<Value> {
+    // 参数只能随基本块一起创建：借一个临时的基本块创建参数，其编号须接在已有的参数之后
+    let dfg = func.dfg_mut();
+    let existing: Vec<Type> = dfg.bb(bb).params().iter().map(|&p| dfg.value(p).ty().clone()).collect();
+    let tmp = dfg.new_bb().basic_block_with_params(None, [existing.clone(), tys].concat());
+    let mut params = std::mem::take(dfg.bb_mut(tmp).params_mut());
+    dfg.remove_bb(tmp);
+    for p in params.drain(..existing.len()) {
+        dfg.remove_value(p);
+    }
+    dfg.bb_mut(bb).params_mut().extend(params.iter().copied());
+    params
+}
+
+/// 从布局中删除指令；不再被使用的同时从数据流图中删除
+///
+/// 删除的顺序由扫描这些指令的操作数决定：指令被改写后 `used_by` 不完整，不能只靠它判断。
+pub fn remove_insts(func: &mut FunctionData, insts: &[Value]) {
+    for &inst in insts {
+        if let Some(bb) = func.layout().parent_bb(inst) {
+            func.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+        }
+    }
+    let mut pending: Vec<Value> = insts.iter().copied().collect::<HashSet<_>>().into_iter().collect();
+    loop {
+        let dfg = func.dfg();
+        let used: HashSet<Value> = pending.iter().flat_map(|&v| dfg.value(v).kind().value_uses()).collect();
+        let removable: HashSet<Value> = pending
+            .iter()
+            .copied()
+            .filter(|v| {
+                let data = dfg.value(*v);
+                !used.contains(v)
+                    && data.used_by().is_empty()
+                    && data.kind().value_uses().all(|u| u.is_global() || dfg.values().contains_key(&u))
+            })
+            .collect();
+        if removable.is_empty() {
+            break;
+        }
+        for &v in removable.iter() {
+            func.dfg_mut().remove_value(v);
+        }
+        pending.retain(|v| !removable.contains(v));
+    }
+}
+
+/// 只保留基本块中满足 `keep` 的参数，同时删去所有跳转到该基本块的对应实参；返回被删去的参数
+///
+/// 被删去的参数须已不再被使用，它们仍在数据流图中，可交给 [`remove_insts`] 删除。
+pub fn retain_params(func: &mut FunctionData, bb: BasicBlock, keep: impl Fn(Value) -> bool) -> Vec<Value> {
+    let params = func.dfg().bb(bb).params().to_vec();
+    let kept: Vec<bool> = params.iter().map(|&p| keep(p)).collect();
+    if kept.iter().all(|&k| k) {
+        return vec![];
+    }
+    let retain = |args: &mut Vec<Value>| {
+        let mut i = 0;
+        args.retain(|_| (kept[i], i += 1).0);
+    };
+    for inst in insts(func) {
+        let kind = func.dfg().value(inst).kind();
+        let targets = match kind {
+            ValueKind::Jump(j) => (j.target() == bb, false),
+            ValueKind::Branch(b) => (b.true_bb() == bb, b.false_bb() == bb),
+            _ => continue,
+        };
+        if targets == (false, false) {
+            continue;
+        }
+        rewrite(func, inst, |kind| match kind {
+            ValueKind::Jump(j) => retain(j.args_mut()),
+            ValueKind::Branch(b) => {
+                if targets.0 {
+                    retain(b.true_args_mut());
+                }
+                if targets.1 {
+                    retain(b.false_args_mut());
+                }
+            }
+            _ => unreachable!(),
+        });
+    }
+    let (keep, removed): (Vec<_>, Vec<_>) = params.iter().zip(kept.iter()).partition(|(_, &k)| k);
+    let keep: Vec<Value> = keep.into_iter().map(|(&p, _)| p).collect();
+    for (i, &p) in keep.iter().enumerate() {
+        rewrite(func, p, |kind| match kind {
+            ValueKind::BlockArgRef(a) => *a.index_mut() = i,
+            _ => unreachable!(),
+        });
+    }
+    *func.dfg_mut().bb_mut(bb).params_mut() = keep;
+    removed.into_iter().map(|(&p, _)| p).collect()
+}
+
+/// 删除基本块及其中的指令
+pub fn remove_bbs(func: &mut FunctionData, bbs: &[BasicBlock]) {
+    let insts: Vec<Value> = bbs.iter().flat_map(|&bb| block_insts(func, bb)).collect();
+    remove_insts(func, &insts);
+    for &bb in bbs {
+        func.layout_mut().bbs_mut().remove(&bb);
+        let dfg = func.dfg();
+        let data = dfg.bb(bb);
+        if data.used_by().is_empty() && data.params().iter().all(|&p| dfg.value(p).used_by().is_empty()) {
+            func.dfg_mut().remove_bb(bb);
+        }
+    }
+}
+
+/// 为新的基本块取不重复的名字
+pub struct Namer(HashSet<String>);
+
+impl Namer {
+    pub fn new(func: &FunctionData) -> Namer {
+        Namer(func.dfg().bbs().values().filter_map(|data| data.name().clone()).collect())
+    }
+
+    /// `%name`，已被占用时依次尝试 `%name_1`、`%name_2`……；`name` 不带 `%`
+    pub fn name(&mut self, name: String) -> Option<String> {
+        let name = (0..)
+            .map(|k| if k == 0 { format!("%{name}") } else { format!("%{name}_{k}") })
+            .find(|n| !self.0.contains(n))
+            .unwrap();
+        self.0.insert(name.clone());
+        Some(name)
+    }
+
+    /// 以基本块 `bb` 的名字加上 `_suffix` 为基础的名字
+    pub fn derive(&mut self, func: &FunctionData, bb: BasicBlock, suffix: &str) -> Option<String> {
+        let name = func.dfg().bb(bb).name().clone().unwrap();
+        self.name(format!("{}_{suffix}", &name[1..]))
+    }
+}
@@ -0,0 +1,358 @@
+//! 全局值编号与公共子表达式删除
+//!
+//! 沿支配树先序遍历，记录已经计算过的无副作用的表达式（`Binary`、`getelemptr` 与 `getptr`）：
+//! 再次遇到操作数相同的表达式时，它被支配它的第一次计算替换。整数常量按值比较，
+//! 可交换的运算与比较也尝试交换两边。
+//!
+//! `load` 另行处理：记录每个地址当前已知的值，来自之前的 `load` 或 `store`。
+//! `store` 使可能指向同一处的地址的记录失效，`call` 使被调用者可能访问的地址的记录失效。
+//! 记录只沿唯一的前驱传给后继；有多个前驱的基本块从空的记录开始，
+//! 因为其他前驱（如循环的回边）上可能有写入。地址是否可能指向同一处由 [`AliasInfo`] 判断。
+
+use std::{
+    collections::HashMap,
+    rc::Rc,
+};
+
+use koopa::ir::{BasicBlock, BinaryOp, FunctionData, Value, ValueKind};
+
+use super::{
+    analysis::{alias::AliasInfo, cfg::Cfg, dom::DomTree, Analyses},
+    edit, FunctionPass,
+};
+
+pub struct Gvn;
+
+impl FunctionPass for Gvn {
+    fn run(&mut self, func: &mut FunctionData, analyses: &mut Analyses) -> bool {
+        let mut numbering = Numbering::new(func, analyses);
+        numbering.run(func);
+        if numbering.values.is_empty() {
+            return false;
+        }
+        let removed: Vec<Value> = numbering.values.keys().copied().collect();
+        edit::replace_uses(func, &numbering.values);
+        edit::remove_insts(func, &removed);
+        true
+    }
+
+    fn preserves_cfg(&self) -> bool {
+        true
+    }
+}
+
+/// 值编号中的操作数：整数常量按值比较
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Num {
+    Value(Value),
+    Const(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Expr {
+    Binary(BinaryOp, Num, Num),
+    GetElemPtr(Num, Num),
+    GetPtr(Num, Num),
+}
+
+/// 交换两边后结果不变的等价表达式
+fn commuted(expr: Expr) -> Option<Expr> {
+    use BinaryOp::*;
+    let Expr::Binary(op, lhs, rhs) = expr else { return None };
+    let op = match op {
+        Add | Mul | And | Or | Xor | Eq | NotEq => op,
+        Lt => Gt,
+        Gt => Lt,
+        Le => Ge,
+        Ge => Le,
+        _ => return None,
+    };
+    Some(Expr::Binary(op, rhs, lhs))
+}
+
+struct Numbering {
+    cfg: Rc<Cfg>,
+    dom: Rc<DomTree>,
+    alias: AliasInfo,
+    /// 被替换的指令到替换它的值的映射
+    values: HashMap<Value, Value>,
+}
+
+impl Numbering {
+    fn new(func: &FunctionData, analyses: &mut Analyses) -> Numbering {
+        Numbering {
+            cfg: analyses.cfg(func),
+            dom: analyses.dom(func),
+            alias: AliasInfo::new(func),
+            values: HashMap::new(),
+        }
+    }
+
+    fn run(&mut self, func: &FunctionData) {
+        let mut stack = vec![(self.dom.entry(), HashMap::new(), HashMap::new())];
+        while let Some((bb, mut exprs, mut memory)) = stack.pop() {
+            self.number(func, bb, &mut exprs, &mut memory);
+            for &child in self.dom.children(bb) {
+                let memory = if self.cfg.preds(child) == [bb] { memory.clone() } else { HashMap::new() };
+                stack.push((child, exprs.clone(), memory));
+            }
+        }
+    }
+
+    fn resolve(&self, v: Value) -> Value {
+        self.values.get(&v).copied().unwrap_or(v)
+    }
+
+    fn num(&self, func: &FunctionData, v: Value) -> Num {
+        if v.is_global() {
+            return Num::Value(v);
+        }
+        match func.dfg().value(v).kind() {
+            ValueKind::Integer(i) => Num::Const(i.value()),
+            _ => Num::Value(self.resolve(v)),
+        }
+    }
+
+    fn number(
+        &mut self,
+        func: &FunctionData,
+        bb: BasicBlock,
+        exprs: &mut HashMap<Expr, Value>,
+        memory: &mut HashMap<Value, Value>,
+    ) {
+        for inst in edit::block_insts(func, bb) {
+            let expr = match func.dfg().value(inst).kind() {
+                ValueKind::Binary(b) => Expr::Binary(b.op(), self.num(func, b.lhs()), self.num(func, b.rhs())),
+                ValueKind::GetElemPtr(g) => Expr::GetElemPtr(self.num(func, g.src()), self.num(func, g.index())),
+                ValueKind::GetPtr(g) => Expr::GetPtr(self.num(func, g.src()), self.num(func, g.index())),
+                ValueKind::Load(l) => {
+                    let src = self.resolve(l.src());
+                    match memory.get(&src) {
+                        Some(&v) => {
+                            self.values.insert(inst, v);
+                        }
+                        None => {
+                            memory.insert(src, inst);
+                        }
+                    }
+                    continue;
+                }
+                ValueKind::Store(s) => {
+                    let dest = self.resolve(s.dest());
+                    memory.retain(|&addr, _| !self.alias.may_alias(func, addr, dest));
+                    memory.insert(dest, self.resolve(s.value()));
+                    continue;
+                }
+                ValueKind::Call(_) => {
+                    memory.retain(|&addr, _| !self.alias.call_may_access(func, addr));
+                    continue;
+                }
+                _ => continue,
+            };
+            let found = exprs.get(&expr).or_else(|| commuted(expr).and_then(|e| exprs.get(&e)));
+            match found {
+                Some(&v) => {
+                    self.values.insert(inst, v);
+                }
+                None => {
+                    exprs.insert(expr, inst);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn expressions() {
+        // 常量按值比较，`mul` 可交换；`%then` 中的 `%4` 被入口的 `%0` 替换，
+        // 两个分支各自计算的 `%2` 与 `%5` 互不支配，都保留
+        assert_passes(
+            &["gvn"],
+            r#"
+fun @f(@a: *[i32, 10], @i: i32, @n: i32): i32 {
+%entry:
+  %0 = mul @i, @n
+  %1 = mul @n, @i
+  %p = getptr @a, 1
+  %q = getptr @a, 1
+  %x = getelemptr %p, %0
+  %y = getelemptr %q, %1
+  br %0, %then, %else
+%then:
+  %2 = add %0, 1
+  %4 = mul @i, @n
+  jump %end(%2)
+%else:
+  %5 = add %0, 1
+  jump %end(%5)
+%end(%r: i32):
+  %6 = add %0, 1
+  ret %6
+}
+"#,
+            r#"
+fun @f(@a: *[i32, 10], @i: i32, @n: i32): i32 {
+%entry:
+  %0 = mul @i, @n
+  %p = getptr @a, 1
+  %x = getelemptr %p, %0
+  br %0, %then, %else
+%then:
+  %1 = add %0, 1
+  jump %end(%1)
+%else:
+  %2 = add %0, 1
+  jump %end(%2)
+%end(%r: i32):
+  %3 = add %0, 1
+  ret %3
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn loads() {
+        // `%v` 的地址传给了 `@g`，`call` 之后须重新读取；`%u` 的不会被 `call` 或经由 `@p` 的写入改变；
+        // 对 `%v[0]` 的写入不影响 `%v[1]`；循环头有回边，不沿用入口的记录
+        assert_passes(
+            &["gvn"],
+            r#"
+global @k = alloc i32, zeroinit
+
+decl @g(*i32)
+
+fun @f(@p: *i32): i32 {
+%entry:
+  %u = alloc i32
+  %v = alloc [i32, 2]
+  store 1, %u
+  %v0 = getelemptr %v, 0
+  %v1 = getelemptr %v, 1
+  store 2, %v1
+  %0 = load %u
+  store 3, %v0
+  %1 = load %v1
+  store 4, @p
+  %2 = load @k
+  %3 = load %u
+  %4 = load @k
+  call @g(%v0)
+  %5 = load %v1
+  %6 = load %u
+  jump %loop
+%loop:
+  %7 = load %u
+  %8 = add %0, %1
+  %9 = add %8, %2
+  %10 = add %9, %3
+  %11 = add %10, %4
+  %12 = add %11, %5
+  %13 = add %12, %6
+  %14 = add %13, %7
+  store %14, %u
+  br %14, %loop, %end
+%end:
+  ret %14
+}
+"#,
+            r#"
+global @k = alloc i32, zeroinit
+
+decl @g(*i32)
+
+fun @f(@p: *i32): i32 {
+%entry:
+  %u = alloc i32
+  %v = alloc [i32, 2]
+  store 1, %u
+  %v0 = getelemptr %v, 0
+  %v1 = getelemptr %v, 1
+  store 2, %v1
+  store 3, %v0
+  store 4, @p
+  %0 = load @k
+  call @g(%v0)
+  %1 = load %v1
+  jump %loop
+%loop:
+  %2 = load %u
+  %3 = add 1, 2
+  %4 = add %3, %0
+  %5 = add %4, 1
+  %6 = add %5, %0
+  %7 = add %6, %1
+  %8 = add %7, 1
+  %9 = add %8, %2
+  store %9, %u
+  br %9, %loop, %end
+%end:
+  ret %9
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn getptr_past_element() {
+        // `%w` 由 `@a[0]` 经 `getptr` 后移一个元素，与 `%r` 同为 `@a[1]`，写入后须重新读取；
+        // `%t` 只经过 `getelemptr`，落在 `@b[0]` 中，不影响 `@b[1]` 中的 `%s`
+        assert_passes(
+            &["gvn"],
+            r#"
+global @a = alloc [i32, 2], zeroinit
+global @b = alloc [[i32, 2], 2], zeroinit
+
+fun @f(): i32 {
+%entry:
+  %a0 = getelemptr @a, 0
+  %w = getptr %a0, 1
+  %r = getelemptr @a, 1
+  %0 = load %r
+  store 5, %w
+  %1 = load %r
+  %b0 = getelemptr @b, 0
+  %t = getelemptr %b0, 1
+  %b1 = getelemptr @b, 1
+  %s = getelemptr %b1, 0
+  %2 = load %s
+  store 6, %t
+  %3 = load %s
+  %4 = add %0, %1
+  %5 = add %2, %3
+  %6 = add %4, %5
+  ret %6
+}
+"#,
+            r#"
+global @a = alloc [i32, 2], zeroinit
+
+global @b = alloc [[i32, 2], 2], zeroinit
+
+fun @f(): i32 {
+%entry:
+  %a0 = getelemptr @a, 0
+  %w = getptr %a0, 1
+  %r = getelemptr @a, 1
+  %0 = load %r
+  store 5, %w
+  %1 = load %r
+  %b0 = getelemptr @b, 0
+  %t = getelemptr %b0, 1
+  %b1 = getelemptr @b, 1
+  %s = getelemptr %b1, 0
+  %2 = load %s
+  store 6, %t
+  %3 = add %0, %1
+  %4 = add %2, %2
+  %5 = add %3, %4
+  ret %5
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! 函数内联
+//!
+//! 按调用图自底向上处理各个函数，被调用者先于调用者，其中的调用已经内联过。内联一个调用处时：
+//! 调用所在的基本块在调用之后拆开，后半部分成为继续块，调用的结果为继续块的参数；
+//! 被调用者可达的基本块连同其中的指令复制到调用者中，形参替换为实参，`ret` 改为带着返回值跳转到继续块；
+//! 原来的调用改为跳转到复制的入口。复制的 `alloc` 移到调用者的入口，之后可由 mem2reg 提升。
+//!
+//! 是否内联由被调用者的大小（指令数）决定：不超过 [`SMALL`] 的总是内联，只有一个调用处的不超过 [`SINGLE_CALL`]
+//! 时内联；调用者内联后不超过 [`MAX_CALLER`]。递归的函数（在调用图的环上）、没有函数体的函数，
+//! 以及用 `SetHi` 返回 64 位值的函数不内联。所有调用处都已内联的函数在最后删去。
+//!
+//! 后端以函数名和基本块名作为标号，复制的基本块命名为被调用者的名字加上原来的名字，重名时加上编号。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{
+    builder_traits::*, entities::ValueData, BasicBlock, Function, FunctionData, Program, Type, TypeKind, Value,
+    ValueKind,
+};
+
+use super::{analysis::cfg::Cfg, edit, ModulePass};
+use crate::util::intrinsic::Intrinsic;
+
+/// 不超过该大小的函数总是内联
+const SMALL: usize = 40;
+/// 只有一个调用处的函数不超过该大小时内联
+const SINGLE_CALL: usize = 200;
+/// 调用者内联后的大小上限
+const MAX_CALLER: usize = 2000;
+
+/// 每个函数调用的函数，每个调用处一次
+type CallGraph = HashMap<Function, Vec<Function>>;
+
+pub struct Inline;
+
+impl ModulePass for Inline {
+    fn run(&mut self, program: &mut Program) -> bool {
+        let funcs: Vec<Function> = program.func_layout().to_vec();
+        let graph: CallGraph = funcs.iter().map(|&f| (f, callees(program.func(f)))).collect();
+        let recursive: HashSet<Function> = funcs.iter().copied().filter(|&f| reaches(&graph, f, f)).collect();
+        let mut sizes: HashMap<Function, usize> =
+            funcs.iter().map(|&f| (f, edit::insts(program.func(f)).len())).collect();
+        let mut calls: HashMap<Function, usize> = HashMap::new();
+        for callee in graph.values().flatten() {
+            *calls.entry(*callee).or_default() += 1;
+        }
+        let set_hi = funcs.iter().copied().filter(|&f| calls_set_hi(program, f)).collect::<HashSet<_>>();
+
+        let mut inlined = HashSet::new();
+        for caller in bottom_up(&funcs, &graph) {
+            for call in call_sites(program.func(caller)) {
+                let ValueKind::Call(c) = program.func(caller).dfg().value(call).kind() else {
+                    unreachable!()
+                };
+                let callee = c.callee();
+                let size = sizes[&callee];
+                let wanted = program.func(callee).layout().entry_bb().is_some()
+                    && !recursive.contains(&callee)
+                    && !set_hi.contains(&callee)
+                    && (size <= SMALL || calls[&callee] == 1 && size <= SINGLE_CALL)
+                    && sizes[&caller] + size <= MAX_CALLER;
+                if !wanted {
+                    continue;
+                }
+                let body = Body::new(program.func(callee));
+                inline(program.func_mut(caller), call, &body);
+                *sizes.get_mut(&caller).unwrap() += size;
+                *calls.get_mut(&callee).unwrap() -= 1;
+                // 被调用者中的调用复制到了调用者中，其中的调用可能已经内联过，不用原来的调用图
+                for f in callees(program.func(callee)) {
+                    *calls.get_mut(&f).unwrap() += 1;
+                }
+                inlined.insert(callee);
+            }
+        }
+
+        for f in funcs {
+            if inlined.contains(&f) && calls[&f] == 0 && program.func(f).name() != "@main" {
+                program.remove_func(f);
+            }
+        }
+        !inlined.is_empty()
+    }
+}
+
+/// 函数中调用的函数，每个调用处一次
+fn callees(func: &FunctionData) -> Vec<Function> {
+    call_sites(func)
+        .into_iter()
+        .map(|inst| match func.dfg().value(inst).kind() {
+            ValueKind::Call(c) => c.callee(),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// 函数中的 `call` 指令，按布局顺序排列
+fn call_sites(func: &FunctionData) -> Vec<Value> {
+    edit::insts(func)
+        .into_iter()
+        .filter(|&inst| matches!(func.dfg().value(inst).kind(), ValueKind::Call(_)))
+        .collect()
+}
+
+/// 从 `from` 调用的函数出发，是否能经过调用到达 `to`
+fn reaches(graph: &CallGraph, from: Function, to: Function) -> bool {
+    let mut visited = HashSet::new();
+    let mut work = graph[&from].clone();
+    while let Some(f) = work.pop() {
+        if f == to {
+            return true;
+        }
+        if visited.insert(f) {
+            work.extend(graph[&f].iter().copied());
+        }
+    }
+    false
+}
+
+/// 调用图的后序，被调用者在调用者之前
+fn bottom_up(funcs: &[Function], graph: &CallGraph) -> Vec<Function> {
+    fn visit(f: Function, graph: &CallGraph, visited: &mut HashSet<Function>, order: &mut Vec<Function>) {
+        if !visited.insert(f) {
+            return;
+        }
+        for &callee in graph[&f].iter() {
+            visit(callee, graph, visited, order);
+        }
+        order.push(f);
+    }
+    let (mut visited, mut order) = (HashSet::new(), vec![]);
+    for &f in funcs {
+        visit(f, graph, &mut visited, &mut order);
+    }
+    order
+}
+
+fn calls_set_hi(program: &Program, f: Function) -> bool {
+    callees(program.func(f)).into_iter().any(|callee| program.func(callee).name() == Intrinsic::SetHi.name())
+}
+
+/// 复制所需的被调用者的内容
+struct Body {
+    /// 不带 `@` 的函数名
+    name: String,
+    params: Vec<Value>,
+    /// 可达的基本块，按逆后序排列
+    bbs: Vec<BasicBlock>,
+    bb_names: HashMap<BasicBlock, String>,
+    bb_params: HashMap<BasicBlock, Vec<Value>>,
+    insts: HashMap<BasicBlock, Vec<Value>>,
+    values: HashMap<Value, ValueData>,
+}
+
+impl Body {
+    fn new(func: &FunctionData) -> Body {
+        let bbs = Cfg::new(func).rpo().to_vec();
+        let bb_name = |bb: BasicBlock| {
+            let name = func.dfg().bb(bb).name().clone().unwrap_or_else(|| "%bb".to_string());
+            name[1..].to_string()
+        };
+        Body {
+            name: func.name()[1..].to_string(),
+            params: func.params().to_vec(),
+            bb_names: bbs.iter().map(|&bb| (bb, bb_name(bb))).collect(),
+            bb_params: bbs.iter().map(|&bb| (bb, func.dfg().bb(bb).params().to_vec())).collect(),
+            insts: bbs.iter().map(|&bb| (bb, edit::block_insts(func, bb))).collect(),
+            values: func.dfg().values().clone(),
+            bbs,
+        }
+    }
+}
+
+/// 将调用 `call` 替换为 `body` 的副本
+fn inline(func: &mut FunctionData, call: Value, body: &Body) {
+    let bb = func.layout().parent_bb(call).unwrap();
+    let ValueKind::Call(c) = func.dfg().value(call).kind() else {
+        unreachable!()
+    };
+    let args = c.args().to_vec();
+    let ret_ty = func.dfg().value(call).ty().clone();
+    let mut namer = edit::Namer::new(func);
+
+    // 拆出继续块，调用的结果为其参数
+    let cont_name = namer.name(format!("{}_ret", body.name));
+    let cont = if matches!(ret_ty.kind(), TypeKind::Unit) {
+        func.dfg_mut().new_bb().basic_block(cont_name)
+    } else {
+        func.dfg_mut().new_bb().basic_block_with_params(cont_name, vec![ret_ty])
+    };
+    func.layout_mut().bbs_mut().cursor_mut(bb).insert_key_after(cont).unwrap();
+    let rest: Vec<Value> = edit::block_insts(func, bb).into_iter().skip_while(|&inst| inst != call).skip(1).collect();
+    for inst in rest {
+        func.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+        func.layout_mut().bb_mut(cont).insts_mut().push_key_back(inst).unwrap();
+    }
+    if let Some(&result) = func.dfg().bb(cont).params().first() {
+        edit::replace_uses(func, &HashMap::from([(call, result)]));
+    }
+
+    // 复制基本块，形参与基本块参数对应到实参与新的参数
+    let mut values: HashMap<Value, Value> = body.params.iter().copied().zip(args).collect();
+    let mut bbs: HashMap<BasicBlock, BasicBlock> = HashMap::new();
+    for &old in body.bbs.iter() {
+        let name = namer.name(format!("{}_{}", body.name, body.bb_names[&old]));
+        let params = &body.bb_params[&old];
+        let tys: Vec<Type> = params.iter().map(|p| body.values[p].ty().clone()).collect();
+        let new = func.dfg_mut().new_bb().basic_block_with_params(name, tys);
+        values.extend(params.iter().copied().zip(func.dfg().bb(new).params().to_vec()));
+        func.layout_mut().bbs_mut().cursor_mut(cont).insert_key_before(new).unwrap();
+        bbs.insert(old, new);
+    }
+
+    // 按逆后序复制指令，操作数都已复制；常量在用到时复制
+    let mut allocs = vec![];
+    for &old in body.bbs.iter() {
+        for &inst in body.insts[&old].iter() {
+            let mut data = body.values[&inst].clone();
+            edit::map_operands(data.kind_mut(), |v| {
+                if v.is_global() {
+                    return v;
+                }
+                *values.entry(v).or_insert_with(|| func.dfg_mut().new_value().raw(body.values[&v].clone()))
+            });
+            match data.kind_mut() {
+                ValueKind::Jump(j) => *j.target_mut() = bbs[&j.target()],
+                ValueKind::Branch(b) => {
+                    *b.true_bb_mut() = bbs[&b.true_bb()];
+                    *b.false_bb_mut() = bbs[&b.false_bb()];
+                }
+                _ => {}
+            }
+            let new = match data.kind() {
+                ValueKind::Return(r) => {
+                    let args = r.value().into_iter().collect();
+                    func.dfg_mut().new_value().jump_with_args(cont, args)
+                }
+                _ => func.dfg_mut().new_value().raw(data),
+            };
+            if matches!(func.dfg().value(new).kind(), ValueKind::Alloc(_)) {
+                allocs.push(new);
+            } else {
+                func.layout_mut().bb_mut(bbs[&old]).insts_mut().push_key_back(new).unwrap();
+            }
+            values.insert(inst, new);
+        }
+    }
+
+    let entry = func.layout().entry_bb().unwrap();
+    for alloc in allocs.into_iter().rev() {
+        func.layout_mut().bb_mut(entry).insts_mut().push_key_front(alloc).unwrap();
+    }
+    let jump = func.dfg_mut().new_value().jump(bbs[&body.bbs[0]]);
+    edit::remove_insts(func, &[call]);
+    func.layout_mut().bb_mut(bb).insts_mut().push_key_back(jump).unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn inline() {
+        // `@max` 内联后不再被调用，删去；递归的 `@fact` 不内联，其中对 `@inc` 的调用内联，
+        // 复制的 `alloc` 移到入口；声明的 `@putint` 保留
+        assert_passes(
+            &["inline"],
+            r#"
+decl @putint(i32)
+
+fun @max(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = gt @a, @b
+  br %0, %then, %else
+%then:
+  ret @a
+%else:
+  ret @b
+}
+
+fun @inc(@x: i32): i32 {
+%entry:
+  %p = alloc i32
+  store @x, %p
+  %0 = load %p
+  %1 = add %0, 1
+  ret %1
+}
+
+fun @fact(@n: i32): i32 {
+%entry:
+  br @n, %rec, %end
+%rec:
+  %0 = sub @n, 1
+  %1 = call @fact(%0)
+  %2 = mul @n, %1
+  %3 = call @inc(%2)
+  ret %3
+%end:
+  ret 1
+}
+
+fun @main(): i32 {
+%entry:
+  %0 = call @max(1, 2)
+  %1 = call @fact(%0)
+  %2 = call @max(%1, 3)
+  call @putint(%2)
+  ret 0
+}
+"#,
+            r#"
+decl @putint(i32)
+
+fun @fact(@n: i32): i32 {
+%entry:
+  %p = alloc i32
+  br @n, %rec, %end
+%rec:
+  %0 = sub @n, 1
+  %1 = call @fact(%0)
+  %2 = mul @n, %1
+  jump %inc_entry
+%inc_entry:
+  store %2, %p
+  %4 = load %p
+  %5 = add %4, 1
+  jump %inc_ret(%5)
+%inc_ret(%3: i32):
+  ret %3
+%end:
+  ret 1
+}
+
+fun @main(): i32 {
+%entry:
+  jump %max_entry
+%max_entry:
+  %0 = gt 1, 2
+  br %0, %max_then, %max_else
+%max_then:
+  jump %max_ret(1)
+%max_else:
+  jump %max_ret(2)
+%max_ret(%1: i32):
+  %2 = call @fact(%1)
+  jump %max_entry_1
+%max_entry_1:
+  %3 = gt %2, 3
+  br %3, %max_then_1, %max_else_1
+%max_then_1:
+  jump %max_ret_1(%2)
+%max_else_1:
+  jump %max_ret_1(3)
+%max_ret_1(%4: i32):
+  call @putint(%4)
+  ret 0
+}
+"#,
+        );
+    }
+}
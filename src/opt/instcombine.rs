@@ -0,0 +1,321 @@
+//! 代数化简与窥孔重写
+//!
+//! 对每条 `Binary` 指令依次尝试 [`RULES`] 中的规则，第一条适用的规则将它替换为已有的值、常量，
+//! 或另一条 `Binary`。反复进行直到不再有改动。
+//!
+//! 规范形式：可交换的运算与比较把常量放在右边，减去常量改为加上其相反数；
+//! 此后的规则只需考虑常量在右边的情形。前端把 `!x` 翻译为 `eq x, 0`、`-x` 翻译为 `sub 0, x`，
+//! 逻辑运算又与 0 比较，相应的规则消去这些多余的比较与取反。重写后不再被使用的运算随即删除。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{builder_traits::*, values::Binary, BinaryOp, FunctionData, Value, ValueKind};
+
+use super::{analysis::Analyses, edit, sccp, FunctionPass};
+
+use self::Operand::{Const, Value as Val};
+use BinaryOp::*;
+
+pub struct InstCombine;
+
+impl FunctionPass for InstCombine {
+    fn run(&mut self, func: &mut FunctionData, _: &mut Analyses) -> bool {
+        let mut changed = false;
+        loop {
+            let mut round = false;
+            for inst in edit::insts(func) {
+                // 本轮中已被删除
+                if func.layout().parent_bb(inst).is_none() {
+                    continue;
+                }
+                let ValueKind::Binary(bin) = func.dfg().value(inst).kind() else { continue };
+                if let Some(rewrite) = RULES.iter().find_map(|rule| (rule.apply)(func, bin)) {
+                    apply(func, inst, rewrite);
+                    round = true;
+                }
+            }
+            round |= remove_unused(func);
+            if !round {
+                return changed;
+            }
+            changed = true;
+        }
+    }
+
+    fn preserves_cfg(&self) -> bool {
+        true
+    }
+}
+
+/// 操作数：已有的值或常量
+#[derive(Debug, Clone, Copy)]
+pub enum Operand {
+    Value(Value),
+    Const(i32),
+}
+
+/// 规则给出的替换
+#[derive(Debug, Clone, Copy)]
+pub enum Rewrite {
+    Operand(Operand),
+    Binary(BinaryOp, Operand, Operand),
+}
+
+/// 一条重写规则，`apply` 不适用时返回 `None`
+pub struct Rule {
+    pub name: &'static str,
+    pub desc: &'static str,
+    apply: fn(&FunctionData, &Binary) -> Option<Rewrite>,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule { name: "fold", desc: "evaluate operations on two constants", apply: fold },
+    Rule { name: "commute", desc: "move a constant to the right of a commutative operation or comparison", apply: commute },
+    Rule { name: "sub-const", desc: "x - c => x + (-c)", apply: sub_const },
+    Rule { name: "identity", desc: "x + 0, x - 0, x * 1, x / 1, x | 0, x ^ 0 and shifts by 0 => x", apply: identity },
+    Rule { name: "absorb", desc: "x * 0, x & 0 => 0; x % 1 => 0", apply: absorb },
+    Rule { name: "self", desc: "x - x, x ^ x => 0; x & x, x | x => x; x == x => 1 and so on", apply: same_operands },
+    Rule { name: "neg", desc: "x * -1, x / -1 => 0 - x", apply: neg },
+    Rule { name: "double-neg", desc: "0 - (0 - x) => x", apply: double_neg },
+    Rule { name: "add-neg", desc: "x + (0 - y) => x - y; x - (0 - y) => x + y", apply: add_neg },
+    Rule { name: "reassoc", desc: "(x + c1) + c2 => x + (c1 + c2); likewise for *, &, |, ^", apply: reassoc },
+    Rule { name: "bool-cmp", desc: "b != 0 => b and b == 0 => !b when b is a comparison", apply: bool_cmp },
+];
+
+fn int(func: &FunctionData, v: Value) -> Option<i32> {
+    match func.dfg().values().get(&v)?.kind() {
+        ValueKind::Integer(i) => Some(i.value()),
+        _ => None,
+    }
+}
+
+fn binary(func: &FunctionData, v: Value) -> Option<&Binary> {
+    match func.dfg().values().get(&v)?.kind() {
+        ValueKind::Binary(b) => Some(b),
+        _ => None,
+    }
+}
+
+fn is_commutative(op: BinaryOp) -> bool {
+    matches!(op, Add | Mul | And | Or | Xor | Eq | NotEq)
+}
+
+fn is_comparison(op: BinaryOp) -> bool {
+    matches!(op, Eq | NotEq | Lt | Gt | Le | Ge)
+}
+
+/// 交换比较的两边后的比较
+pub(super) fn swapped(op: BinaryOp) -> BinaryOp {
+    match op {
+        Lt => Gt,
+        Gt => Lt,
+        Le => Ge,
+        Ge => Le,
+        _ => op,
+    }
+}
+
+/// 结果取反的比较
+fn inverted(op: BinaryOp) -> BinaryOp {
+    match op {
+        Eq => NotEq,
+        NotEq => Eq,
+        Lt => Ge,
+        Ge => Lt,
+        Gt => Le,
+        Le => Gt,
+        _ => unreachable!(),
+    }
+}
+
+fn fold(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    let (x, y) = (int(func, b.lhs())?, int(func, b.rhs())?);
+    sccp::eval(b.op(), x, y).map(|c| Rewrite::Operand(Const(c)))
+}
+
+fn commute(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    let op = b.op();
+    if int(func, b.lhs()).is_none() || int(func, b.rhs()).is_some() {
+        return None;
+    }
+    (is_commutative(op) || is_comparison(op)).then(|| Rewrite::Binary(swapped(op), Val(b.rhs()), Val(b.lhs())))
+}
+
+fn sub_const(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    let c = int(func, b.rhs())?;
+    (b.op() == Sub && c != 0).then(|| Rewrite::Binary(Add, Val(b.lhs()), Const(c.wrapping_neg())))
+}
+
+fn identity(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    let c = int(func, b.rhs())?;
+    let unit = match b.op() {
+        Add | Sub | Or | Xor | Shl | Shr | Sar => 0,
+        Mul | Div => 1,
+        _ => return None,
+    };
+    (c == unit).then(|| Rewrite::Operand(Val(b.lhs())))
+}
+
+fn absorb(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    match (b.op(), int(func, b.rhs())?) {
+        (Mul | And, 0) | (Mod, 1 | -1) => Some(Rewrite::Operand(Const(0))),
+        _ => None,
+    }
+}
+
+fn same_operands(_: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    if b.lhs() != b.rhs() {
+        return None;
+    }
+    Some(Rewrite::Operand(match b.op() {
+        Sub | Xor | NotEq | Lt | Gt => Const(0),
+        Eq | Le | Ge => Const(1),
+        And | Or => Val(b.lhs()),
+        _ => return None,
+    }))
+}
+
+/// `v` 为 `0 - x` 时的 `x`
+fn is_neg(func: &FunctionData, v: Value) -> Option<Value> {
+    let b = binary(func, v)?;
+    (b.op() == Sub && int(func, b.lhs()) == Some(0)).then(|| b.rhs())
+}
+
+fn neg(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    (matches!(b.op(), Mul | Div) && int(func, b.rhs())? == -1).then(|| Rewrite::Binary(Sub, Const(0), Val(b.lhs())))
+}
+
+fn double_neg(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    if b.op() != Sub || int(func, b.lhs()) != Some(0) {
+        return None;
+    }
+    is_neg(func, b.rhs()).map(|x| Rewrite::Operand(Val(x)))
+}
+
+fn add_neg(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    let op = match b.op() {
+        Add => Sub,
+        Sub => Add,
+        _ => return None,
+    };
+    // `0 - (0 - y)` 由 `double-neg` 处理
+    if int(func, b.lhs()) == Some(0) {
+        return None;
+    }
+    let y = is_neg(func, b.rhs())?;
+    Some(Rewrite::Binary(op, Val(b.lhs()), Val(y)))
+}
+
+fn reassoc(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    let op = b.op();
+    if !matches!(op, Add | Mul | And | Or | Xor) {
+        return None;
+    }
+    let c2 = int(func, b.rhs())?;
+    let inner = binary(func, b.lhs())?;
+    if inner.op() != op {
+        return None;
+    }
+    let c1 = int(func, inner.rhs())?;
+    let c = sccp::eval(op, c1, c2)?;
+    Some(Rewrite::Binary(op, Val(inner.lhs()), Const(c)))
+}
+
+fn bool_cmp(func: &FunctionData, b: &Binary) -> Option<Rewrite> {
+    if int(func, b.rhs())? != 0 {
+        return None;
+    }
+    let inner = binary(func, b.lhs()).filter(|i| is_comparison(i.op()))?;
+    match b.op() {
+        NotEq => Some(Rewrite::Operand(Val(b.lhs()))),
+        Eq => Some(Rewrite::Binary(inverted(inner.op()), Val(inner.lhs()), Val(inner.rhs()))),
+        _ => None,
+    }
+}
+
+/// 删除不再被使用的运算，如被重写后只剩下 `double-neg` 中的内层取反
+fn remove_unused(func: &mut FunctionData) -> bool {
+    let insts = edit::insts(func);
+    let used: HashSet<Value> = insts.iter().flat_map(|&v| func.dfg().value(v).kind().value_uses()).collect();
+    let dead: Vec<Value> = insts
+        .into_iter()
+        .filter(|v| !used.contains(v) && matches!(func.dfg().value(*v).kind(), ValueKind::Binary(_)))
+        .collect();
+    if dead.is_empty() {
+        return false;
+    }
+    edit::remove_insts(func, &dead);
+    true
+}
+
+fn operand(func: &mut FunctionData, op: Operand) -> Value {
+    match op {
+        Val(v) => v,
+        Const(c) => func.dfg_mut().new_value().integer(c),
+    }
+}
+
+fn apply(func: &mut FunctionData, inst: Value, rewrite: Rewrite) {
+    match rewrite {
+        Rewrite::Operand(op) => {
+            let v = operand(func, op);
+            edit::replace_uses(func, &HashMap::from([(inst, v)]));
+            edit::remove_insts(func, &[inst]);
+        }
+        Rewrite::Binary(op, lhs, rhs) => {
+            let (lhs, rhs) = (operand(func, lhs), operand(func, rhs));
+            edit::rewrite(func, inst, |kind| {
+                let ValueKind::Binary(b) = kind else { unreachable!() };
+                *b.op_mut() = op;
+                *b.lhs_mut() = lhs;
+                *b.rhs_mut() = rhs;
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RULES;
+    use crate::opt::run_text;
+
+    /// 每条规则的例子：函数体的前后两个版本，函数有参数 `@x` 与 `@y`
+    const CASES: &[(&str, &str, &str)] = &[
+        ("fold", "%0 = mul 6, 7\n ret %0", "ret 42"),
+        ("fold", "%0 = div 1, 0\n ret %0", "%0 = div 1, 0\n ret %0"),
+        ("commute", "%0 = add 3, @x\n ret %0", "%0 = add @x, 3\n ret %0"),
+        ("commute", "%0 = lt 3, @x\n ret %0", "%0 = gt @x, 3\n ret %0"),
+        ("sub-const", "%0 = sub @x, 3\n ret %0", "%0 = add @x, -3\n ret %0"),
+        ("identity", "%0 = mul @x, 1\n %1 = add %0, 0\n %2 = sar %1, 0\n ret %2", "ret @x"),
+        ("absorb", "%0 = mul @x, 0\n %1 = mod @y, 1\n %2 = add %0, %1\n ret %2", "ret 0"),
+        ("self", "%0 = sub @x, @x\n ret %0", "ret 0"),
+        ("self", "%0 = le @x, @x\n ret %0", "ret 1"),
+        ("self", "%0 = and @x, @x\n ret %0", "ret @x"),
+        ("neg", "%0 = mul @x, -1\n ret %0", "%0 = sub 0, @x\n ret %0"),
+        ("neg", "%0 = div @x, -1\n ret %0", "%0 = sub 0, @x\n ret %0"),
+        ("double-neg", "%0 = sub 0, @x\n %1 = sub 0, %0\n ret %1", "ret @x"),
+        ("add-neg", "%0 = sub 0, @y\n %1 = add @x, %0\n ret %1", "%0 = sub @x, @y\n ret %0"),
+        ("add-neg", "%0 = sub 0, @y\n %1 = sub @x, %0\n ret %1", "%0 = add @x, @y\n ret %0"),
+        ("reassoc", "%0 = add @x, 1\n %1 = add %0, 2\n ret %1", "%0 = add @x, 3\n ret %0"),
+        ("reassoc", "%0 = sub @x, 1\n %1 = sub %0, 2\n ret %1", "%0 = add @x, -3\n ret %0"),
+        ("bool-cmp", "%0 = lt @x, @y\n %1 = ne %0, 0\n ret %1", "%0 = lt @x, @y\n ret %0"),
+        ("bool-cmp", "%0 = lt @x, @y\n %1 = eq %0, 0\n ret %1", "%0 = ge @x, @y\n ret %0"),
+        // `a || b` 的前端翻译：`!a` 再与 0 比较
+        ("bool-cmp", "%0 = eq 0, @x\n %1 = eq %0, 0\n ret %1", "%0 = ne @x, 0\n ret %0"),
+    ];
+
+    fn function(body: &str) -> String {
+        format!("fun @f(@x: i32, @y: i32): i32 {{\n%entry:\n {}\n}}\n", body)
+    }
+
+    #[test]
+    fn rules() {
+        for (name, before, after) in CASES {
+            let actual = run_text(&run_text(&function(before), &["instcombine"]), &[]);
+            assert_eq!(actual, run_text(&function(after), &[]), "rule '{}'", name);
+        }
+        for rule in RULES {
+            assert!(CASES.iter().any(|(name, ..)| *name == rule.name), "rule '{}' has no test case", rule.name);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! 循环不变代码外提
+//!
+//! 先为每个循环插入前置块，再由内向外处理各个循环：操作数都在循环之外定义、或自身已外提的
+//! `Binary`、`getelemptr` 与 `getptr` 移到前置块末尾。RISC-V 的除法不会陷入，这些运算都可以提前执行。
+//!
+//! `load` 还须满足：循环中没有可能写入该地址的 `store`，也没有可能访问它的 `call`；
+//! 且读取不会出错，即地址为局部或全局变量中下标均为常量的位置，或 `load` 位于循环头中——
+//! 到达前置块后循环头一定执行，而循环体可能一次也不执行。
+//!
+//! 内层循环外提到其前置块中的指令，在处理外层循环时可以继续外提。
+
+use std::collections::HashSet;
+
+use koopa::ir::{BasicBlock, FunctionData, Value, ValueKind};
+
+use super::{
+    analysis::{
+        alias::{AliasInfo, Base, Index},
+        loops::{insert_preheaders, Loop},
+        Analyses,
+    },
+    edit, FunctionPass,
+};
+
+pub struct Licm;
+
+impl FunctionPass for Licm {
+    fn run(&mut self, func: &mut FunctionData, analyses: &mut Analyses) -> bool {
+        let mut changed = insert_preheaders(func, analyses);
+        let (cfg, loops) = (analyses.cfg(func), analyses.loops(func));
+        let alias = AliasInfo::new(func);
+        for l in loops.loops().iter().rev() {
+            let pre = l.preheader(&cfg).unwrap();
+            let order: Vec<BasicBlock> = cfg.rpo().iter().copied().filter(|bb| l.contains(*bb)).collect();
+            let hoisted = invariants(func, &alias, l, &order);
+            let term = *func.layout().bbs().node(&pre).unwrap().insts().back_key().unwrap();
+            for inst in hoisted {
+                let bb = func.layout().parent_bb(inst).unwrap();
+                func.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+                func.layout_mut().bb_mut(pre).insts_mut().cursor_mut(term).insert_key_before(inst).unwrap();
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// 循环中可以外提的指令，按外提后的顺序排列
+fn invariants(func: &FunctionData, alias: &AliasInfo, l: &Loop, order: &[BasicBlock]) -> Vec<Value> {
+    // 循环中定义的值
+    let mut defined: HashSet<Value> = HashSet::new();
+    let mut writes = vec![];
+    let mut has_call = false;
+    for &bb in order {
+        defined.extend(func.dfg().bb(bb).params());
+        for inst in edit::block_insts(func, bb) {
+            defined.insert(inst);
+            match func.dfg().value(inst).kind() {
+                ValueKind::Store(s) => writes.push(s.dest()),
+                ValueKind::Call(_) => has_call = true,
+                _ => {}
+            }
+        }
+    }
+
+    let mut hoisted = vec![];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &bb in order {
+            for inst in edit::block_insts(func, bb) {
+                if !defined.contains(&inst) {
+                    continue;
+                }
+                let kind = func.dfg().value(inst).kind();
+                let movable = match kind {
+                    ValueKind::Binary(_) | ValueKind::GetElemPtr(_) | ValueKind::GetPtr(_) => true,
+                    ValueKind::Load(ld) => {
+                        let src = ld.src();
+                        let written = writes.iter().any(|&w| alias.may_alias(func, w, src))
+                            || has_call && alias.call_may_access(func, src);
+                        !written && (bb == l.header || always_valid(func, alias, src))
+                    }
+                    _ => false,
+                };
+                if movable && kind.value_uses().all(|v| !defined.contains(&v)) {
+                    defined.remove(&inst);
+                    hoisted.push(inst);
+                    changed = true;
+                }
+            }
+        }
+    }
+    hoisted
+}
+
+/// 读取该地址是否一定不会出错：局部或全局变量中下标均为常量的位置
+fn always_valid(func: &FunctionData, alias: &AliasInfo, addr: Value) -> bool {
+    let (base, path) = alias.base(func, addr);
+    !matches!(base, Base::Unknown) && path.iter().all(|(_, index)| matches!(index, Index::Const(_)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn hoist() {
+        // `%0`、`%1` 及其地址计算外提；`@g` 在循环中被写入、`%2` 依赖循环变量，都不外提；
+        // `@h` 在循环中没有写入也没有调用，外提到插入的前置块
+        assert_passes(
+            &["licm"],
+            r#"
+global @g = alloc i32, zeroinit
+global @h = alloc i32, zeroinit
+global @m = alloc [[i32, 4], 4], zeroinit
+
+fun @f(@n: i32, @k: i32): i32 {
+%entry:
+  br @n, %cond(0, 0), %end
+%cond(%i: i32, %s: i32):
+  %c = lt %i, @n
+  br %c, %body, %end
+%body:
+  %0 = mul @n, @k
+  %p = getelemptr @m, 1
+  %q = getelemptr %p, 2
+  %1 = load %q
+  %x = load @h
+  %y = load @g
+  %2 = add %i, %0
+  %3 = add %2, %1
+  %4 = add %3, %x
+  store %4, @g
+  %5 = add %i, 1
+  %6 = add %s, %y
+  jump %cond(%5, %6)
+%end:
+  ret 0
+}
+"#,
+            r#"
+global @g = alloc i32, zeroinit
+global @h = alloc i32, zeroinit
+global @m = alloc [[i32, 4], 4], zeroinit
+
+fun @f(@n: i32, @k: i32): i32 {
+%entry:
+  br @n, %cond_preheader, %end
+%cond_preheader:
+  %0 = mul @n, @k
+  %p = getelemptr @m, 1
+  %q = getelemptr %p, 2
+  %1 = load %q
+  %x = load @h
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, @n
+  br %c, %body, %end
+%body:
+  %y = load @g
+  %2 = add %i, %0
+  %3 = add %2, %1
+  %4 = add %3, %x
+  store %4, @g
+  %5 = add %i, 1
+  %6 = add %s, %y
+  jump %cond(%5, %6)
+%end:
+  ret 0
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn calls_and_nesting() {
+        // 内层循环中的 `%0` 先外提到内层的前置块，再外提到外层的前置块；
+        // 有调用时，全局变量不外提，地址没有传出的局部变量 `%v` 可以外提
+        assert_passes(
+            &["licm"],
+            r#"
+global @g = alloc i32, zeroinit
+
+decl @putint(i32)
+
+fun @f(@n: i32) {
+%entry:
+  %v = alloc i32
+  store 7, %v
+  jump %outer(0)
+%outer(%i: i32):
+  %c = lt %i, @n
+  br %c, %inner, %end
+%inner:
+  %0 = mul @n, @n
+  %a = load %v
+  %b = load @g
+  %1 = add %0, %a
+  %2 = add %1, %b
+  call @putint(%2)
+  br %2, %inner, %latch
+%latch:
+  %3 = add %i, 1
+  jump %outer(%3)
+%end:
+  ret
+}
+"#,
+            r#"
+global @g = alloc i32, zeroinit
+
+decl @putint(i32)
+
+fun @f(@n: i32) {
+%entry:
+  %v = alloc i32
+  store 7, %v
+  %0 = mul @n, @n
+  %a = load %v
+  %1 = add %0, %a
+  jump %outer(0)
+%outer(%i: i32):
+  %c = lt %i, @n
+  br %c, %inner_preheader, %end
+%inner_preheader:
+  jump %inner
+%inner:
+  %b = load @g
+  %2 = add %1, %b
+  call @putint(%2)
+  br %2, %inner, %latch
+%latch:
+  %3 = add %i, 1
+  jump %outer(%3)
+%end:
+  ret
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn getptr_past_element() {
+        // 循环中写入的 `%w` 由 `@a[0]` 经 `getptr` 后移一个元素，与 `%r` 同为 `@a[1]`，`%x` 不外提；
+        // `%t` 只经过 `getelemptr`，落在 `@b[0]` 中，读取 `@b[1]` 中的 `%y` 外提
+        assert_passes(
+            &["licm"],
+            r#"
+global @a = alloc [i32, 2], zeroinit
+global @b = alloc [[i32, 2], 2], zeroinit
+
+fun @f(@n: i32): i32 {
+%entry:
+  %a0 = getelemptr @a, 0
+  %w = getptr %a0, 1
+  %r = getelemptr @a, 1
+  %b0 = getelemptr @b, 0
+  %t = getelemptr %b0, 1
+  %b1 = getelemptr @b, 1
+  %u = getelemptr %b1, 0
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, @n
+  br %c, %body, %end
+%body:
+  store %i, %w
+  store %i, %t
+  %x = load %r
+  %y = load %u
+  %0 = add %s, %x
+  %1 = add %0, %y
+  %2 = add %i, 1
+  jump %cond(%2, %1)
+%end:
+  ret %s
+}
+"#,
+            r#"
+global @a = alloc [i32, 2], zeroinit
+
+global @b = alloc [[i32, 2], 2], zeroinit
+
+fun @f(@n: i32): i32 {
+%entry:
+  %a0 = getelemptr @a, 0
+  %w = getptr %a0, 1
+  %r = getelemptr @a, 1
+  %b0 = getelemptr @b, 0
+  %t = getelemptr %b0, 1
+  %b1 = getelemptr @b, 1
+  %u = getelemptr %b1, 0
+  %y = load %u
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, @n
+  br %c, %body, %end
+%body:
+  store %i, %w
+  store %i, %t
+  %x = load %r
+  %0 = add %s, %x
+  %1 = add %0, %y
+  %2 = add %i, 1
+  jump %cond(%2, %1)
+%end:
+  ret %s
+}
+"#,
+        );
+    }
+}
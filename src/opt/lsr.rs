@@ -0,0 +1,269 @@
+//! 循环强度削减
+//!
+//! 循环中的地址 `getelemptr p, j`（或 `getptr p, j`）的 `p` 循环不变、`j` 为派生归纳变量 `c * i + k` 时，
+//! 每次循环地址恰好前进 `c * s` 个元素（`s` 为 `i` 的步长）。为循环头加入一个指针参数代替它：
+//! 前置块中按 `i` 的初值重新计算 `j` 与地址作为初值，`i` 的下一次的值之后以 `getptr` 前进 `c * s` 个元素，
+//! 由回边传入。原先每次循环的乘法、加法与地址计算于是不再使用；最后删除不再使用的值，
+//! 只在循环中传来传去、不再被读取的归纳变量也随之删除。
+//!
+//! 由内向外处理各个循环，内层循环前置块中的初值计算在外层循环中可以继续削减。
+
+use std::collections::HashMap;
+
+use koopa::ir::{builder_traits::*, BasicBlock, BinaryOp, FunctionData, Value, ValueKind};
+
+use super::{
+    analysis::{
+        cfg::Cfg,
+        indvars::{IndVars, Step},
+        loops::{insert_preheaders, Loop},
+        Analyses,
+    },
+    dce, edit, FunctionPass,
+};
+
+pub struct Lsr;
+
+impl FunctionPass for Lsr {
+    fn run(&mut self, func: &mut FunctionData, analyses: &mut Analyses) -> bool {
+        let changed = insert_preheaders(func, analyses);
+        let (cfg, loops) = (analyses.cfg(func), analyses.loops(func));
+        let mut reduced = false;
+        for l in loops.loops().iter().rev() {
+            reduced |= reduce(func, &cfg, l);
+        }
+        if reduced {
+            dce::remove_dead_values(func);
+        }
+        changed | reduced
+    }
+}
+
+/// 削减一个循环中的地址计算，返回是否有改动
+fn reduce(func: &mut FunctionData, cfg: &Cfg, l: &Loop) -> bool {
+    let ivs = IndVars::new(func, cfg, l);
+    let pre = l.preheader(cfg).unwrap();
+    let pre_term = *func.layout().bbs().node(&pre).unwrap().insts().back_key().unwrap();
+    let insts: Vec<Value> = cfg
+        .rpo()
+        .iter()
+        .filter(|bb| l.contains(**bb))
+        .flat_map(|&bb| edit::block_insts(func, bb))
+        .collect();
+
+    let mut memo = HashMap::new();
+    let mut map = HashMap::new();
+    for inst in insts {
+        let (src, index) = match func.dfg().value(inst).kind() {
+            ValueKind::GetElemPtr(g) => (g.src(), g.index()),
+            ValueKind::GetPtr(g) => (g.src(), g.index()),
+            _ => continue,
+        };
+        let Some(&d) = ivs.derived.get(&index) else {
+            continue;
+        };
+        if !ivs.is_invariant(src) || d.coef == 0 {
+            continue;
+        }
+        let basic = &ivs.basics[d.iv];
+
+        // 前置块中计算初值与步长
+        let start = materialize(func, &ivs, pre_term, index, &mut memo);
+        let zero = matches!(func.dfg().value(start).kind(), ValueKind::Integer(i) if i.value() == 0);
+        let start = match func.dfg().value(inst).kind() {
+            ValueKind::GetPtr(_) if zero => src,
+            ValueKind::GetElemPtr(_) => func.dfg_mut().new_value().get_elem_ptr(src, start),
+            _ => func.dfg_mut().new_value().get_ptr(src, start),
+        };
+        if start != src {
+            insert_before(func, pre_term, start);
+        }
+        let step = match basic.step {
+            Step::Const(s) => func.dfg_mut().new_value().integer(s.wrapping_mul(d.coef)),
+            Step::Value(s) if d.coef == 1 => s,
+            Step::Value(s) => {
+                let coef = func.dfg_mut().new_value().integer(d.coef);
+                let step = func.dfg_mut().new_value().binary(BinaryOp::Mul, s, coef);
+                insert_before(func, pre_term, step);
+                step
+            }
+        };
+
+        let ty = func.dfg().value(inst).ty().clone();
+        let ptr = edit::add_params(func, l.header, vec![ty])[0];
+        // 指针参数沿用被代替的地址的名字
+        let name = func.dfg().value(inst).name().clone();
+        func.dfg_mut().set_value_name(ptr, name);
+        let next = func.dfg_mut().new_value().get_ptr(ptr, step);
+        let bb = func.layout().parent_bb(basic.next).unwrap();
+        func.layout_mut().bb_mut(bb).insts_mut().cursor_mut(basic.next).insert_key_after(next).unwrap();
+        append_arg(func, pre, l.header, start);
+        for &latch in l.latches.iter() {
+            append_arg(func, latch, l.header, next);
+        }
+        map.insert(inst, ptr);
+    }
+    edit::replace_uses(func, &map);
+    !map.is_empty()
+}
+
+/// 在前置块中按基本归纳变量的初值重新计算派生归纳变量 `v`，操作数均为常量时直接求值
+fn materialize(
+    func: &mut FunctionData,
+    ivs: &IndVars,
+    pre_term: Value,
+    v: Value,
+    memo: &mut HashMap<Value, Value>,
+) -> Value {
+    if ivs.is_invariant(v) {
+        return v;
+    }
+    if let Some(&m) = memo.get(&v) {
+        return m;
+    }
+    let basic = &ivs.basics[ivs.derived[&v].iv];
+    let new = if v == basic.param {
+        basic.init
+    } else {
+        let ValueKind::Binary(b) = func.dfg().value(v).kind().clone() else {
+            unreachable!()
+        };
+        let lhs = materialize(func, ivs, pre_term, b.lhs(), memo);
+        let rhs = materialize(func, ivs, pre_term, b.rhs(), memo);
+        let constant = |v: Value| match func.dfg().value(v).kind() {
+            ValueKind::Integer(i) => Some(i.value()),
+            _ => None,
+        };
+        let folded = match (b.op(), constant(lhs), constant(rhs)) {
+            (BinaryOp::Add, Some(l), Some(r)) => Some(l.wrapping_add(r)),
+            (BinaryOp::Sub, Some(l), Some(r)) => Some(l.wrapping_sub(r)),
+            (BinaryOp::Mul, Some(l), Some(r)) => Some(l.wrapping_mul(r)),
+            (BinaryOp::Shl, Some(l), Some(r)) => Some(l.wrapping_shl(r as u32)),
+            _ => None,
+        };
+        match folded {
+            Some(i) => func.dfg_mut().new_value().integer(i),
+            None => {
+                let new = func.dfg_mut().new_value().binary(b.op(), lhs, rhs);
+                insert_before(func, pre_term, new);
+                new
+            }
+        }
+    };
+    memo.insert(v, new);
+    new
+}
+
+fn insert_before(func: &mut FunctionData, at: Value, inst: Value) {
+    let bb = func.layout().parent_bb(at).unwrap();
+    func.layout_mut().bb_mut(bb).insts_mut().cursor_mut(at).insert_key_before(inst).unwrap();
+}
+
+/// 在 `bb` 跳转到 `header` 的实参末尾加入 `arg`
+fn append_arg(func: &mut FunctionData, bb: BasicBlock, header: BasicBlock, arg: Value) {
+    let term = *func.layout().bbs().node(&bb).unwrap().insts().back_key().unwrap();
+    edit::rewrite(func, term, |kind| match kind {
+        ValueKind::Jump(j) => j.args_mut().push(arg),
+        ValueKind::Branch(b) => {
+            if b.true_bb() == header {
+                b.true_args_mut().push(arg);
+            }
+            if b.false_bb() == header {
+                b.false_args_mut().push(arg);
+            }
+        }
+        _ => unreachable!(),
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn pointer_induction() {
+        // `@sum` 中 `@a[2 * i + 1]` 改为每次前进 2 个元素的指针，初值在入口中求出；`%k` 依赖循环中读取的值，不变换。
+        // `@fill` 中的 `%j` 只用于计算地址，削减后不再使用而被删除；步长 `@s` 在循环之外定义，初值即为 `@p`
+        assert_passes(
+            &["lsr"],
+            r#"
+global @a = alloc [i32, 100], zeroinit
+
+fun @sum(@n: i32): i32 {
+%entry:
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, @n
+  br %c, %body, %end
+%body:
+  %0 = mul %i, 2
+  %1 = add %0, 1
+  %p = getelemptr @a, %1
+  %x = load %p
+  %k = add %i, %x
+  %q = getelemptr @a, %k
+  store 0, %q
+  %t = add %s, %x
+  %i1 = add %i, 1
+  jump %cond(%i1, %t)
+%end:
+  ret %s
+}
+
+fun @fill(@p: *i32, @n: i32, @s: i32) {
+%entry:
+  jump %cond(0, @n)
+%cond(%j: i32, %m: i32):
+  %c = gt %m, 0
+  br %c, %body, %end
+%body:
+  %q = getptr @p, %j
+  store %m, %q
+  %j1 = add %j, @s
+  %m1 = sub %m, 1
+  jump %cond(%j1, %m1)
+%end:
+  ret
+}
+"#,
+            r#"
+global @a = alloc [i32, 100], zeroinit
+
+fun @sum(@n: i32): i32 {
+%entry:
+  %0 = getelemptr @a, 1
+  jump %cond(0, 0, %0)
+%cond(%i: i32, %s: i32, %p: *i32):
+  %c = lt %i, @n
+  br %c, %body, %end
+%body:
+  %x = load %p
+  %k = add %i, %x
+  %q = getelemptr @a, %k
+  store 0, %q
+  %t = add %s, %x
+  %i1 = add %i, 1
+  %1 = getptr %p, 2
+  jump %cond(%i1, %t, %1)
+%end:
+  ret %s
+}
+
+fun @fill(@p: *i32, @n: i32, @s: i32) {
+%entry:
+  jump %cond(@n, @p)
+%cond(%m: i32, %q: *i32):
+  %c = gt %m, 0
+  br %c, %body, %end
+%body:
+  store %m, %q
+  %1 = getptr %q, @s
+  %m1 = sub %m, 1
+  jump %cond(%m1, %1)
+%end:
+  ret
+}
+"#,
+        );
+    }
+}
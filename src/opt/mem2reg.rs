@@ -0,0 +1,383 @@
+//! 将局部变量从内存提升为 SSA 值
+//!
+//! 只被 `load` 读取、被 `store` 写入的 `alloc i32` 可以提升。对每个这样的变量，
+//! 在写入它的基本块的迭代支配边界上、且变量在入口处活跃的基本块加入一个参数（剪枝的 SSA），
+//! 汇合处的值由前驱的跳转实参传入，而不是 phi。然后沿支配树重命名：`load` 替换为变量当前的值，
+//! `store` 更新当前的值，二者与 `alloc` 一同删除。未写入就读取得到 `undef`。
+//!
+//! 不可达的基本块不在支配树中，其中的读取一律视为 `undef`。
+
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use koopa::ir::{builder_traits::*, BasicBlock, FunctionData, Type, TypeKind, Value, ValueKind};
+
+use super::{
+    analysis::{cfg::Cfg, dom::DomTree, Analyses},
+    edit, FunctionPass,
+};
+
+pub struct Mem2Reg;
+
+impl FunctionPass for Mem2Reg {
+    fn run(&mut self, func: &mut FunctionData, analyses: &mut Analyses) -> bool {
+        let allocs = promotable(func);
+        if allocs.is_empty() {
+            return false;
+        }
+        Promote::new(func, analyses, allocs).run(func);
+        true
+    }
+
+    /// 只加入基本块参数与跳转的实参
+    fn preserves_cfg(&self) -> bool {
+        true
+    }
+}
+
+/// 可以提升的 `alloc`，按布局顺序排列
+fn promotable(func: &FunctionData) -> Vec<Value> {
+    let insts = edit::insts(func);
+    let mut candidates: HashSet<Value> = insts
+        .iter()
+        .copied()
+        .filter(|&v| {
+            let data = func.dfg().value(v);
+            matches!(data.kind(), ValueKind::Alloc(_))
+                && matches!(data.ty().kind(), TypeKind::Pointer(base) if base.is_i32())
+        })
+        .collect();
+    for &inst in insts.iter() {
+        let kind = func.dfg().value(inst).kind();
+        for operand in kind.value_uses() {
+            let ok = match kind {
+                ValueKind::Load(_) => true,
+                ValueKind::Store(s) => s.dest() == operand && s.value() != operand,
+                _ => false,
+            };
+            if !ok {
+                candidates.remove(&operand);
+            }
+        }
+    }
+    insts.into_iter().filter(|v| candidates.contains(v)).collect()
+}
+
+struct Promote {
+    allocs: Vec<Value>,
+    index: HashMap<Value, usize>,
+    cfg: Rc<Cfg>,
+    dom: Rc<DomTree>,
+    /// 加入的参数对应的变量编号及参数本身，按变量编号排列
+    params: HashMap<BasicBlock, Vec<(usize, Value)>>,
+    undef: Value,
+    /// 被删除的 `load` 到其值的映射
+    values: HashMap<Value, Value>,
+    /// 终结指令要追加的实参：`jump` 的或 `br` 的真、假两个目标的
+    args: HashMap<Value, (Vec<Value>, Vec<Value>)>,
+    removed: Vec<Value>,
+}
+
+impl Promote {
+    fn new(func: &mut FunctionData, analyses: &mut Analyses, allocs: Vec<Value>) -> Promote {
+        let index = allocs.iter().enumerate().map(|(i, &a)| (a, i)).collect();
+        let undef = func.dfg_mut().new_value().undef(Type::get_i32());
+        Promote {
+            allocs,
+            index,
+            cfg: analyses.cfg(func),
+            dom: analyses.dom(func),
+            params: HashMap::new(),
+            undef,
+            values: HashMap::new(),
+            args: HashMap::new(),
+            removed: vec![],
+        }
+    }
+
+    fn run(mut self, func: &mut FunctionData) {
+        self.place_params(func);
+
+        let mut stack = vec![(self.dom.entry(), vec![self.undef; self.allocs.len()])];
+        while let Some((bb, mut state)) = stack.pop() {
+            self.rename(func, bb, &mut state);
+            for &child in self.dom.children(bb) {
+                stack.push((child, state.clone()));
+            }
+        }
+        let unreachable: Vec<BasicBlock> =
+            func.layout().bbs().keys().copied().filter(|bb| !self.dom.is_reachable(*bb)).collect();
+        for bb in unreachable {
+            self.rename(func, bb, &mut vec![self.undef; self.allocs.len()]);
+        }
+
+        for (term, (first, second)) in self.args.iter() {
+            edit::rewrite(func, *term, |kind| match kind {
+                ValueKind::Jump(j) => j.args_mut().extend(first),
+                ValueKind::Branch(b) => {
+                    b.true_args_mut().extend(first);
+                    b.false_args_mut().extend(second);
+                }
+                _ => unreachable!(),
+            });
+        }
+        edit::replace_uses(func, &self.values);
+        // 先删除 `store` 和 `load`，`alloc` 才不再被使用
+        self.removed.extend(self.allocs.iter().copied());
+        edit::remove_insts(func, &self.removed);
+    }
+
+    /// 每个变量须加参数的基本块：写入处的迭代支配边界中变量活跃的那些
+    fn place_params(&mut self, func: &mut FunctionData) {
+        let frontiers = self.dom.frontiers(&self.cfg);
+        let mut placed: HashMap<BasicBlock, Vec<usize>> = HashMap::new();
+        for (i, &alloc) in self.allocs.iter().enumerate() {
+            let (defs, exposed) = self.accesses(func, alloc);
+            let live = self.live_in(&defs, exposed);
+            let mut work: Vec<BasicBlock> = defs.iter().copied().collect();
+            let mut visited: HashSet<BasicBlock> = defs;
+            while let Some(bb) = work.pop() {
+                for &y in frontiers.get(&bb).into_iter().flatten() {
+                    if live.contains(&y) && !placed.get(&y).is_some_and(|p| p.contains(&i)) {
+                        placed.entry(y).or_default().push(i);
+                        if visited.insert(y) {
+                            work.push(y);
+                        }
+                    }
+                }
+            }
+        }
+        // 按布局顺序创建参数，使结果确定
+        let bbs: Vec<BasicBlock> = func.layout().bbs().keys().copied().collect();
+        for bb in bbs {
+            if let Some(mut vars) = placed.remove(&bb) {
+                vars.sort();
+                let params = edit::add_params(func, bb, vec![Type::get_i32(); vars.len()]);
+                self.params.insert(bb, vars.into_iter().zip(params).collect());
+            }
+        }
+    }
+
+    /// 可达的基本块中写入变量的那些，以及在写入之前读取变量的那些
+    fn accesses(&self, func: &FunctionData, alloc: Value) -> (HashSet<BasicBlock>, Vec<BasicBlock>) {
+        let mut defs = HashSet::new();
+        let mut exposed = vec![];
+        for &bb in self.dom.rpo() {
+            let mut stored = false;
+            for inst in edit::block_insts(func, bb) {
+                match func.dfg().value(inst).kind() {
+                    ValueKind::Load(l) if l.src() == alloc && !stored && exposed.last() != Some(&bb) => {
+                        exposed.push(bb)
+                    }
+                    ValueKind::Store(s) if s.dest() == alloc => stored = true,
+                    _ => {}
+                }
+            }
+            if stored {
+                defs.insert(bb);
+            }
+        }
+        (defs, exposed)
+    }
+
+    /// 变量在入口处活跃的基本块：从读取处沿前驱反向传播，遇到写入变量的基本块为止
+    fn live_in(&self, defs: &HashSet<BasicBlock>, exposed: Vec<BasicBlock>) -> HashSet<BasicBlock> {
+        let mut live: HashSet<BasicBlock> = exposed.iter().copied().collect();
+        let mut work = exposed;
+        while let Some(bb) = work.pop() {
+            for &p in self.cfg.preds(bb) {
+                if self.dom.is_reachable(p) && !defs.contains(&p) && live.insert(p) {
+                    work.push(p);
+                }
+            }
+        }
+        live
+    }
+
+    fn rename(&mut self, func: &FunctionData, bb: BasicBlock, state: &mut [Value]) {
+        for &(i, param) in self.params.get(&bb).into_iter().flatten() {
+            state[i] = param;
+        }
+        for inst in edit::block_insts(func, bb) {
+            match func.dfg().value(inst).kind() {
+                ValueKind::Load(l) if self.index.contains_key(&l.src()) => {
+                    self.values.insert(inst, state[self.index[&l.src()]]);
+                    self.removed.push(inst);
+                }
+                ValueKind::Store(s) if self.index.contains_key(&s.dest()) => {
+                    let value = self.values.get(&s.value()).copied().unwrap_or(s.value());
+                    state[self.index[&s.dest()]] = value;
+                    self.removed.push(inst);
+                }
+                ValueKind::Jump(j) => {
+                    let args = self.args_for(j.target(), state);
+                    if !args.is_empty() {
+                        self.args.insert(inst, (args, vec![]));
+                    }
+                }
+                ValueKind::Branch(b) => {
+                    let (t, f) = (self.args_for(b.true_bb(), state), self.args_for(b.false_bb(), state));
+                    if !t.is_empty() || !f.is_empty() {
+                        self.args.insert(inst, (t, f));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn args_for(&self, target: BasicBlock, state: &[Value]) -> Vec<Value> {
+        self.params.get(&target).into_iter().flatten().map(|&(i, _)| state[i]).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn straight_line() {
+        assert_passes(
+            &["mem2reg"],
+            r#"
+fun @f(@a: i32): i32 {
+%entry:
+  %a = alloc i32
+  store @a, %a
+  %x = alloc i32
+  %0 = load %a
+  %1 = add %0, 1
+  store %1, %x
+  %2 = load %x
+  %3 = load %x
+  %4 = mul %2, %3
+  ret %4
+}
+"#,
+            r#"
+fun @f(@a: i32): i32 {
+%entry:
+  %0 = add @a, 1
+  %1 = mul %0, %0
+  ret %1
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn merge() {
+        assert_passes(
+            &["mem2reg"],
+            r#"
+fun @f(@c: i32): i32 {
+%entry:
+  %x = alloc i32
+  %y = alloc i32
+  store 1, %y
+  br @c, %then, %else
+%then:
+  store 2, %x
+  jump %end
+%else:
+  store 3, %x
+  jump %end
+%end:
+  %0 = load %x
+  %1 = load %y
+  %2 = add %0, %1
+  ret %2
+}
+"#,
+            r#"
+fun @f(@c: i32): i32 {
+%entry:
+  br @c, %then, %else
+%then:
+  jump %end(2)
+%else:
+  jump %end(3)
+%end(%0: i32):
+  %1 = add %0, 1
+  ret %1
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn loop_and_undef() {
+        // 循环头需要 `i` 与 `s` 的参数；`t` 只在循环体内先写后读，无需参数；`u` 未写入就读取
+        assert_passes(
+            &["mem2reg"],
+            r#"
+fun @f(@n: i32): i32 {
+%entry:
+  %i = alloc i32
+  %s = alloc i32
+  %t = alloc i32
+  %u = alloc i32
+  store 0, %i
+  store 0, %s
+  jump %cond
+%cond:
+  %0 = load %i
+  %1 = lt %0, @n
+  br %1, %body, %end
+%body:
+  %2 = load %i
+  store %2, %t
+  %3 = load %s
+  %4 = load %t
+  %5 = add %3, %4
+  store %5, %s
+  %6 = add %2, 1
+  store %6, %i
+  jump %cond
+%end:
+  %7 = load %s
+  %8 = load %u
+  %9 = add %7, %8
+  ret %9
+}
+"#,
+            r#"
+fun @f(@n: i32): i32 {
+%entry:
+  jump %cond(0, 0)
+%cond(%0: i32, %1: i32):
+  %2 = lt %0, @n
+  br %2, %body, %end
+%body:
+  %3 = add %1, %0
+  %4 = add %0, 1
+  jump %cond(%4, %3)
+%end:
+  %5 = add %1, undef
+  ret %5
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn escaping() {
+        // 数组和被取地址（作为 `store` 的值）的变量不提升
+        let source = r#"
+fun @f(): i32 {
+%entry:
+  %a = alloc [i32, 2]
+  %p = alloc i32
+  %q = alloc *i32
+  store %p, %q
+  %0 = getelemptr %a, 0
+  store 1, %0
+  %1 = load %0
+  ret %1
+}
+"#;
+        assert_passes(&["mem2reg"], source, source);
+    }
+}
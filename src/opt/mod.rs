@@ -0,0 +1,332 @@
+//! 中端优化：Koopa IR 上的变换
+//!
+//! 变换（pass）分为逐个函数进行的 [`FunctionPass`] 和作用于整个程序的 [`ModulePass`]，
+//! 都在 [`PASSES`] 中按名字登记。[`PassManager`] 按顺序执行一列变换：
+//! 命令行给出 `--passes=a,b,c` 时即为这些变换，否则为 `-O` 级别对应的预设序列（[`OptLevel::pipeline`]）。
+//! 调试构建或给出 `--verify` 时，每个变换之后都用 [`verify`] 检查 IR，出错时指出是哪个变换。
+//! 各函数的控制流分析（[`analysis`]）由 [`PassManager`] 缓存，变换改动了控制流图后丢弃。
+
+use std::{collections::HashMap, fmt};
+
+use koopa::ir::{Function, FunctionData, Program};
+
+use crate::util::verify;
+
+use analysis::Analyses;
+
+pub mod analysis;
+pub mod edit;
+pub mod instcombine;
+mod dce;
+mod gvn;
+mod inline;
+mod licm;
+mod lsr;
+mod mem2reg;
+mod sccp;
+mod simplify_cfg;
+mod tre;
+mod unroll;
+
+pub trait FunctionPass {
+    /// 变换一个有函数体的函数，返回是否有改动；`analyses` 为该函数的分析结果的缓存
+    fn run(&mut self, func: &mut FunctionData, analyses: &mut Analyses) -> bool;
+
+    /// 改动是否保持控制流图不变；若是，改动后不必丢弃分析结果
+    fn preserves_cfg(&self) -> bool {
+        false
+    }
+}
+
+pub trait ModulePass {
+    /// 变换整个程序，返回是否有改动
+    fn run(&mut self, program: &mut Program) -> bool;
+}
+
+pub enum Pass {
+    Function(Box<dyn FunctionPass>),
+    Module(Box<dyn ModulePass>),
+}
+
+pub struct PassInfo {
+    pub name: &'static str,
+    pub desc: &'static str,
+    pub create: fn() -> Pass,
+}
+
+pub const PASSES: &[PassInfo] = &[
+    PassInfo {
+        name: "verify",
+        desc: "check the IR and stop on malformed code",
+        create: || Pass::Module(Box::new(Verify)),
+    },
+    PassInfo {
+        name: "mem2reg",
+        desc: "promote scalar allocs to SSA values with block parameters",
+        create: || Pass::Function(Box::new(mem2reg::Mem2Reg)),
+    },
+    PassInfo {
+        name: "dce",
+        desc: "remove unreachable blocks, dead stores and values without uses",
+        create: || Pass::Function(Box::new(dce::Dce)),
+    },
+    PassInfo {
+        name: "simplify-cfg",
+        desc: "fold constant branches, thread jumps and merge trivial blocks",
+        create: || Pass::Function(Box::new(simplify_cfg::SimplifyCfg)),
+    },
+    PassInfo {
+        name: "sccp",
+        desc: "propagate constants along executable paths and prune dead branches",
+        create: || Pass::Function(Box::new(sccp::Sccp)),
+    },
+    PassInfo {
+        name: "instcombine",
+        desc: "simplify algebraic identities and move constants to the right",
+        create: || Pass::Function(Box::new(instcombine::InstCombine)),
+    },
+    PassInfo {
+        name: "gvn",
+        desc: "reuse values of repeated pure expressions and loads along the dominator tree",
+        create: || Pass::Function(Box::new(gvn::Gvn)),
+    },
+    PassInfo {
+        name: "licm",
+        desc: "hoist loop-invariant computations and loads into loop preheaders",
+        create: || Pass::Function(Box::new(licm::Licm)),
+    },
+    PassInfo {
+        name: "lsr",
+        desc: "replace addresses computed from induction variables with pointers advanced each iteration",
+        create: || Pass::Function(Box::new(lsr::Lsr)),
+    },
+    PassInfo {
+        name: "unroll",
+        desc: "unroll small counted loops fully, or partially with a remainder loop",
+        create: || Pass::Function(Box::new(unroll::Unroll)),
+    },
+    PassInfo {
+        name: "inline",
+        desc: "inline small and single-use non-recursive functions into their callers",
+        create: || Pass::Module(Box::new(inline::Inline)),
+    },
+    PassInfo {
+        name: "tre",
+        desc: "turn self tail calls into jumps back to the function entry",
+        create: || Pass::Module(Box::new(tre::Tre)),
+    },
+];
+
+/// `-O0`、`-O1`、`-O2`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OptLevel {
+    #[default]
+    O0,
+    O1,
+    O2,
+}
+
+impl OptLevel {
+    pub fn new(level: &str) -> OptLevel {
+        match level {
+            "0" => OptLevel::O0,
+            "1" => OptLevel::O1,
+            "2" => OptLevel::O2,
+            _ => panic!("Unknown optimization level '-O{}', expected one of -O0, -O1, -O2.", level),
+        }
+    }
+
+    /// 该级别执行的变换，按顺序排列
+    pub fn pipeline(self) -> &'static [&'static str] {
+        match self {
+            OptLevel::O0 => &[],
+            OptLevel::O1 => &["simplify-cfg", "mem2reg", "instcombine", "dce", "simplify-cfg"],
+            OptLevel::O2 => &[
+                "inline",
+                "tre",
+                "simplify-cfg",
+                "mem2reg",
+                "sccp",
+                "instcombine",
+                "gvn",
+                "licm",
+                "unroll",
+                "sccp",
+                "instcombine",
+                "gvn",
+                "lsr",
+                "dce",
+                "simplify-cfg",
+            ],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PassError {
+    UnknownPass(String),
+    /// 某个变换之后 IR 不合法
+    Malformed(&'static str, Vec<verify::Issue>),
+}
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassError::UnknownPass(name) => {
+                let names: Vec<_> = PASSES.iter().map(|p| p.name).collect();
+                write!(f, "unknown pass '{}', expected one of {}", name, names.join(", "))
+            }
+            PassError::Malformed(pass, issues) => {
+                write!(f, "Koopa IR is malformed after pass '{}':", pass)?;
+                for issue in issues {
+                    write!(f, "\n{}", issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for PassError {}
+
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<(&'static str, Pass)>,
+    /// 每个函数的分析结果
+    analyses: HashMap<Function, Analyses>,
+    /// 每个变换之后是否检查 IR
+    pub verify: bool,
+}
+
+impl PassManager {
+    pub fn new() -> PassManager {
+        PassManager::default()
+    }
+
+    pub fn with_level(level: OptLevel) -> PassManager {
+        PassManager::with_names(level.pipeline()).unwrap()
+    }
+
+    pub fn with_names<S: AsRef<str>>(names: &[S]) -> Result<PassManager, PassError> {
+        let mut pm = PassManager::new();
+        for name in names {
+            pm.add(name.as_ref())?;
+        }
+        Ok(pm)
+    }
+
+    /// 在序列末尾加入名为 `name` 的变换
+    pub fn add(&mut self, name: &str) -> Result<(), PassError> {
+        let info = PASSES
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| PassError::UnknownPass(name.to_string()))?;
+        self.passes.push((info.name, (info.create)()));
+        Ok(())
+    }
+
+    /// 依次执行所有变换，返回是否有改动
+    pub fn run(&mut self, program: &mut Program) -> Result<bool, PassError> {
+        let mut changed = false;
+        for (name, pass) in self.passes.iter_mut() {
+            changed |= match pass {
+                Pass::Function(pass) => {
+                    let funcs: Vec<_> = program.func_layout().to_vec();
+                    let mut changed = false;
+                    for f in funcs {
+                        let func = program.func_mut(f);
+                        if func.layout().entry_bb().is_none() {
+                            continue;
+                        }
+                        let analyses = self.analyses.entry(f).or_default();
+                        if pass.run(func, analyses) {
+                            if !pass.preserves_cfg() {
+                                analyses.invalidate();
+                            }
+                            changed = true;
+                        }
+                    }
+                    changed
+                }
+                Pass::Module(pass) => {
+                    let changed = pass.run(program);
+                    if changed {
+                        self.analyses.clear();
+                    }
+                    changed
+                }
+            };
+            if self.verify {
+                let errors: Vec<_> = verify::verify(program).into_iter().filter(|i| i.error).collect();
+                if !errors.is_empty() {
+                    return Err(PassError::Malformed(name, errors));
+                }
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// 显式的检查，不论 [`PassManager::verify`] 如何；有错误时中止
+struct Verify;
+
+impl ModulePass for Verify {
+    fn run(&mut self, program: &mut Program) -> bool {
+        let errors: Vec<String> = verify::verify(program)
+            .into_iter()
+            .filter(|i| i.error)
+            .map(|i| i.to_string())
+            .collect();
+        assert!(errors.is_empty(), "Koopa IR is malformed:\n{}", errors.join("\n"));
+        false
+    }
+}
+
+/// 解析 Koopa 文本，执行给出的变换，再输出为文本；供各个变换的单元测试使用
+#[cfg(test)]
+pub(crate) fn run_text(source: &str, passes: &[&str]) -> String {
+    use koopa::{back::KoopaGenerator, front::Driver};
+
+    let mut program = Driver::from(source).generate_program().expect("invalid Koopa text");
+    let mut pm = PassManager::with_names(passes).unwrap();
+    pm.verify = true;
+    pm.run(&mut program).unwrap_or_else(|e| panic!("{}", e));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    String::from_utf8(gen.writer()).unwrap()
+}
+
+/// 断言对 `source` 执行 `passes` 后与 `expected` 相同；两者都经过解析再输出，不比较空白和值的编号
+///
+/// 解析时基本块按广度优先的顺序排列，变换的结果也重新解析一次，插入的基本块的位置不影响比较。
+#[cfg(test)]
+pub(crate) fn assert_passes(passes: &[&str], source: &str, expected: &str) {
+    assert_eq!(run_text(&run_text(source, passes), &[]), run_text(expected, &[]));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = r#"
+decl @putint(i32)
+
+fun @main(): i32 {
+%entry:
+  %0 = add 1, 2
+  call @putint(%0)
+  ret 0
+}
+"#;
+
+    #[test]
+    fn registry() {
+        assert_passes(&["verify"], SOURCE, SOURCE);
+        assert!(matches!(PassManager::with_names(&["verify", "nope"]), Err(PassError::UnknownPass(name)) if name == "nope"));
+        for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+            for name in level.pipeline() {
+                assert!(PASSES.iter().any(|p| p.name == *name), "'{}' is not registered", name);
+            }
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! 稀疏条件常量传播
+//!
+//! 按 Wegman 与 Zadeck 的算法，每个值取格上的一点：尚未确定（`Top`）、常量、或不是常量（`Bottom`），只会下降。
+//! 从入口出发，只有可能执行的基本块中的指令才被求值；`br` 的条件为常量时只有一个目标可能执行。
+//! 基本块参数的值是所有可能执行的前驱传来的实参的交汇。值改变时，重新求值使用它的指令。
+//!
+//! 没有经过 mem2reg 时，只被写入一次的局部变量的 `load` 取写入的值：在写入之前读取得到的是未定义的值，
+//! 取同一个值也无妨。
+//!
+//! 结束后，值为常量的运算、`load` 与基本块参数替换为 `Integer`，条件为常量的 `br` 改为 `jump`，
+//! 不可能执行的基本块被删除。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{builder_traits::*, BasicBlock, BinaryOp, FunctionData, TypeKind, Value, ValueKind};
+
+use super::{analysis::Analyses, dce, edit, simplify_cfg, FunctionPass};
+
+pub struct Sccp;
+
+impl FunctionPass for Sccp {
+    fn run(&mut self, func: &mut FunctionData, _: &mut Analyses) -> bool {
+        let mut solver = Solver::new(func);
+        solver.solve();
+        let consts = solver.constants();
+        let mut changed = fold(func, consts);
+        changed |= simplify_cfg::fold_branches(func);
+        // 不可能执行的基本块在 `br` 改为 `jump` 后不可达
+        changed | dce::remove_unreachable(func)
+    }
+}
+
+/// 对两个 `i32` 常量进行运算；除数为零时结果不确定，不是常量
+pub(super) fn eval(op: BinaryOp, x: i32, y: i32) -> Option<i32> {
+    use BinaryOp::*;
+    Some(match op {
+        NotEq => (x != y) as i32,
+        Eq => (x == y) as i32,
+        Gt => (x > y) as i32,
+        Lt => (x < y) as i32,
+        Ge => (x >= y) as i32,
+        Le => (x <= y) as i32,
+        Add => x.wrapping_add(y),
+        Sub => x.wrapping_sub(y),
+        Mul => x.wrapping_mul(y),
+        Div if y == 0 => return None,
+        Div => x.wrapping_div(y),
+        Mod if y == 0 => return None,
+        Mod => x.wrapping_rem(y),
+        And => x & y,
+        Or => x | y,
+        Xor => x ^ y,
+        // 与 RISC-V 一致，移位量只取低 5 位
+        Shl => x.wrapping_shl(y as u32),
+        Shr => (x as u32).wrapping_shr(y as u32) as i32,
+        Sar => x.wrapping_shr(y as u32),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lattice {
+    Top,
+    Const(i32),
+    Bottom,
+}
+
+impl Lattice {
+    fn meet(self, other: Lattice) -> Lattice {
+        match (self, other) {
+            (Lattice::Top, x) | (x, Lattice::Top) => x,
+            (Lattice::Const(a), Lattice::Const(b)) if a == b => self,
+            _ => Lattice::Bottom,
+        }
+    }
+}
+
+struct Solver<'a> {
+    func: &'a FunctionData,
+    values: HashMap<Value, Lattice>,
+    /// 使用每个值的指令，只被写入一次的局部变量的 `load` 算作使用写入的值
+    users: HashMap<Value, Vec<Value>>,
+    /// 只被写入一次的局部变量，及写入的值
+    stored: HashMap<Value, Value>,
+    executable: HashSet<BasicBlock>,
+    blocks: Vec<BasicBlock>,
+    changed: Vec<Value>,
+}
+
+impl<'a> Solver<'a> {
+    fn new(func: &'a FunctionData) -> Solver<'a> {
+        let insts = edit::insts(func);
+        let stored = single_stores(func, &insts);
+        let mut users: HashMap<Value, Vec<Value>> = HashMap::new();
+        for &inst in insts.iter() {
+            let kind = func.dfg().value(inst).kind();
+            for u in kind.value_uses() {
+                users.entry(u).or_default().push(inst);
+            }
+            if let ValueKind::Load(l) = kind {
+                if let Some(&v) = stored.get(&l.src()) {
+                    users.entry(v).or_default().push(inst);
+                }
+            }
+        }
+        let entry = func.layout().entry_bb().unwrap();
+        Solver {
+            func,
+            values: HashMap::new(),
+            users,
+            stored,
+            executable: HashSet::from([entry]),
+            blocks: vec![entry],
+            changed: vec![],
+        }
+    }
+
+    fn get(&self, v: Value) -> Lattice {
+        if v.is_global() {
+            return Lattice::Bottom;
+        }
+        match self.func.dfg().value(v).kind() {
+            ValueKind::Integer(i) => Lattice::Const(i.value()),
+            ValueKind::Binary(_) | ValueKind::Load(_) | ValueKind::BlockArgRef(_) => {
+                self.values.get(&v).copied().unwrap_or(Lattice::Top)
+            }
+            _ => Lattice::Bottom,
+        }
+    }
+
+    /// 将值与 `new` 交汇
+    fn lower(&mut self, v: Value, new: Lattice) {
+        let old = self.get(v);
+        let new = old.meet(new);
+        if new != old {
+            self.values.insert(v, new);
+            self.changed.push(v);
+        }
+    }
+
+    fn solve(&mut self) {
+        loop {
+            if let Some(bb) = self.blocks.pop() {
+                for inst in edit::block_insts(self.func, bb) {
+                    self.visit(inst);
+                }
+            } else if let Some(v) = self.changed.pop() {
+                for user in self.users.get(&v).cloned().unwrap_or_default() {
+                    let bb = self.func.layout().parent_bb(user).unwrap();
+                    if self.executable.contains(&bb) {
+                        self.visit(user);
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn visit(&mut self, inst: Value) {
+        match self.func.dfg().value(inst).kind() {
+            ValueKind::Binary(b) => {
+                let value = match (self.get(b.lhs()), self.get(b.rhs())) {
+                    (Lattice::Const(x), Lattice::Const(y)) => eval(b.op(), x, y).map_or(Lattice::Bottom, Lattice::Const),
+                    (Lattice::Bottom, _) | (_, Lattice::Bottom) => Lattice::Bottom,
+                    _ => Lattice::Top,
+                };
+                self.lower(inst, value);
+            }
+            ValueKind::Load(l) => {
+                let value = self.stored.get(&l.src()).map_or(Lattice::Bottom, |&v| self.get(v));
+                self.lower(inst, value);
+            }
+            ValueKind::Jump(j) => self.flow(j.target(), j.args()),
+            ValueKind::Branch(b) => {
+                let (taken, not_taken) = match self.get(b.cond()) {
+                    Lattice::Top => (false, false),
+                    Lattice::Const(0) => (false, true),
+                    Lattice::Const(_) => (true, false),
+                    Lattice::Bottom => (true, true),
+                };
+                if taken {
+                    self.flow(b.true_bb(), b.true_args());
+                }
+                if not_taken {
+                    self.flow(b.false_bb(), b.false_args());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 到 `target` 的边可能执行
+    fn flow(&mut self, target: BasicBlock, args: &[Value]) {
+        for (&param, &arg) in self.func.dfg().bb(target).params().iter().zip(args) {
+            let value = self.get(arg);
+            self.lower(param, value);
+        }
+        if self.executable.insert(target) {
+            self.blocks.push(target);
+        }
+    }
+
+    /// 值为常量的运算、`load` 与可能执行的基本块的参数
+    fn constants(&self) -> Vec<(Value, i32)> {
+        let mut consts = vec![];
+        for &bb in self.func.layout().bbs().keys().filter(|bb| self.executable.contains(bb)) {
+            let values = self.func.dfg().bb(bb).params().iter().copied().chain(edit::block_insts(self.func, bb));
+            for v in values {
+                if let Lattice::Const(c) = self.get(v) {
+                    consts.push((v, c));
+                }
+            }
+        }
+        consts
+    }
+}
+
+/// 只被写入一次、此外只被 `load` 读取的 `i32` 局部变量，及写入的值
+fn single_stores(func: &FunctionData, insts: &[Value]) -> HashMap<Value, Value> {
+    let mut stores: HashMap<Value, Vec<Value>> = insts
+        .iter()
+        .copied()
+        .filter(|&v| {
+            let data = func.dfg().value(v);
+            matches!(data.kind(), ValueKind::Alloc(_))
+                && matches!(data.ty().kind(), TypeKind::Pointer(base) if base.is_i32())
+        })
+        .map(|v| (v, vec![]))
+        .collect();
+    for &inst in insts {
+        let kind = func.dfg().value(inst).kind();
+        for operand in kind.value_uses() {
+            match kind {
+                ValueKind::Load(_) => {}
+                ValueKind::Store(s) if s.dest() == operand && s.value() != operand => {
+                    if let Some(list) = stores.get_mut(&operand) {
+                        list.push(s.value());
+                    }
+                }
+                _ => {
+                    stores.remove(&operand);
+                }
+            }
+        }
+    }
+    stores.into_iter().filter_map(|(alloc, values)| (values.len() == 1).then(|| (alloc, values[0]))).collect()
+}
+
+/// 将常量的使用替换为 `Integer`，删除不再需要的运算与 `load`
+fn fold(func: &mut FunctionData, consts: Vec<(Value, i32)>) -> bool {
+    if consts.is_empty() {
+        return false;
+    }
+    let map: HashMap<Value, Value> =
+        consts.iter().map(|&(v, c)| (v, func.dfg_mut().new_value().integer(c))).collect();
+    edit::replace_uses(func, &map);
+    let dead: Vec<Value> = consts
+        .into_iter()
+        .map(|(v, _)| v)
+        .filter(|&v| !matches!(func.dfg().value(v).kind(), ValueKind::BlockArgRef(_)))
+        .collect();
+    edit::remove_insts(func, &dead);
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn through_variables() {
+        // 没有 mem2reg：`%n` 只被写入一次；除数为零时不折叠
+        assert_passes(
+            &["sccp"],
+            r#"
+fun @f(): i32 {
+%entry:
+  %n = alloc i32
+  store 10, %n
+  %0 = load %n
+  %1 = mul %0, %0
+  %2 = sub %1, 100
+  %3 = div %0, %2
+  ret %3
+}
+"#,
+            r#"
+fun @f(): i32 {
+%entry:
+  %n = alloc i32
+  store 10, %n
+  %0 = div 10, 0
+  ret %0
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn conditional() {
+        // `%x` 在循环中保持为 1，因为给它赋其他值的 `%other` 从不执行
+        assert_passes(
+            &["sccp"],
+            r#"
+fun @f(@n: i32): i32 {
+%entry:
+  jump %cond(0, 1)
+%cond(%i: i32, %x: i32):
+  %0 = lt %i, @n
+  br %0, %body, %end
+%body:
+  %1 = eq %x, 1
+  br %1, %same, %other
+%same:
+  %2 = add %i, 1
+  jump %cond(%2, %x)
+%other:
+  jump %cond(%i, 2)
+%end:
+  ret %x
+}
+"#,
+            r#"
+fun @f(@n: i32): i32 {
+%entry:
+  jump %cond(0, 1)
+%cond(%i: i32, %x: i32):
+  %0 = lt %i, @n
+  br %0, %body, %end
+%body:
+  jump %same
+%same:
+  %1 = add %i, 1
+  jump %cond(%1, 1)
+%end:
+  ret 1
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! 化简控制流图
+//!
+//! 反复进行以下变换，直到不再有改动：
+//! - 条件为常量、或两个目标相同的 `br` 改为 `jump`；
+//! - 删除从入口不可达的基本块；
+//! - 只含一条 `jump` 的无参数基本块（如没有 `else` 的 `if` 留下的基本块）不再经过：
+//!   跳转到它的指令直接跳转到它的目标。`jump` 的实参在它的支配者中定义，也在这些前驱的支配者中定义；
+//! - 只有一个前驱、且前驱以 `jump` 结尾的基本块并入前驱，参数替换为前驱传来的实参。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{builder_traits::*, BasicBlock, FunctionData, Value, ValueKind};
+
+use crate::util::cfg;
+
+use super::{analysis::Analyses, dce, edit, FunctionPass};
+
+pub struct SimplifyCfg;
+
+impl FunctionPass for SimplifyCfg {
+    fn run(&mut self, func: &mut FunctionData, _: &mut Analyses) -> bool {
+        let mut changed = false;
+        loop {
+            let mut round = fold_branches(func);
+            round |= dce::remove_unreachable(func);
+            round |= thread_jumps(func);
+            round |= dce::remove_unreachable(func);
+            round |= merge_blocks(func);
+            if !round {
+                return changed;
+            }
+            changed = true;
+        }
+    }
+}
+
+fn terminator(func: &FunctionData, bb: BasicBlock) -> Option<Value> {
+    func.layout().bbs().node(&bb).unwrap().insts().back_key().copied()
+}
+
+/// 条件为常量或两个目标相同的 `br` 改为 `jump`
+pub(super) fn fold_branches(func: &mut FunctionData) -> bool {
+    let mut changed = false;
+    let bbs: Vec<BasicBlock> = func.layout().bbs().keys().copied().collect();
+    for bb in bbs {
+        let Some(term) = terminator(func, bb) else { continue };
+        let ValueKind::Branch(br) = func.dfg().value(term).kind() else { continue };
+        let (target, args) = match func.dfg().value(br.cond()).kind() {
+            _ if br.true_bb() == br.false_bb() && br.true_args() == br.false_args() => {
+                (br.true_bb(), br.true_args())
+            }
+            ValueKind::Integer(i) if i.value() != 0 => (br.true_bb(), br.true_args()),
+            ValueKind::Integer(_) => (br.false_bb(), br.false_args()),
+            _ => continue,
+        };
+        let args = args.to_vec();
+        func.dfg_mut().replace_value_with(term).jump_with_args(target, args);
+        changed = true;
+    }
+    changed
+}
+
+/// 只含一条 `jump` 的无参数基本块最终跳转到的目标，以及跳转的实参；成环时为 `None`
+fn forward_target(func: &FunctionData, forwarders: &HashSet<BasicBlock>, bb: BasicBlock) -> Option<(BasicBlock, Vec<Value>)> {
+    let mut visited = HashSet::from([bb]);
+    let mut cur = bb;
+    loop {
+        let ValueKind::Jump(j) = func.dfg().value(terminator(func, cur).unwrap()).kind() else { unreachable!() };
+        if !forwarders.contains(&j.target()) || !j.args().is_empty() {
+            return Some((j.target(), j.args().to_vec()));
+        }
+        if !visited.insert(j.target()) {
+            return None;
+        }
+        cur = j.target();
+    }
+}
+
+fn thread_jumps(func: &mut FunctionData) -> bool {
+    let entry = func.layout().entry_bb().unwrap();
+    let forwarders: HashSet<BasicBlock> = func
+        .layout()
+        .bbs()
+        .iter()
+        .filter(|(&bb, node)| {
+            bb != entry
+                && func.dfg().bb(bb).params().is_empty()
+                && node.insts().len() == 1
+                && matches!(func.dfg().value(*node.insts().front_key().unwrap()).kind(), ValueKind::Jump(_))
+        })
+        .map(|(&bb, _)| bb)
+        .collect();
+    let targets: HashMap<BasicBlock, (BasicBlock, Vec<Value>)> = forwarders
+        .iter()
+        .filter_map(|&bb| forward_target(func, &forwarders, bb).map(|t| (bb, t)))
+        .collect();
+    if targets.is_empty() {
+        return false;
+    }
+
+    let mut changed = false;
+    let bbs: Vec<BasicBlock> = func.layout().bbs().keys().copied().collect();
+    for bb in bbs {
+        let Some(term) = terminator(func, bb) else { continue };
+        let redirect = |target: &mut BasicBlock, args: &mut Vec<Value>| {
+            if let Some((to, to_args)) = targets.get(target) {
+                *target = *to;
+                *args = to_args.clone();
+            }
+        };
+        let threaded = match func.dfg().value(term).kind() {
+            ValueKind::Jump(j) => targets.contains_key(&j.target()),
+            ValueKind::Branch(b) => targets.contains_key(&b.true_bb()) || targets.contains_key(&b.false_bb()),
+            _ => false,
+        };
+        if threaded {
+            edit::rewrite(func, term, |kind| match kind {
+                ValueKind::Jump(j) => {
+                    let (mut target, mut args) = (j.target(), j.args().to_vec());
+                    redirect(&mut target, &mut args);
+                    *j.target_mut() = target;
+                    *j.args_mut() = args;
+                }
+                ValueKind::Branch(b) => {
+                    let (mut target, mut args) = (b.true_bb(), b.true_args().to_vec());
+                    redirect(&mut target, &mut args);
+                    *b.true_bb_mut() = target;
+                    *b.true_args_mut() = args;
+                    let (mut target, mut args) = (b.false_bb(), b.false_args().to_vec());
+                    redirect(&mut target, &mut args);
+                    *b.false_bb_mut() = target;
+                    *b.false_args_mut() = args;
+                }
+                _ => unreachable!(),
+            });
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn merge_blocks(func: &mut FunctionData) -> bool {
+    let mut changed = false;
+    let entry = func.layout().entry_bb().unwrap();
+    let mut preds = cfg::predecessors(func);
+    let bbs: Vec<BasicBlock> = func.layout().bbs().keys().copied().collect();
+    for bb in bbs {
+        let [pred] = preds[&bb][..] else { continue };
+        if bb == entry || pred == bb {
+            continue;
+        }
+        let term = terminator(func, pred).unwrap();
+        let ValueKind::Jump(j) = func.dfg().value(term).kind() else { continue };
+        let args = j.args().to_vec();
+        let params = std::mem::take(func.dfg_mut().bb_mut(bb).params_mut());
+        let map: HashMap<Value, Value> = params.iter().copied().zip(args).collect();
+
+        edit::remove_insts(func, &[term]);
+        for inst in edit::block_insts(func, bb) {
+            func.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+            func.layout_mut().bb_mut(pred).insts_mut().push_key_back(inst).unwrap();
+        }
+        edit::replace_uses(func, &map);
+        edit::remove_insts(func, &params);
+        edit::remove_bbs(func, &[bb]);
+
+        // 原来以 `bb` 为前驱的基本块，现在以 `pred` 为前驱
+        for list in preds.values_mut() {
+            for p in list.iter_mut().filter(|p| **p == bb) {
+                *p = pred;
+            }
+        }
+        changed = true;
+    }
+    changed
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn merge_and_thread() {
+        // 没有 `else` 的 `if`：`%skip` 被跳过；`%then` 的前驱以 `br` 结尾，`%end` 有两个前驱，都不能合并；
+        // `%tail` 并入 `%end`
+        assert_passes(
+            &["simplify-cfg"],
+            r#"
+decl @putint(i32)
+
+fun @f(@c: i32) {
+%entry:
+  br @c, %then, %skip
+%then:
+  call @putint(1)
+  jump %end
+%skip:
+  jump %end
+%end:
+  call @putint(2)
+  jump %tail
+%tail:
+  ret
+}
+"#,
+            r#"
+decl @putint(i32)
+
+fun @f(@c: i32) {
+%entry:
+  br @c, %then, %end
+%then:
+  call @putint(1)
+  jump %end
+%end:
+  call @putint(2)
+  ret
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn constant_branches() {
+        // `while (1)` 的条件恒真，循环头并入入口后只剩自环
+        assert_passes(
+            &["simplify-cfg"],
+            r#"
+decl @putint(i32)
+
+fun @f() {
+%entry:
+  jump %while
+%while:
+  br 1, %loop, %endwhile
+%loop:
+  call @putint(1)
+  jump %while
+%endwhile:
+  ret
+}
+"#,
+            r#"
+decl @putint(i32)
+
+fun @f() {
+%entry:
+  jump %loop
+%loop:
+  call @putint(1)
+  jump %loop
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn params() {
+        // 并入前驱时参数替换为实参；带实参的跳转目标也可以被跳过
+        assert_passes(
+            &["simplify-cfg"],
+            r#"
+fun @f(@c: i32): i32 {
+%entry:
+  br @c, %a, %b
+%a:
+  jump %fwd
+%fwd:
+  jump %join(1)
+%b:
+  jump %join(2)
+%join(%x: i32):
+  %0 = add %x, 1
+  jump %exit(%0)
+%exit(%y: i32):
+  ret %y
+}
+"#,
+            r#"
+fun @f(@c: i32): i32 {
+%entry:
+  br @c, %join(1), %join(2)
+%join(%x: i32):
+  %0 = add %x, 1
+  ret %0
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! 尾递归消除
+//!
+//! 函数对自身的调用之后紧接着返回该调用的结果（或不返回值）时为尾递归，可以改为循环：入口中 `alloc` 以外的指令
+//! 移到新的循环头，循环头的参数代替函数的形参，入口以形参跳转到循环头；尾递归的调用与其后的 `ret`
+//! 改为以调用的实参跳转到循环头。局部变量在各次循环中复用，因此实参中有局部变量的地址时不作变换。
+//!
+//! 对其他函数的尾调用由后端生成为拆除栈帧之后的 `tail`。
+
+use std::collections::HashMap;
+
+use koopa::ir::{builder_traits::*, Function, FunctionData, Program, Type, Value, ValueKind};
+
+use super::{
+    analysis::alias::{AliasInfo, Base},
+    edit, ModulePass,
+};
+
+pub struct Tre;
+
+impl ModulePass for Tre {
+    fn run(&mut self, program: &mut Program) -> bool {
+        let mut changed = false;
+        for f in program.func_layout().to_vec() {
+            let func = program.func_mut(f);
+            if func.layout().entry_bb().is_none() {
+                continue;
+            }
+            let calls = tail_calls(func, f);
+            if !calls.is_empty() {
+                eliminate(func, &calls);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// 函数 `f` 中对自身的尾调用，及其后的 `ret`
+fn tail_calls(func: &FunctionData, f: Function) -> Vec<(Value, Value)> {
+    let alias = AliasInfo::new(func);
+    let mut calls = vec![];
+    for &bb in func.layout().bbs().keys() {
+        for pair in edit::block_insts(func, bb).windows(2) {
+            let (call, ret) = (pair[0], pair[1]);
+            let (ValueKind::Call(c), ValueKind::Return(r)) =
+                (func.dfg().value(call).kind(), func.dfg().value(ret).kind())
+            else {
+                continue;
+            };
+            let local = c.args().iter().any(|&arg| matches!(alias.base(func, arg).0, Base::Local(_)));
+            if c.callee() == f && r.value().is_none_or(|v| v == call) && !local {
+                calls.push((call, ret));
+            }
+        }
+    }
+    calls
+}
+
+/// 将尾递归改为跳转到循环头
+fn eliminate(func: &mut FunctionData, calls: &[(Value, Value)]) {
+    let entry = func.layout().entry_bb().unwrap();
+    let name = edit::Namer::new(func).derive(func, entry, "loop");
+    let tys: Vec<Type> = func.params().iter().map(|&p| func.dfg().value(p).ty().clone()).collect();
+    let header = func.dfg_mut().new_bb().basic_block_with_params(name, tys);
+    func.layout_mut().bbs_mut().cursor_mut(entry).insert_key_after(header).unwrap();
+    for inst in edit::block_insts(func, entry) {
+        if !matches!(func.dfg().value(inst).kind(), ValueKind::Alloc(_)) {
+            func.layout_mut().bb_mut(entry).insts_mut().remove(&inst);
+            func.layout_mut().bb_mut(header).insts_mut().push_key_back(inst).unwrap();
+        }
+    }
+
+    // 循环头的参数沿用形参的名字
+    let params = func.params().to_vec();
+    let map: HashMap<Value, Value> = params.iter().copied().zip(func.dfg().bb(header).params().to_vec()).collect();
+    for (&param, &new) in map.iter() {
+        let name = func.dfg().value(param).name().as_ref().map(|n| format!("%{}", &n[1..]));
+        func.dfg_mut().set_value_name(new, name);
+    }
+    edit::replace_uses(func, &map);
+    let jump = func.dfg_mut().new_value().jump_with_args(header, params);
+    func.layout_mut().bb_mut(entry).insts_mut().push_key_back(jump).unwrap();
+
+    for &(call, ret) in calls {
+        let bb = func.layout().parent_bb(call).unwrap();
+        let ValueKind::Call(c) = func.dfg().value(call).kind() else {
+            unreachable!()
+        };
+        let args = c.args().to_vec();
+        let jump = func.dfg_mut().new_value().jump_with_args(header, args);
+        edit::remove_insts(func, &[ret, call]);
+        func.layout_mut().bb_mut(bb).insts_mut().push_key_back(jump).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn tail_recursion() {
+        // `@gcd` 与无返回值的 `@down` 的尾递归改为循环，`@down` 的 `alloc` 留在入口；
+        // `@fib` 的递归调用之后还有加法，`@addr` 传递局部变量的地址，都不变换
+        assert_passes(
+            &["tre"],
+            r#"
+decl @putint(i32)
+
+fun @gcd(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = eq @b, 0
+  br %0, %then, %else
+%then:
+  ret @a
+%else:
+  %1 = mod @a, @b
+  %2 = call @gcd(@b, %1)
+  ret %2
+}
+
+fun @down(@n: i32) {
+%entry:
+  %p = alloc i32
+  store @n, %p
+  %0 = load %p
+  br %0, %body, %end
+%body:
+  call @putint(%0)
+  %1 = sub %0, 1
+  call @down(%1)
+  ret
+%end:
+  ret
+}
+
+fun @fib(@n: i32): i32 {
+%entry:
+  %0 = lt @n, 2
+  br %0, %then, %else
+%then:
+  ret @n
+%else:
+  %1 = sub @n, 1
+  %2 = call @fib(%1)
+  %3 = add %2, @n
+  ret %3
+}
+
+fun @addr(@p: *i32): i32 {
+%entry:
+  %v = alloc i32
+  %0 = load @p
+  br %0, %then, %else
+%then:
+  ret 0
+%else:
+  store 0, %v
+  %1 = call @addr(%v)
+  ret %1
+}
+"#,
+            r#"
+decl @putint(i32)
+
+fun @gcd(@a: i32, @b: i32): i32 {
+%entry:
+  jump %entry_loop(@a, @b)
+%entry_loop(%a: i32, %b: i32):
+  %0 = eq %b, 0
+  br %0, %then, %else
+%then:
+  ret %a
+%else:
+  %1 = mod %a, %b
+  jump %entry_loop(%b, %1)
+}
+
+fun @down(@n: i32) {
+%entry:
+  %p = alloc i32
+  jump %entry_loop(@n)
+%entry_loop(%n: i32):
+  store %n, %p
+  %0 = load %p
+  br %0, %body, %end
+%body:
+  call @putint(%0)
+  %1 = sub %0, 1
+  jump %entry_loop(%1)
+%end:
+  ret
+}
+
+fun @fib(@n: i32): i32 {
+%entry:
+  %0 = lt @n, 2
+  br %0, %then, %else
+%then:
+  ret @n
+%else:
+  %1 = sub @n, 1
+  %2 = call @fib(%1)
+  %3 = add %2, @n
+  ret %3
+}
+
+fun @addr(@p: *i32): i32 {
+%entry:
+  %v = alloc i32
+  %0 = load @p
+  br %0, %then, %else
+%then:
+  ret 0
+%else:
+  store 0, %v
+  %1 = call @addr(%v)
+  ret %1
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,681 @@
+//! 计数循环展开
+//!
+//! 处理前端的 `while` 经 mem2reg 之后的计数循环：循环头只有一条比较与 `br`，比较步长为常量的基本归纳变量 `i`
+//! 与循环不变的界 `n`（步长为正时为 `lt`、`le`，为负时为 `gt`、`ge`），成立时进入循环体，否则离开循环；
+//! 循环体中没有内层循环，只经唯一的回边 `jump` 回到循环头，也不从别处离开循环。循环体的大小为其中的指令数。
+//!
+//! - 完全展开：初值与界都是常量时可以求出循环次数 `c`。`c` 份循环体总共不超过 [`FULL_SIZE`] 条指令时，
+//!   前置块直接进入 `c` 份依次相连的循环体副本，最后一份以最终的值跳转到循环的出口，原循环删除。
+//! - 部分展开：展开因子 `k` 为 [`PARTIAL_SIZE`] 除以循环体的大小，不超过 [`MAX_FACTOR`]，取 2 的幂，至少为 2。
+//!   新的循环头比较 `i` 与 `n - (k - 1) * s`：成立时其后 `k` 次循环都会执行，进入 `k` 份相连的副本，
+//!   最后一份回到新的循环头；否则转到原循环，由它执行余下的不足 `k` 次循环。界不是常量时 `n - (k - 1) * s`
+//!   在前置块中求出，溢出时前置块直接转到原循环。
+//!
+//! 副本中循环头的参数对应到上一份副本传给回边的值，循环条件对应到 1。
+
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{builder_traits::*, BasicBlock, BinaryOp, FunctionData, Type, Value, ValueKind};
+
+use super::{
+    analysis::{
+        cfg::Cfg,
+        indvars::{header_args, IndVars, Step},
+        loops::{insert_preheaders, Loop, LoopInfo},
+        Analyses,
+    },
+    edit, instcombine, FunctionPass,
+};
+
+/// 完全展开后的指令数上限
+const FULL_SIZE: usize = 128;
+/// 部分展开后的循环体的指令数上限
+const PARTIAL_SIZE: usize = 64;
+const MAX_FACTOR: usize = 8;
+
+pub struct Unroll;
+
+impl FunctionPass for Unroll {
+    fn run(&mut self, func: &mut FunctionData, analyses: &mut Analyses) -> bool {
+        let mut changed = insert_preheaders(func, analyses);
+        // 已经处理过的循环头，包括部分展开后新的循环头
+        let mut done = HashSet::new();
+        loop {
+            let (cfg, loops) = (analyses.cfg(func), analyses.loops(func));
+            let Some(l) = loops.loops().iter().find(|l| !done.contains(&l.header)) else {
+                return changed;
+            };
+            done.insert(l.header);
+            let Some(counted) = Counted::new(func, &cfg, &loops, l) else {
+                continue;
+            };
+            let unrolled = match counted.trip_count(func) {
+                Some(c) if c > 0 && c * counted.size <= FULL_SIZE => {
+                    counted.unroll_fully(func, c);
+                    true
+                }
+                trips => counted.unroll_partially(func, trips).map(|header| done.insert(header)).is_some(),
+            };
+            if unrolled {
+                analyses.invalidate();
+                changed = true;
+            }
+        }
+    }
+}
+
+/// 计数循环
+struct Counted {
+    header: BasicBlock,
+    pre: BasicBlock,
+    /// 循环体的入口与回边的起点
+    body: BasicBlock,
+    latch: BasicBlock,
+    exit: BasicBlock,
+    /// 循环体中的基本块，按逆后序排列，不含循环头
+    blocks: Vec<BasicBlock>,
+    size: usize,
+    cond: Value,
+    /// 比较的运算，已交换为归纳变量在左边
+    op: BinaryOp,
+    /// 归纳变量在循环头参数中的位置
+    iv: usize,
+    bound: Value,
+    step: i32,
+}
+
+impl Counted {
+    fn new(func: &FunctionData, cfg: &Cfg, loops: &LoopInfo, l: &Loop) -> Option<Counted> {
+        let index = loops.innermost(l.header)?;
+        if loops.loops().iter().any(|m| m.parent == Some(index)) {
+            return None;
+        }
+        let pre = l.preheader(cfg)?;
+        let [latch] = l.latches[..] else {
+            return None;
+        };
+        let blocks: Vec<BasicBlock> =
+            cfg.rpo().iter().copied().filter(|&bb| bb != l.header && l.contains(bb)).collect();
+        if blocks.iter().any(|&bb| cfg.succs(bb).iter().any(|s| !l.contains(*s))) {
+            return None;
+        }
+        let jumps = |bb: BasicBlock| {
+            let term = *func.layout().bbs().node(&bb).unwrap().insts().back_key().unwrap();
+            matches!(func.dfg().value(term).kind(), ValueKind::Jump(_))
+        };
+        if !jumps(pre) || !jumps(latch) {
+            return None;
+        }
+
+        let [cond, br] = edit::block_insts(func, l.header)[..] else {
+            return None;
+        };
+        let ValueKind::Branch(br) = func.dfg().value(br).kind() else {
+            return None;
+        };
+        let (body, exit) = (br.true_bb(), br.false_bb());
+        if br.cond() != cond || !l.contains(body) || l.contains(exit) || !br.true_args().is_empty() {
+            return None;
+        }
+        let ValueKind::Binary(cmp) = func.dfg().value(cond).kind() else {
+            return None;
+        };
+
+        let ivs = IndVars::new(func, cfg, l);
+        let basic = |v: Value| ivs.basics.iter().find(|b| b.param == v);
+        let (iv, op, bound) = match (basic(cmp.lhs()), basic(cmp.rhs())) {
+            (Some(iv), _) if ivs.is_invariant(cmp.rhs()) => (iv, cmp.op(), cmp.rhs()),
+            (_, Some(iv)) if ivs.is_invariant(cmp.lhs()) => (iv, instcombine::swapped(cmp.op()), cmp.lhs()),
+            _ => return None,
+        };
+        let Step::Const(step) = iv.step else {
+            return None;
+        };
+        match op {
+            BinaryOp::Lt | BinaryOp::Le if step > 0 => {}
+            BinaryOp::Gt | BinaryOp::Ge if step < 0 => {}
+            _ => return None,
+        }
+        let iv = func.dfg().bb(l.header).params().iter().position(|&p| p == iv.param).unwrap();
+        let size = blocks.iter().map(|&bb| edit::block_insts(func, bb).len()).sum();
+        Some(Counted { header: l.header, pre, body, latch, exit, blocks, size, cond, op, iv, bound, step })
+    }
+
+    /// 初值与界都是常量时的循环次数；最终的值溢出时为 `None`
+    fn trip_count(&self, func: &FunctionData) -> Option<usize> {
+        let constant = |v: Value| match func.dfg().value(v).kind() {
+            ValueKind::Integer(i) => Some(i.value() as i64),
+            _ => None,
+        };
+        let init = constant(header_args(func, self.pre, self.header)[self.iv])?;
+        let (bound, step) = (constant(self.bound)?, self.step as i64);
+        // 化为 `i` 每次前进 `step` 步、距离循环结束还有 `dist` 步
+        let (dist, step) = match self.op {
+            BinaryOp::Lt => (bound - init, step),
+            BinaryOp::Le => (bound - init + 1, step),
+            BinaryOp::Gt => (init - bound, -step),
+            _ => (init - bound + 1, -step),
+        };
+        let count = if dist > 0 { (dist + step - 1) / step } else { 0 };
+        i32::try_from(init + count * self.step as i64).ok()?;
+        usize::try_from(count).ok()
+    }
+
+    fn unroll_fully(&self, func: &mut FunctionData, count: usize) {
+        let mut namer = edit::Namer::new(func);
+        let mut args = header_args(func, self.pre, self.header);
+        let mut entry = None;
+        let mut last: Option<BasicBlock> = None;
+        for k in 1..=count {
+            let (body, latch, next) = self.copy(func, &mut namer, k, &args);
+            match last {
+                Some(prev) => push_jump(func, prev, body, vec![]),
+                None => entry = Some(body),
+            }
+            (last, args) = (Some(latch), next);
+        }
+
+        // 循环头的参数与条件在循环之外的使用，对应到最终的值与 0
+        let mut map: HashMap<Value, Value> = func.dfg().bb(self.header).params().iter().copied().zip(args).collect();
+        map.insert(self.cond, func.dfg_mut().new_value().integer(0));
+        let br = *func.layout().bbs().node(&self.header).unwrap().insts().back_key().unwrap();
+        let ValueKind::Branch(br) = func.dfg().value(br).kind() else {
+            unreachable!()
+        };
+        let exit_args: Vec<Value> = br.false_args().iter().map(|v| map.get(v).copied().unwrap_or(*v)).collect();
+        edit::replace_uses(func, &map);
+        push_jump(func, last.unwrap(), self.exit, exit_args);
+        redirect(func, self.pre, self.header, entry.unwrap(), |_| vec![]);
+        let mut dead = self.blocks.clone();
+        dead.push(self.header);
+        edit::remove_bbs(func, &dead);
+    }
+
+    /// 部分展开，返回新的循环头；循环体太大或不会执行展开后的循环时为 `None`
+    fn unroll_partially(&self, func: &mut FunctionData, trips: Option<usize>) -> Option<BasicBlock> {
+        let factor = (PARTIAL_SIZE / self.size).min(MAX_FACTOR);
+        if factor < 2 || trips.is_some_and(|c| c < factor) {
+            return None;
+        }
+        let factor = 1 << factor.ilog2();
+        let offset = self.step.checked_mul(factor as i32 - 1)?;
+        // 界为常量时直接求出新的界，否则在前置块中求出并检查是否溢出
+        let (limit, overflow) = match func.dfg().value(self.bound).kind().clone() {
+            ValueKind::Integer(n) => (func.dfg_mut().new_value().integer(n.value().checked_sub(offset)?), None),
+            _ => {
+                let offset = func.dfg_mut().new_value().integer(offset);
+                let limit = func.dfg_mut().new_value().binary(BinaryOp::Sub, self.bound, offset);
+                let op = if self.step > 0 { BinaryOp::Lt } else { BinaryOp::Gt };
+                let ok = func.dfg_mut().new_value().binary(op, limit, self.bound);
+                (limit, Some(ok))
+            }
+        };
+
+        let mut namer = edit::Namer::new(func);
+        let tys: Vec<Type> =
+            func.dfg().bb(self.header).params().iter().map(|&p| func.dfg().value(p).ty().clone()).collect();
+        let name = namer.derive(func, self.header, "unrolled");
+        let header = func.dfg_mut().new_bb().basic_block_with_params(name, tys);
+        func.layout_mut().bbs_mut().cursor_mut(self.header).insert_key_before(header).unwrap();
+        copy_param_names(func, self.header, header);
+        let params = func.dfg().bb(header).params().to_vec();
+
+        let mut args = params.clone();
+        let mut last: Option<BasicBlock> = None;
+        let mut entry = None;
+        for k in 1..=factor {
+            let (body, latch, next) = self.copy(func, &mut namer, k, &args);
+            match last {
+                Some(prev) => push_jump(func, prev, body, vec![]),
+                None => entry = Some(body),
+            }
+            (last, args) = (Some(latch), next);
+        }
+        push_jump(func, last.unwrap(), header, args);
+
+        let cond = func.dfg_mut().new_value().binary(self.op, params[self.iv], limit);
+        let br = func.dfg_mut().new_value().branch_with_args(cond, entry.unwrap(), self.header, vec![], params);
+        func.layout_mut().bb_mut(header).insts_mut().extend([cond, br]);
+
+        match overflow {
+            None => redirect(func, self.pre, self.header, header, |args| args),
+            Some(ok) => {
+                let term = *func.layout().bbs().node(&self.pre).unwrap().insts().back_key().unwrap();
+                let args = header_args(func, self.pre, self.header);
+                let br = func.dfg_mut().new_value().branch_with_args(ok, header, self.header, args.clone(), args);
+                edit::remove_insts(func, &[term]);
+                func.layout_mut().bb_mut(self.pre).insts_mut().extend([limit, ok, br]);
+            }
+        }
+        Some(header)
+    }
+
+    /// 复制一份循环体，循环头的参数对应到 `args`；返回副本的入口、回边的起点及其传给循环头的实参。
+    /// 回边的 `jump` 不复制，由调用者补上
+    fn copy(
+        &self,
+        func: &mut FunctionData,
+        namer: &mut edit::Namer,
+        k: usize,
+        args: &[Value],
+    ) -> (BasicBlock, BasicBlock, Vec<Value>) {
+        let mut values: HashMap<Value, Value> =
+            func.dfg().bb(self.header).params().iter().copied().zip(args.iter().copied()).collect();
+        values.insert(self.cond, func.dfg_mut().new_value().integer(1));
+        let mut bbs = HashMap::new();
+        for &old in self.blocks.iter() {
+            let params = func.dfg().bb(old).params().to_vec();
+            let tys: Vec<Type> = params.iter().map(|&p| func.dfg().value(p).ty().clone()).collect();
+            let name = namer.derive(func, old, &k.to_string());
+            let new = func.dfg_mut().new_bb().basic_block_with_params(name, tys);
+            copy_param_names(func, old, new);
+            values.extend(params.into_iter().zip(func.dfg().bb(new).params().to_vec()));
+            func.layout_mut().bbs_mut().cursor_mut(self.header).insert_key_before(new).unwrap();
+            bbs.insert(old, new);
+        }
+
+        let mut latch_args = vec![];
+        for &old in self.blocks.iter() {
+            for inst in edit::block_insts(func, old) {
+                let mut data = func.dfg().value(inst).clone();
+                edit::map_operands(data.kind_mut(), |v| values.get(&v).copied().unwrap_or(v));
+                match data.kind_mut() {
+                    ValueKind::Jump(j) if old == self.latch => {
+                        latch_args = j.args().to_vec();
+                        continue;
+                    }
+                    ValueKind::Jump(j) => *j.target_mut() = bbs[&j.target()],
+                    ValueKind::Branch(b) => {
+                        *b.true_bb_mut() = bbs[&b.true_bb()];
+                        *b.false_bb_mut() = bbs[&b.false_bb()];
+                    }
+                    _ => {}
+                }
+                let new = func.dfg_mut().new_value().raw(data);
+                func.layout_mut().bb_mut(bbs[&old]).insts_mut().push_key_back(new).unwrap();
+                values.insert(inst, new);
+            }
+        }
+        (bbs[&self.body], bbs[&self.latch], latch_args)
+    }
+}
+
+/// 新的基本块 `to` 的参数沿用 `from` 的参数的名字
+fn copy_param_names(func: &mut FunctionData, from: BasicBlock, to: BasicBlock) {
+    let params = func.dfg().bb(from).params().to_vec();
+    for (old, new) in params.into_iter().zip(func.dfg().bb(to).params().to_vec()) {
+        let name = func.dfg().value(old).name().clone();
+        func.dfg_mut().set_value_name(new, name);
+    }
+}
+
+fn push_jump(func: &mut FunctionData, bb: BasicBlock, target: BasicBlock, args: Vec<Value>) {
+    let jump = func.dfg_mut().new_value().jump_with_args(target, args);
+    func.layout_mut().bb_mut(bb).insts_mut().push_key_back(jump).unwrap();
+}
+
+/// 将 `bb` 以 `jump` 结尾的到 `from` 的跳转改为到 `to`，实参由 `f` 给出
+fn redirect(
+    func: &mut FunctionData,
+    bb: BasicBlock,
+    from: BasicBlock,
+    to: BasicBlock,
+    f: impl FnOnce(Vec<Value>) -> Vec<Value>,
+) {
+    let term = *func.layout().bbs().node(&bb).unwrap().insts().back_key().unwrap();
+    edit::rewrite(func, term, |kind| match kind {
+        ValueKind::Jump(j) if j.target() == from => {
+            *j.target_mut() = to;
+            *j.args_mut() = f(j.args().to_vec());
+        }
+        _ => unreachable!(),
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::opt::assert_passes;
+
+    #[test]
+    fn counted_loops() {
+        // `@full` 循环 3 次，完全展开后不再有循环；`@partial` 的界不是常量，展开 8 次，
+        // 前置块检查 `@n - 7` 是否溢出，不足 8 次的部分由原循环执行
+        assert_passes(
+            &["unroll", "simplify-cfg"],
+            r#"
+fun @full(): i32 {
+%entry:
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, 3
+  br %c, %body, %exit
+%body:
+  %t = mul %i, %i
+  %s1 = add %s, %t
+  %i1 = add %i, 1
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+
+fun @partial(@n: i32): i32 {
+%entry:
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, @n
+  br %c, %body, %exit
+%body:
+  %s1 = add %s, %i
+  %i1 = add %i, 1
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+"#,
+            r#"
+fun @full(): i32 {
+%entry:
+  %t = mul 0, 0
+  %s1 = add 0, %t
+  %i1 = add 0, 1
+  %t_0 = mul %i1, %i1
+  %s1_0 = add %s1, %t_0
+  %i1_0 = add %i1, 1
+  %t_1 = mul %i1_0, %i1_0
+  %s1_1 = add %s1_0, %t_1
+  %i1_1 = add %i1_0, 1
+  ret %s1_1
+}
+
+fun @partial(@n: i32): i32 {
+%entry:
+  %0 = sub @n, 7
+  %1 = lt %0, @n
+  br %1, %cond_unrolled(0, 0), %cond(0, 0)
+
+%cond_unrolled(%i: i32, %s: i32):
+  %2 = lt %i, %0
+  br %2, %body_1, %cond(%i, %s)
+
+%cond(%i_0: i32, %s_0: i32):
+  %c = lt %i_0, @n
+  br %c, %body, %exit
+
+%body_1:
+  %s1 = add %s, %i
+  %i1 = add %i, 1
+  %s1_0 = add %s1, %i1
+  %i1_0 = add %i1, 1
+  %s1_1 = add %s1_0, %i1_0
+  %i1_1 = add %i1_0, 1
+  %s1_2 = add %s1_1, %i1_1
+  %i1_2 = add %i1_1, 1
+  %s1_3 = add %s1_2, %i1_2
+  %i1_3 = add %i1_2, 1
+  %s1_4 = add %s1_3, %i1_3
+  %i1_4 = add %i1_3, 1
+  %s1_5 = add %s1_4, %i1_4
+  %i1_5 = add %i1_4, 1
+  %s1_6 = add %s1_5, %i1_5
+  %i1_6 = add %i1_5, 1
+  jump %cond_unrolled(%i1_6, %s1_6)
+
+%body:
+  %s1_7 = add %s_0, %i_0
+  %i1_7 = add %i_0, 1
+  jump %cond(%i1_7, %s1_7)
+
+%exit:
+  ret %s_0
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn trip_counts() {
+        // `le` 多执行一次；`gt`、`ge` 的步长为负，`@ge` 的比较交换为归纳变量在左边；三者都循环 3 次，完全展开。
+        // `@never` 一次也不执行，少于展开因子，不展开
+        assert_passes(
+            &["unroll", "simplify-cfg"],
+            r#"
+fun @le(): i32 {
+%entry:
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %c = le %i, 2
+  br %c, %body, %exit
+%body:
+  %s1 = add %s, %i
+  %i1 = add %i, 1
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+
+fun @gt(): i32 {
+%entry:
+  jump %cond(5, 0)
+%cond(%i: i32, %s: i32):
+  %c = gt %i, 0
+  br %c, %body, %exit
+%body:
+  %s1 = add %s, %i
+  %i1 = sub %i, 2
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+
+fun @ge(): i32 {
+%entry:
+  jump %cond(2, 0)
+%cond(%i: i32, %s: i32):
+  %c = le 0, %i
+  br %c, %body, %exit
+%body:
+  %s1 = add %s, %i
+  %i1 = add %i, -1
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+
+fun @never(): i32 {
+%entry:
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, 0
+  br %c, %body, %exit
+%body:
+  %s1 = add %s, %i
+  %i1 = add %i, 1
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+"#,
+            r#"
+fun @le(): i32 {
+%entry:
+  %s1 = add 0, 0
+  %i1 = add 0, 1
+  %s1_0 = add %s1, %i1
+  %i1_0 = add %i1, 1
+  %s1_1 = add %s1_0, %i1_0
+  %i1_1 = add %i1_0, 1
+  ret %s1_1
+}
+
+fun @gt(): i32 {
+%entry:
+  %s1 = add 0, 5
+  %i1 = sub 5, 2
+  %s1_0 = add %s1, %i1
+  %i1_0 = sub %i1, 2
+  %s1_1 = add %s1_0, %i1_0
+  %i1_1 = sub %i1_0, 2
+  ret %s1_1
+}
+
+fun @ge(): i32 {
+%entry:
+  %s1 = add 0, 2
+  %i1 = add 2, -1
+  %s1_0 = add %s1, %i1
+  %i1_0 = add %i1, -1
+  %s1_1 = add %s1_0, %i1_0
+  %i1_1 = add %i1_0, -1
+  ret %s1_1
+}
+
+fun @never(): i32 {
+%entry:
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, 0
+  br %c, %body, %exit
+%body:
+  %s1 = add %s, %i
+  %i1 = add %i, 1
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn constant_bounds() {
+        // 循环体有 9 条指令，展开 4 次；界为常量时新的界直接求出，步长为负时为 `0 - 3 * -1`。
+        // `@overflow` 的新的界 `-2147483647 - 3` 溢出，不展开
+        assert_passes(
+            &["unroll", "simplify-cfg"],
+            r#"
+fun @down(): i32 {
+%entry:
+  jump %cond(1000, 0)
+%cond(%i: i32, %s: i32):
+  %c = gt %i, 0
+  br %c, %body, %exit
+%body:
+  %t = mul %i, %i
+  %u = add %t, %i
+  %v = add %u, 1
+  %w = add %v, %s
+  %x = add %w, 7
+  %y = add %x, %i
+  %s1 = add %s, %y
+  %i1 = sub %i, 1
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+
+fun @overflow(@x: i32): i32 {
+%entry:
+  jump %cond(@x, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, -2147483647
+  br %c, %body, %exit
+%body:
+  %t = mul %i, %i
+  %u = add %t, %i
+  %v = add %u, 1
+  %w = add %v, %s
+  %x = add %w, 7
+  %y = add %x, %i
+  %s1 = add %s, %y
+  %i1 = add %i, 1
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+"#,
+            r#"
+fun @down(): i32 {
+%entry:
+  jump %cond_unrolled(1000, 0)
+
+%cond_unrolled(%i: i32, %s: i32):
+  %0 = gt %i, 3
+  br %0, %body_1, %cond(%i, %s)
+
+%body_1:
+  %t = mul %i, %i
+  %u = add %t, %i
+  %v = add %u, 1
+  %w = add %v, %s
+  %x = add %w, 7
+  %y = add %x, %i
+  %s1 = add %s, %y
+  %i1 = sub %i, 1
+  %t_0 = mul %i1, %i1
+  %u_0 = add %t_0, %i1
+  %v_0 = add %u_0, 1
+  %w_0 = add %v_0, %s1
+  %x_0 = add %w_0, 7
+  %y_0 = add %x_0, %i1
+  %s1_0 = add %s1, %y_0
+  %i1_0 = sub %i1, 1
+  %t_1 = mul %i1_0, %i1_0
+  %u_1 = add %t_1, %i1_0
+  %v_1 = add %u_1, 1
+  %w_1 = add %v_1, %s1_0
+  %x_1 = add %w_1, 7
+  %y_1 = add %x_1, %i1_0
+  %s1_1 = add %s1_0, %y_1
+  %i1_1 = sub %i1_0, 1
+  %t_2 = mul %i1_1, %i1_1
+  %u_2 = add %t_2, %i1_1
+  %v_2 = add %u_2, 1
+  %w_2 = add %v_2, %s1_1
+  %x_2 = add %w_2, 7
+  %y_2 = add %x_2, %i1_1
+  %s1_2 = add %s1_1, %y_2
+  %i1_2 = sub %i1_1, 1
+  jump %cond_unrolled(%i1_2, %s1_2)
+
+%cond(%i_0: i32, %s_0: i32):
+  %c = gt %i_0, 0
+  br %c, %body, %exit
+
+%body:
+  %t_3 = mul %i_0, %i_0
+  %u_3 = add %t_3, %i_0
+  %v_3 = add %u_3, 1
+  %w_3 = add %v_3, %s_0
+  %x_3 = add %w_3, 7
+  %y_3 = add %x_3, %i_0
+  %s1_3 = add %s_0, %y_3
+  %i1_3 = sub %i_0, 1
+  jump %cond(%i1_3, %s1_3)
+
+%exit:
+  ret %s_0
+}
+
+fun @overflow(@x: i32): i32 {
+%entry:
+  jump %cond(@x, 0)
+%cond(%i: i32, %s: i32):
+  %c = lt %i, -2147483647
+  br %c, %body, %exit
+%body:
+  %t = mul %i, %i
+  %u = add %t, %i
+  %v = add %u, 1
+  %w = add %v, %s
+  %x = add %w, 7
+  %y = add %x, %i
+  %s1 = add %s, %y
+  %i1 = add %i, 1
+  jump %cond(%i1, %s1)
+%exit:
+  ret %s
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use std::collections::HashMap;
+
+use koopa::ir::{BasicBlock, FunctionData, ValueKind};
+
+/// 基本块的后继，由其最后一条指令决定
+pub fn successors(func: &FunctionData, bb: BasicBlock) -> Vec<BasicBlock> {
+    let Some(&last) = func.layout().bbs().node(&bb).and_then(|node| node.insts().back_key()) else {
+        return vec![];
+    };
+    match func.dfg().value(last).kind() {
+        ValueKind::Jump(j) => vec![j.target()],
+        ValueKind::Branch(b) => vec![b.true_bb(), b.false_bb()],
+        _ => vec![],
+    }
+}
+
+/// 所有基本块的前驱，按布局顺序排列
+pub fn predecessors(func: &FunctionData) -> HashMap<BasicBlock, Vec<BasicBlock>> {
+    let mut preds: HashMap<BasicBlock, Vec<BasicBlock>> =
+        func.layout().bbs().keys().map(|&bb| (bb, vec![])).collect();
+    for &bb in func.layout().bbs().keys() {
+        for succ in successors(func, bb) {
+            preds.entry(succ).or_default().push(bb);
+        }
+    }
+    preds
+}
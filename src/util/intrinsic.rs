@@ -0,0 +1,170 @@
+use koopa::ir;
+
+/// 编译器内部函数
+///
+/// Koopa IR 只有有符号的 `i32`，无符号运算和 64 位整数的部分运算无法直接表示。
+/// 前端将这些运算生成为对内部函数的 `call`，后端再将其展开为对应的 RISC-V 指令，
+/// 或按照 RV32 的调用约定调用 libgcc 的运行时函数。
+///
+/// `float` 以 `i32` 位模式保存，其运算默认调用 libgcc 的软浮点函数（`ilp32` ABI，
+/// 浮点参数和返回值都在整数寄存器中）；启用 F 扩展时，后端将其中有对应指令的展开为 RV32F 指令。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Intrinsic {
+    /// 无符号小于 `sltu`
+    Sltu,
+    /// 无符号除 `divu`
+    Divu,
+    /// 无符号模 `remu`
+    Remu,
+    /// 无符号乘法高位 `mulhu`
+    Mulhu,
+    /// 64 位有符号除，调用 libgcc 的 `__divdi3`，返回低位字
+    Divdi3,
+    /// 64 位有符号模，调用 libgcc 的 `__moddi3`，返回低位字
+    Moddi3,
+    /// 取一次返回 64 位值的调用在 `a1` 中返回的高位字，参数为该调用的值
+    Hi,
+    /// 在 `ret` 之前将 64 位返回值的高位字放入 `a1`
+    SetHi,
+    /// 浮点加 `__addsf3`
+    Addsf3,
+    /// 浮点减 `__subsf3`
+    Subsf3,
+    /// 浮点乘 `__mulsf3`
+    Mulsf3,
+    /// 浮点除 `__divsf3`
+    Divsf3,
+    /// 相等时返回 0
+    Eqsf2,
+    /// 不等时返回非零值
+    Nesf2,
+    /// 小于时返回负值
+    Ltsf2,
+    /// 小于等于时返回非正值
+    Lesf2,
+    /// 大于时返回正值
+    Gtsf2,
+    /// 大于等于时返回非负值
+    Gesf2,
+    /// `int` 转为 `float`
+    Floatsisf,
+    /// `unsigned int` 转为 `float`
+    Floatunsisf,
+    /// `long` 转为 `float`，参数为低、高位字
+    Floatdisf,
+    /// `float` 向零取整为 `int`
+    Fixsfsi,
+    /// `float` 向零取整为 `unsigned int`
+    Fixunssfsi,
+    /// `float` 向零取整为 `long`，返回低位字
+    Fixsfdi,
+}
+
+impl Intrinsic {
+    pub const ALL: [Intrinsic; 24] = [
+        Intrinsic::Sltu,
+        Intrinsic::Divu,
+        Intrinsic::Remu,
+        Intrinsic::Mulhu,
+        Intrinsic::Divdi3,
+        Intrinsic::Moddi3,
+        Intrinsic::Hi,
+        Intrinsic::SetHi,
+        Intrinsic::Addsf3,
+        Intrinsic::Subsf3,
+        Intrinsic::Mulsf3,
+        Intrinsic::Divsf3,
+        Intrinsic::Eqsf2,
+        Intrinsic::Nesf2,
+        Intrinsic::Ltsf2,
+        Intrinsic::Lesf2,
+        Intrinsic::Gtsf2,
+        Intrinsic::Gesf2,
+        Intrinsic::Floatsisf,
+        Intrinsic::Floatunsisf,
+        Intrinsic::Floatdisf,
+        Intrinsic::Fixsfsi,
+        Intrinsic::Fixunssfsi,
+        Intrinsic::Fixsfdi,
+    ];
+
+    /// 在 Koopa IR 中的函数名
+    pub fn name(self) -> &'static str {
+        use Intrinsic::*;
+        match self {
+            Sltu => "@__sysy_sltu",
+            Divu => "@__sysy_divu",
+            Remu => "@__sysy_remu",
+            Mulhu => "@__sysy_mulhu",
+            Divdi3 => "@__divdi3",
+            Moddi3 => "@__moddi3",
+            Hi => "@__sysy_hi",
+            SetHi => "@__sysy_set_hi",
+            Addsf3 => "@__addsf3",
+            Subsf3 => "@__subsf3",
+            Mulsf3 => "@__mulsf3",
+            Divsf3 => "@__divsf3",
+            Eqsf2 => "@__eqsf2",
+            Nesf2 => "@__nesf2",
+            Ltsf2 => "@__ltsf2",
+            Lesf2 => "@__lesf2",
+            Gtsf2 => "@__gtsf2",
+            Gesf2 => "@__gesf2",
+            Floatsisf => "@__floatsisf",
+            Floatunsisf => "@__floatunsisf",
+            Floatdisf => "@__floatdisf",
+            Fixsfsi => "@__fixsfsi",
+            Fixunssfsi => "@__fixunssfsi",
+            Fixsfdi => "@__fixsfdi",
+        }
+    }
+
+    /// 不带 `@` 的函数名，用于符号表
+    pub fn ident(self) -> &'static str {
+        &self.name()[1..]
+    }
+
+    pub fn from_name(name: &str) -> Option<Intrinsic> {
+        Intrinsic::ALL.into_iter().find(|i| i.name() == name)
+    }
+
+    pub fn params_ty(self) -> Vec<ir::Type> {
+        use Intrinsic::*;
+        match self {
+            Sltu | Divu | Remu | Mulhu => vec![ty!(i32), ty!(i32)],
+            Divdi3 | Moddi3 => vec![ty!(i32), ty!(i32), ty!(i32), ty!(i32)],
+            Hi | SetHi => vec![ty!(i32)],
+            Addsf3 | Subsf3 | Mulsf3 | Divsf3 => vec![ty!(i32), ty!(i32)],
+            Eqsf2 | Nesf2 | Ltsf2 | Lesf2 | Gtsf2 | Gesf2 => vec![ty!(i32), ty!(i32)],
+            Floatdisf => vec![ty!(i32), ty!(i32)],
+            Floatsisf | Floatunsisf | Fixsfsi | Fixunssfsi | Fixsfdi => vec![ty!(i32)],
+        }
+    }
+
+    pub fn ret_ty(self) -> ir::Type {
+        match self {
+            Intrinsic::SetHi => ty!(()),
+            _ => ty!(i32),
+        }
+    }
+
+    /// 是否为软浮点运算，启用 F 扩展时有对应的 RV32F 指令
+    pub fn is_soft_float(self) -> bool {
+        use Intrinsic::*;
+        matches!(
+            self,
+            Addsf3 | Subsf3 | Mulsf3 | Divsf3
+                | Eqsf2 | Nesf2 | Ltsf2 | Lesf2 | Gtsf2 | Gesf2
+                | Floatsisf | Floatunsisf | Fixsfsi | Fixunssfsi
+        )
+    }
+
+    /// 是否展开为内联指令而不是真正的函数调用，`rv32f` 表示是否启用 F 扩展
+    pub fn is_inline(self, rv32f: bool) -> bool {
+        use Intrinsic::*;
+        match self {
+            Sltu | Divu | Remu | Mulhu | Hi | SetHi => true,
+            _ => rv32f && self.is_soft_float(),
+        }
+    }
+}
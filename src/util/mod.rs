@@ -1,4 +1,7 @@
 #[macro_use]
 pub mod autonum;
 #[macro_use]
-pub mod ir_type;
\ No newline at end of file
+pub mod ir_type;
+pub mod cfg;
+pub mod intrinsic;
+pub mod verify;
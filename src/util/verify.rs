@@ -0,0 +1,351 @@
+//! Koopa IR 的合法性检查
+//!
+//! 检查生成或变换后的 IR：每个基本块恰以一条终结指令（`jump`、`br`、`ret`）结束；
+//! 操作数在本函数中有定义且类型与指令相符；跳转的目标在布局中，实参与基本块参数一致；
+//! 调用的实参个数与类型符合被调函数的签名。从入口不可达的基本块只给出警告。
+
+use std::{collections::HashSet, fmt};
+
+use koopa::ir::{BasicBlock, FunctionData, Program, Type, TypeKind, Value, ValueKind};
+
+use super::cfg;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub func: String,
+    /// 所在的基本块，没有名字的基本块为 `None`
+    pub bb: Option<String>,
+    pub message: String,
+    /// 错误还是警告
+    pub error: bool,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "in function '{}'", self.func)?;
+        if let Some(bb) = &self.bb {
+            write!(f, ", block '{}'", bb)?;
+        }
+        let level = if self.error { "error" } else { "warning" };
+        write!(f, ": {}: {}", level, self.message)
+    }
+}
+
+/// 检查所有有函数体的函数，返回发现的问题
+pub fn verify(program: &Program) -> Vec<Issue> {
+    let mut issues = vec![];
+    for &func in program.func_layout() {
+        let func = program.func(func);
+        if func.layout().entry_bb().is_none() {
+            continue;
+        }
+        let mut v = Verifier { program, func, bb: None, issues: &mut issues };
+        v.func();
+    }
+    issues
+}
+
+/// 指令的名字，用于报告
+fn opcode(kind: &ValueKind) -> &'static str {
+    match kind {
+        ValueKind::Integer(_) => "integer",
+        ValueKind::ZeroInit(_) => "zeroinit",
+        ValueKind::Undef(_) => "undef",
+        ValueKind::Aggregate(_) => "aggregate",
+        ValueKind::FuncArgRef(_) => "function argument",
+        ValueKind::BlockArgRef(_) => "block argument",
+        ValueKind::Alloc(_) => "alloc",
+        ValueKind::GlobalAlloc(_) => "global alloc",
+        ValueKind::Load(_) => "load",
+        ValueKind::Store(_) => "store",
+        ValueKind::GetPtr(_) => "getptr",
+        ValueKind::GetElemPtr(_) => "getelemptr",
+        ValueKind::Binary(_) => "binary",
+        ValueKind::Branch(_) => "br",
+        ValueKind::Jump(_) => "jump",
+        ValueKind::Call(_) => "call",
+        ValueKind::Return(_) => "ret",
+    }
+}
+
+fn is_terminator(kind: &ValueKind) -> bool {
+    matches!(kind, ValueKind::Jump(_) | ValueKind::Branch(_) | ValueKind::Return(_))
+}
+
+struct Verifier<'a> {
+    program: &'a Program,
+    func: &'a FunctionData,
+    bb: Option<BasicBlock>,
+    issues: &'a mut Vec<Issue>,
+}
+
+impl<'a> Verifier<'a> {
+    fn report(&mut self, error: bool, message: String) {
+        self.issues.push(Issue {
+            func: self.func.name().to_string(),
+            bb: self.bb.and_then(|bb| self.func.dfg().bb(bb).name().clone()),
+            message,
+            error,
+        });
+    }
+
+    fn error(&mut self, message: String) {
+        self.report(true, message);
+    }
+
+    fn ty(&self, value: Value) -> Type {
+        if value.is_global() {
+            self.program.borrow_value(value).ty().clone()
+        } else {
+            self.func.dfg().value(value).ty().clone()
+        }
+    }
+
+    fn func(&mut self) {
+        let bbs: Vec<BasicBlock> = self.func.layout().bbs().keys().copied().collect();
+        for &bb in bbs.iter() {
+            self.bb = Some(bb);
+            self.block(bb);
+        }
+
+        let entry = bbs[0];
+        let mut reached = HashSet::from([entry]);
+        let mut stack = vec![entry];
+        while let Some(bb) = stack.pop() {
+            for succ in cfg::successors(self.func, bb) {
+                if self.func.layout().bbs().contains_key(&succ) && reached.insert(succ) {
+                    stack.push(succ);
+                }
+            }
+        }
+        for &bb in bbs.iter().filter(|bb| !reached.contains(bb)) {
+            self.bb = Some(bb);
+            self.report(false, "block is unreachable from the entry".to_string());
+        }
+    }
+
+    fn block(&mut self, bb: BasicBlock) {
+        let insts: Vec<Value> = self.func.layout().bbs().node(&bb).unwrap().insts().keys().copied().collect();
+        match insts.last() {
+            None => self.error("block is empty".to_string()),
+            Some(&last) if !is_terminator(self.func.dfg().value(last).kind()) => {
+                self.error("block does not end with a terminator".to_string())
+            }
+            _ => {}
+        }
+        for (idx, &inst) in insts.iter().enumerate() {
+            let kind = self.func.dfg().value(inst).kind();
+            if is_terminator(kind) && idx + 1 < insts.len() {
+                self.error(format!("terminator '{}' is followed by {} instruction(s)", opcode(kind), insts.len() - idx - 1));
+            }
+            self.operands(inst);
+            self.inst(inst);
+        }
+    }
+
+    /// 操作数须为常量、全局值、本函数或其基本块的参数，或位于布局中且有结果的指令
+    fn operands(&mut self, inst: Value) {
+        let kind = self.func.dfg().value(inst).kind();
+        for operand in kind.value_uses() {
+            if operand.is_global() {
+                continue;
+            }
+            let Some(data) = self.func.dfg().values().get(&operand) else {
+                self.error(format!("operand of '{}' is not a value of this function", opcode(kind)));
+                continue;
+            };
+            let defined = match data.kind() {
+                k if k.is_const() => true,
+                ValueKind::FuncArgRef(_) => self.func.params().contains(&operand),
+                ValueKind::BlockArgRef(_) => self
+                    .func
+                    .layout()
+                    .bbs()
+                    .keys()
+                    .any(|bb| self.func.dfg().bb(*bb).params().contains(&operand)),
+                _ => self.func.layout().parent_bb(operand).is_some(),
+            };
+            if !defined {
+                self.error(format!("operand '{}' of '{}' is not defined", opcode(data.kind()), opcode(kind)));
+            } else if data.ty().is_unit() {
+                self.error(format!("'{}' has no result but is used by '{}'", opcode(data.kind()), opcode(kind)));
+            }
+        }
+        for target in kind.bb_uses() {
+            if !self.func.layout().bbs().contains_key(&target) {
+                self.error(format!("target of '{}' is not in the layout", opcode(kind)));
+            }
+        }
+    }
+
+    fn expect(&mut self, what: &str, ty: &Type, expected: &Type) {
+        if ty != expected {
+            self.error(format!("{} has type '{}', expected '{}'", what, ty, expected));
+        }
+    }
+
+    fn pointee(&mut self, what: &str, ty: &Type) -> Option<Type> {
+        match ty.kind() {
+            TypeKind::Pointer(base) => Some(base.clone()),
+            _ => {
+                self.error(format!("{} has type '{}', expected a pointer", what, ty));
+                None
+            }
+        }
+    }
+
+    fn args(&mut self, target: BasicBlock, args: &[Value]) {
+        if !self.func.layout().bbs().contains_key(&target) {
+            return;
+        }
+        let params = self.func.dfg().bb(target).params();
+        if params.len() != args.len() {
+            self.error(format!("target takes {} argument(s) but {} are given", params.len(), args.len()));
+            return;
+        }
+        for (&arg, &param) in args.iter().zip(params) {
+            self.expect("block argument", &self.ty(arg), &self.ty(param));
+        }
+    }
+
+    fn inst(&mut self, inst: Value) {
+        let data = self.func.dfg().value(inst);
+        let i32 = Type::get_i32();
+        match data.kind() {
+            ValueKind::Load(l) => {
+                if let Some(base) = self.pointee("source of 'load'", &self.ty(l.src())) {
+                    self.expect("result of 'load'", data.ty(), &base);
+                }
+            }
+            ValueKind::Store(s) => {
+                if let Some(base) = self.pointee("destination of 'store'", &self.ty(s.dest())) {
+                    self.expect("stored value", &self.ty(s.value()), &base);
+                }
+            }
+            ValueKind::GetPtr(g) => {
+                let src = self.ty(g.src());
+                self.pointee("source of 'getptr'", &src);
+                self.expect("index of 'getptr'", &self.ty(g.index()), &i32);
+                self.expect("result of 'getptr'", data.ty(), &src);
+            }
+            ValueKind::GetElemPtr(g) => {
+                if let Some(base) = self.pointee("source of 'getelemptr'", &self.ty(g.src())) {
+                    match base.kind() {
+                        TypeKind::Array(elem, _) => {
+                            self.expect("result of 'getelemptr'", data.ty(), &Type::get_pointer(elem.clone()))
+                        }
+                        _ => self.error(format!("source of 'getelemptr' points to '{}', expected an array", base)),
+                    }
+                }
+                self.expect("index of 'getelemptr'", &self.ty(g.index()), &i32);
+            }
+            ValueKind::Binary(b) => {
+                self.expect("left operand", &self.ty(b.lhs()), &i32);
+                self.expect("right operand", &self.ty(b.rhs()), &i32);
+                self.expect("result of 'binary'", data.ty(), &i32);
+            }
+            ValueKind::Branch(b) => {
+                self.expect("condition of 'br'", &self.ty(b.cond()), &i32);
+                self.args(b.true_bb(), b.true_args());
+                self.args(b.false_bb(), b.false_args());
+            }
+            ValueKind::Jump(j) => self.args(j.target(), j.args()),
+            ValueKind::Call(c) => {
+                let callee = self.program.func(c.callee());
+                let TypeKind::Function(params, ret) = callee.ty().kind() else {
+                    unreachable!()
+                };
+                if params.len() != c.args().len() {
+                    self.error(format!(
+                        "'{}' takes {} argument(s) but {} are given",
+                        callee.name(),
+                        params.len(),
+                        c.args().len()
+                    ));
+                } else {
+                    for (idx, (&arg, param)) in c.args().iter().zip(params).enumerate() {
+                        self.expect(&format!("argument {} of '{}'", idx + 1, callee.name()), &self.ty(arg), param);
+                    }
+                }
+                self.expect(&format!("result of '{}'", callee.name()), data.ty(), ret);
+            }
+            ValueKind::Return(r) => {
+                let TypeKind::Function(_, ret) = self.func.ty().kind() else {
+                    unreachable!()
+                };
+                match r.value() {
+                    Some(value) => self.expect("returned value", &self.ty(value), ret),
+                    None if !ret.is_unit() => self.error(format!("'ret' without a value in a function returning '{}'", ret)),
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use koopa::ir::{builder_traits::*, FunctionData, Program, Type};
+
+    use super::verify;
+    use crate::front;
+
+    #[test]
+    fn generated() {
+        let source = r#"
+int fib(int n) {
+    if (n < 2) return n;
+    return fib(n - 1) + fib(n - 2);
+}
+
+int main() {
+    long s = 0;
+    int i = 0;
+    while (i < 10) {
+        if (i == 5) break;
+        s = s + fib(i) * 2.5;
+        i = i + 1;
+        continue;
+        i = 0;
+    }
+    return s;
+}
+"#;
+        let ir = front::into_ir(source.to_string());
+        let errors: Vec<_> = verify(&ir).into_iter().filter(|i| i.error).collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn malformed() {
+        let mut program = Program::new();
+        let callee = program.new_func(FunctionData::new("@f".into(), vec![Type::get_i32()], Type::get_i32()));
+        let main = program.new_func(FunctionData::new("@main".into(), vec![], Type::get_i32()));
+        let func = program.func_mut(main);
+        let dfg = func.dfg_mut();
+        let entry = dfg.new_bb().basic_block(Some("%entry".into()));
+        let dead = dfg.new_bb().basic_block(Some("%dead".into()));
+        let call = dfg.new_value().call(callee, vec![]);
+        let ret = dfg.new_value().ret(None);
+        let jump = dfg.new_value().jump(entry);
+        let alloc = dfg.new_value().alloc(Type::get_i32());
+        let load = dfg.new_value().load(alloc);
+        func.layout_mut().bbs_mut().extend([entry, dead]);
+        func.layout_mut().bb_mut(entry).insts_mut().extend([call, ret, jump]);
+        func.layout_mut().bb_mut(dead).insts_mut().extend([load]);
+
+        let issues: Vec<_> = verify(&program).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            issues,
+            [
+                "in function '@main', block '%entry': error: '@f' takes 1 argument(s) but 0 are given",
+                "in function '@main', block '%entry': error: terminator 'ret' is followed by 1 instruction(s)",
+                "in function '@main', block '%entry': error: 'ret' without a value in a function returning 'i32'",
+                "in function '@main', block '%dead': error: block does not end with a terminator",
+                "in function '@main', block '%dead': error: operand 'alloc' of 'load' is not defined",
+                "in function '@main', block '%dead': warning: block is unreachable from the entry",
+            ]
+        );
+    }
+}